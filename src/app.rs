@@ -9,7 +9,46 @@ use std::sync::mpsc;
 use git2::*;
 use ratatui::widgets::ListState;
 
+use crate::blame::Blame;
+use crate::commit_detail::{CommitDetail, SignatureStatus, verify_commit};
 use crate::file_tree::FileTree;
+use crate::graph::{GRAPH_REF_GLOBS, GraphCell, GraphLayout, HistoryWalk, RefLabel, ref_labels};
+use crate::history_filter::HistoryFilter;
+use crate::hooks::{HookEvent, Hooks, cleanup_message};
+use crate::operation::{Operation, OperationKind, conflicted_paths};
+use crate::patch::{HunkLines, partial_patch};
+use crate::rebase::{self, RebaseAction, RebasePlan, RebaseStop, autosquash_base, fixup_target};
+use crate::signing::{SignConfig, SignError};
+
+const STAGED_MASK: Status = Status::INDEX_NEW
+    .union(Status::INDEX_MODIFIED)
+    .union(Status::INDEX_DELETED)
+    .union(Status::INDEX_RENAMED)
+    .union(Status::INDEX_TYPECHANGE);
+
+/// Commits loaded into the graph per page.
+pub const GRAPH_PAGE_SIZE: usize = 200;
+
+/// How close to the end of the loaded history the selection may get before
+/// the next page is loaded.
+const GRAPH_PAGE_MARGIN: usize = 20;
+
+/// How many commits a filtered page looks at before it gives up and waits
+/// for the next scroll, so a rare match cannot stall the UI.
+pub const GRAPH_SCAN_BUDGET: usize = 2000;
+
+/// The same budget for filters that diff every commit they look at.
+pub const GRAPH_DIFF_SCAN_BUDGET: usize = 300;
+
+/// Why continue, skip and abort refuse a rebase fastgit cannot resume.
+const NOT_RESUMABLE: &str =
+    "this rebase was started by git; finish it with git rebase --continue or --abort";
+
+const UNSTAGED_MASK: Status = Status::WT_NEW
+    .union(Status::WT_MODIFIED)
+    .union(Status::WT_DELETED)
+    .union(Status::WT_RENAMED)
+    .union(Status::WT_TYPECHANGE);
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Tab {
@@ -22,6 +61,41 @@ pub enum BranchTab {
     Remote,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphTab {
+    Commits,
+    Stash,
+}
+
+/// What a running hook belongs to, and so what happens when it finishes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookStage {
+    Commit,
+    PostCommit,
+    Push,
+}
+
+#[derive(Debug, Clone)]
+pub struct StashEntry {
+    pub index: usize,
+    pub message: String,
+    pub oid: Oid,
+    pub time: i64,
+}
+
+/// One file touched by the commit open in the diff panel.
+#[derive(Debug, Clone)]
+pub struct CommitFile {
+    /// `A`, `M`, `D`, `R`, `C` or `T`, as in `git show --name-status`.
+    pub status: char,
+    pub path: String,
+    /// The path before a rename or copy.
+    pub old_path: Option<String>,
+    pub additions: usize,
+    pub deletions: usize,
+    pub lines: Vec<DiffLine>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum DiffLineKind {
     Add,
@@ -30,12 +104,99 @@ pub enum DiffLineKind {
     Header,
 }
 
+/// What a pending discard will throw away.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiscardTarget {
+    File(PathBuf),
+    Marked(Vec<PathBuf>),
+    Hunk(PathBuf),
+    Lines(PathBuf),
+}
+
+/// A reset just made from the graph, kept so it can be undone.
+#[derive(Clone)]
+pub struct ResetDone {
+    pub mode: ResetType,
+    pub previous: Oid,
+    pub previous_summary: String,
+    pub target: Oid,
+}
+
+/// Why `App::create_commit` failed.
+#[derive(Debug)]
+pub enum CommitError {
+    Git(Error),
+    /// The commit could not be signed, or signing is misconfigured.
+    Sign(SignError),
+}
+
+impl From<Error> for CommitError {
+    fn from(err: Error) -> Self {
+        CommitError::Git(err)
+    }
+}
+
+impl From<CommitError> for Error {
+    fn from(err: CommitError) -> Self {
+        match err {
+            CommitError::Git(err) => err,
+            CommitError::Sign(err) => Error::from_str(&err.0),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DiffLine {
     pub kind: DiffLineKind,
     pub content: String,
 }
 
+/// A hunk of the working diff, as a range of rows in `diff_content`.
+///
+/// `staged` hunks come from HEAD -> index, the others from index -> workdir.
+/// The line numbers are the ones from the `@@` header and are used to find
+/// the hunk again when the diff is regenerated for staging.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    pub start: usize,
+    pub end: usize,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub staged: bool,
+}
+
+impl Hunk {
+    pub fn contains(&self, row: usize) -> bool {
+        row >= self.start && row < self.end
+    }
+
+    /// Whether `hunk` is this hunk, optionally as seen from a reversed diff.
+    fn matches(&self, hunk: &git2::DiffHunk, reversed: bool) -> bool {
+        let (old_start, old_lines, new_start, new_lines) = if reversed {
+            (
+                self.new_start,
+                self.new_lines,
+                self.old_start,
+                self.old_lines,
+            )
+        } else {
+            (
+                self.old_start,
+                self.old_lines,
+                self.new_start,
+                self.new_lines,
+            )
+        };
+
+        hunk.old_start() == old_start
+            && hunk.old_lines() == old_lines
+            && hunk.new_start() == new_start
+            && hunk.new_lines() == new_lines
+    }
+}
+
 pub struct App {
     pub current_tab: Tab,
     pub cur_dir: String,
@@ -43,14 +204,42 @@ pub struct App {
     pub current_branch: String,
     pub commit_graph: Vec<String>,
     pub commit_graph_oids: Vec<String>,
+    pub commit_graph_rows: Vec<Vec<GraphCell>>,
+    pub commit_graph_labels: HashMap<String, Vec<RefLabel>>,
+    pub commit_graph_head: Option<String>,
+    pub graph_all_refs: bool,
+    pub graph_walk: HistoryWalk,
+    /// Commits walked since the graph was last reloaded.
+    pub graph_scanned: usize,
+    pub graph_layout: GraphLayout,
+    pub graph_refs_key: Vec<String>,
+    pub graph_filter: Option<HistoryFilter>,
+    /// Commits marked in the graph for a batch action such as cherry-pick.
+    pub graph_marked: Vec<String>,
+    pub show_graph_filter_dialog: bool,
+    pub graph_filter_input: String,
+    pub graph_filter_error: Option<String>,
     pub commit_graph_state: ListState,
+    pub graph_tab: GraphTab,
+    pub stashes: Vec<StashEntry>,
+    pub stash_state: ListState,
+    pub show_stash_dialog: bool,
+    pub stash_message: String,
+    pub stash_include_untracked: bool,
+    pub stash_keep_index: bool,
+    pub stash_drop_confirm: bool,
+    pub stash_error: Option<String>,
     pub tree: FileTree,
     pub file_statuses: HashMap<PathBuf, Status>,
     pub branches: Vec<String>,
     pub branch_state: ListState,
     pub selected_file: Option<PathBuf>,
     pub diff_content: Vec<DiffLine>,
+    pub diff_hunks: Vec<Hunk>,
     pub diff_scroll: usize,
+    pub diff_cursor: usize,
+    pub diff_view_height: usize,
+    pub diff_select_anchor: Option<usize>,
     pub focused: bool,
     pub window_index: u32,
     pub show_commit_dialog: bool,
@@ -61,6 +250,27 @@ pub struct App {
     pub commit_description_cursor: usize,
     pub commit_summary_scroll: usize,
     pub commit_description_scroll: usize,
+    pub commit_amend: bool,
+    pub commit_amend_pushed: Option<String>,
+    /// Commit as this commit's author instead of the user, when a rebase
+    /// rewords it.
+    pub commit_author_from: Option<Oid>,
+    /// A `fixup!` or `squash!` commit being written for a graph commit.
+    pub commit_fixup: Option<(RebaseAction, Oid)>,
+    /// The older commit whose message the dialog is rewriting.
+    pub commit_reword: Option<Oid>,
+    /// A reword of a published commit, waiting for the user to confirm,
+    /// with the remote-tracking branch that has it.
+    pub reword_confirm: Option<(Oid, String)>,
+    pub commit_skip_hooks: bool,
+    pub show_hook_dialog: bool,
+    pub hook_title: Option<String>,
+    pub hook_output: Vec<String>,
+    pub hook_error: Option<String>,
+    pub hook_stage: Option<HookStage>,
+    pub hook_rx: Option<mpsc::Receiver<HookEvent>>,
+    pub commit_sign_error: Option<String>,
+    pub commit_error: Option<(ErrorClass, String)>,
     pub commit_warning_open: bool,
     pub commit_success_open: bool,
     pub staged_count: u32,
@@ -82,9 +292,55 @@ pub struct App {
     pub show_new_branch_dialog: bool,
     pub new_branch_name: String,
     pub checkout_error: Option<String>,
+    pub blame: Option<Blame>,
+    pub blame_state: ListState,
+    /// Earlier blames, with their selected line, to step back to after
+    /// re-blaming at a parent.
+    pub blame_stack: Vec<(Blame, usize)>,
+    pub blame_error: Option<String>,
+    pub operation: Option<Operation>,
+    pub operation_error: Option<String>,
+    /// A merge waiting for the user to pick which parent to revert
+    /// against, with a label for each parent.
+    pub revert_mainline: Option<(Oid, Vec<String>)>,
+    /// The interactive rebase being planned, before it starts.
+    pub rebase_plan: Option<RebasePlan>,
+    pub rebase_plan_state: ListState,
+    pub rebase_plan_error: Option<String>,
+    /// The action for each commit of the running rebase; commits not
+    /// listed, as in a rebase started elsewhere, are picked.
+    pub rebase_actions: HashMap<Oid, RebaseAction>,
+    pub rebase_stop: Option<RebaseStop>,
+    /// The commit the reset dialog is choosing a mode for.
+    pub reset_target: Option<Oid>,
+    /// Uncommitted changes a confirmed hard reset will throw away.
+    pub reset_hard_confirm: Option<Vec<String>>,
+    pub reset_done: Option<ResetDone>,
+    pub reset_error: Option<String>,
+    /// `.gitignore` patterns waiting for confirmation before they are added.
+    pub ignore_confirm: Option<Vec<String>>,
+    pub discard_confirm: Option<DiscardTarget>,
+    pub discard_delete_confirm: bool,
+    pub discard_error: Option<String>,
     pub checkout_success: Option<String>,
     pub show_help: bool,
     pub commit_diff_label: Option<String>,
+    /// Metadata shown above the diff while a graph commit is open.
+    pub commit_detail: Option<CommitDetail>,
+    /// `git verify-commit` results by commit id, so browsing back to a
+    /// commit does not run gpg again.
+    pub signature_cache: HashMap<String, SignatureStatus>,
+    /// Requests to the signature worker: (workdir, oid, kind).
+    pub signature_tx: Option<mpsc::Sender<(String, String, String)>>,
+    pub signature_rx: Option<mpsc::Receiver<(String, SignatureStatus)>>,
+    pub commit_files: Vec<CommitFile>,
+    pub commit_file_state: ListState,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl App {
@@ -96,14 +352,40 @@ impl App {
             current_branch: "-".to_string(),
             commit_graph: vec![],
             commit_graph_oids: vec![],
+            commit_graph_rows: vec![],
+            commit_graph_labels: HashMap::new(),
+            commit_graph_head: None,
+            graph_all_refs: false,
+            graph_walk: HistoryWalk::default(),
+            graph_scanned: 0,
+            graph_layout: GraphLayout::new(),
+            graph_refs_key: vec![],
+            graph_filter: None,
+            graph_marked: Vec::new(),
+            show_graph_filter_dialog: false,
+            graph_filter_input: String::new(),
+            graph_filter_error: None,
             commit_graph_state: ListState::default(),
+            graph_tab: GraphTab::Commits,
+            stashes: vec![],
+            stash_state: ListState::default(),
+            show_stash_dialog: false,
+            stash_message: String::new(),
+            stash_include_untracked: false,
+            stash_keep_index: false,
+            stash_drop_confirm: false,
+            stash_error: None,
             tree: FileTree::new(std::path::PathBuf::from(".")),
             file_statuses: HashMap::new(),
             branches: vec![],
             branch_state: ListState::default(),
             selected_file: None,
             diff_content: vec![],
+            diff_hunks: vec![],
             diff_scroll: 0,
+            diff_cursor: 0,
+            diff_view_height: 0,
+            diff_select_anchor: None,
             focused: false,
 
             // 0 => Tree
@@ -118,6 +400,21 @@ impl App {
             commit_description_cursor: 0,
             commit_summary_scroll: 0,
             commit_description_scroll: 0,
+            commit_amend: false,
+            commit_amend_pushed: None,
+            commit_author_from: None,
+            commit_fixup: None,
+            commit_reword: None,
+            reword_confirm: None,
+            commit_skip_hooks: false,
+            show_hook_dialog: false,
+            hook_title: None,
+            hook_output: vec![],
+            hook_error: None,
+            hook_stage: None,
+            hook_rx: None,
+            commit_sign_error: None,
+            commit_error: None,
             commit_warning_open: false,
             commit_success_open: false,
             staged_count: 0,
@@ -139,9 +436,35 @@ impl App {
             show_new_branch_dialog: false,
             new_branch_name: String::new(),
             checkout_error: None,
+            blame: None,
+            blame_state: ListState::default(),
+            blame_stack: Vec::new(),
+            blame_error: None,
+            operation: None,
+            operation_error: None,
+            revert_mainline: None,
+            rebase_plan: None,
+            rebase_plan_state: ListState::default(),
+            rebase_plan_error: None,
+            rebase_actions: HashMap::new(),
+            rebase_stop: None,
+            reset_target: None,
+            reset_hard_confirm: None,
+            reset_done: None,
+            reset_error: None,
+            ignore_confirm: None,
+            discard_confirm: None,
+            discard_delete_confirm: false,
+            discard_error: None,
             checkout_success: None,
             show_help: false,
             commit_diff_label: None,
+            commit_detail: None,
+            signature_cache: HashMap::new(),
+            signature_tx: None,
+            signature_rx: None,
+            commit_files: Vec::new(),
+            commit_file_state: ListState::default(),
         };
         app_new.get_path();
         app_new.scan_git();
@@ -161,6 +484,7 @@ impl App {
             self.tree.update_items();
             self.selected_file = None;
             self.diff_content.clear();
+            self.diff_hunks.clear();
             self.branch_state.select(None);
             self.remote_state.select(None);
             self.push_remote_override = None;
@@ -186,13 +510,7 @@ impl App {
                         paths.push(path);
                     }
 
-                    let staged_mask = Status::INDEX_NEW
-                        | Status::INDEX_MODIFIED
-                        | Status::INDEX_DELETED
-                        | Status::INDEX_RENAMED
-                        | Status::INDEX_TYPECHANGE;
-
-                    if status.intersects(staged_mask) {
+                    if status.intersects(STAGED_MASK) {
                         self.staged_count += 1;
                     }
                 }
@@ -252,7 +570,10 @@ impl App {
             } else {
                 self.selected_file = None;
                 self.diff_content.clear();
+                self.diff_hunks.clear();
                 self.commit_diff_label = None;
+                self.commit_detail = None;
+                self.commit_files.clear();
             }
         }
     }
@@ -265,13 +586,18 @@ impl App {
             let file_path = path.strip_prefix(".").unwrap_or(path).to_path_buf();
             self.selected_file = Some(file_path);
             self.diff_scroll = 0;
+            self.diff_cursor = 0;
+            self.diff_select_anchor = None;
             self.load_diff();
         }
     }
 
     pub fn load_diff(&mut self) {
         self.diff_content.clear();
+        self.diff_hunks.clear();
         self.commit_diff_label = None;
+        self.commit_detail = None;
+        self.commit_files.clear();
 
         let file_path = match &self.selected_file {
             Some(p) => p.clone(),
@@ -292,479 +618,3209 @@ impl App {
             None => return,
         };
 
-        if let Some(&status) = self.file_statuses.get(&file_path) {
-            if status.contains(Status::WT_NEW) && !status.contains(Status::INDEX_NEW) {
-                let full_path = Path::new(&self.cur_dir).join(&file_path);
-                if let Ok(content) = std::fs::read_to_string(&full_path) {
-                    let lines: Vec<DiffLine> = content
-                        .lines()
-                        .map(|line| DiffLine {
-                            kind: DiffLineKind::Add,
-                            content: line.to_string(),
-                        })
-                        .collect();
-                    self.diff_content = lines;
-                }
-                return;
-            }
-        }
+        let mut opts = Self::file_diff_options(&file_str);
+        let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
 
-        let mut opts = DiffOptions::new();
-        opts.pathspec(&file_str);
-        opts.context_lines(3);
+        let mut lines: Vec<DiffLine> = Vec::new();
+        let mut hunks: Vec<Hunk> = Vec::new();
 
-        let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+        if let Ok(diff) = repo.diff_index_to_workdir(None, Some(&mut opts)) {
+            Self::collect_diff(&diff, "Unstaged changes", false, &mut lines, &mut hunks);
+        }
 
-        let diff = repo.diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut opts));
+        if let Ok(diff) = repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut opts)) {
+            Self::collect_diff(&diff, "Staged changes", true, &mut lines, &mut hunks);
+        }
 
-        if let Ok(diff) = diff {
-            let mut lines: Vec<DiffLine> = Vec::new();
+        self.diff_content = lines;
+        self.diff_hunks = hunks;
+        self.clamp_diff_cursor();
+    }
 
-            let _ = diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
-                let content = String::from_utf8_lossy(line.content()).to_string();
-                let kind = match line.origin() {
-                    '+' => DiffLineKind::Add,
-                    '-' => DiffLineKind::Delete,
-                    'H' | 'F' => DiffLineKind::Header,
-                    _ => DiffLineKind::Context,
-                };
+    fn file_diff_options(path: &str) -> DiffOptions {
+        let mut opts = DiffOptions::new();
+        opts.pathspec(path);
+        opts.disable_pathspec_match(true);
+        opts.context_lines(3);
+        opts.include_untracked(true);
+        opts.recurse_untracked_dirs(true);
+        opts.show_untracked_content(true);
+        opts
+    }
 
-                let kind = if content.starts_with("@@") {
-                    DiffLineKind::Header
-                } else {
-                    kind
-                };
+    fn diff_line(line: &git2::DiffLine) -> DiffLine {
+        let content = String::from_utf8_lossy(line.content()).to_string();
+        let kind = match line.origin() {
+            '+' => DiffLineKind::Add,
+            '-' => DiffLineKind::Delete,
+            'H' | 'F' => DiffLineKind::Header,
+            _ => DiffLineKind::Context,
+        };
 
-                lines.push(DiffLine {
-                    kind,
-                    content: content.trim_end_matches('\n').to_string(),
-                });
-                true
-            });
+        let kind = if content.starts_with("@@") {
+            DiffLineKind::Header
+        } else {
+            kind
+        };
 
-            self.diff_content = lines;
+        DiffLine {
+            kind,
+            content: content.trim_end_matches('\n').to_string(),
         }
     }
 
-    /// Toggle the staged status of a file.
-    /// If the file is currently staged, it will be unstaged,
-    /// and vice versa.
-    pub fn toggle_stage(&mut self, path: &Path) -> Result<(), Error> {
-        let repo = Repository::open(&self.cur_dir)?;
-        let mut index = repo.index()?;
-        let staged_mask = Status::INDEX_NEW
-            | Status::INDEX_MODIFIED
-            | Status::INDEX_DELETED
-            | Status::INDEX_RENAMED
-            | Status::INDEX_TYPECHANGE;
-        let status = repo.status_file(path).unwrap_or(Status::WT_NEW);
-
-        if status.intersects(staged_mask) {
-            if let Ok(head) = repo.head().and_then(|h| h.peel(ObjectType::Any)) {
-                if let Some(path_str) = path.to_str() {
-                    repo.reset_default(Some(&head), Some(path_str))?;
-                } else {
-                    index.remove_path(path)?;
+    /// Split a commit's diff into its files, with line counts and the
+    /// patch of each one.
+    fn commit_files(diff: &Diff) -> Vec<CommitFile> {
+        let mut files = Vec::new();
+        for (idx, delta) in diff.deltas().enumerate() {
+            let status = match delta.status() {
+                Delta::Added => 'A',
+                Delta::Deleted => 'D',
+                Delta::Renamed => 'R',
+                Delta::Copied => 'C',
+                Delta::Typechange => 'T',
+                _ => 'M',
+            };
+            let path_of = |file: DiffFile| file.path().map(|p| p.display().to_string());
+            let new_path = path_of(delta.new_file());
+            let old_path = path_of(delta.old_file());
+            let path = new_path.clone().or(old_path.clone()).unwrap_or_default();
+            let old_path = old_path.filter(|_| matches!(status, 'R' | 'C'));
+
+            let mut lines = Vec::new();
+            let (mut additions, mut deletions) = (0, 0);
+            if let Ok(Some(mut patch)) = Patch::from_diff(diff, idx) {
+                if let Ok((_, add, del)) = patch.line_stats() {
+                    additions = add;
+                    deletions = del;
                 }
-            } else {
-                index.remove_path(path)?;
+                let _ = patch.print(&mut |_delta, _hunk, line| {
+                    lines.push(Self::diff_line(&line));
+                    true
+                });
             }
-        } else {
-            index.add_path(path)?;
-            self.staged_count += 1;
-        }
 
-        index.write()?;
-
-        let updated_status = repo.status_file(path).unwrap_or(Status::CURRENT);
-        let key = path.to_path_buf();
-        if updated_status == Status::CURRENT {
-            self.file_statuses.remove(&key);
-        } else {
-            self.file_statuses.insert(key, updated_status);
+            files.push(CommitFile {
+                status,
+                path,
+                old_path,
+                additions,
+                deletions,
+                lines,
+            });
         }
+        files
+    }
 
-        Ok(())
+    fn diff_lines(diff: &Diff) -> Vec<DiffLine> {
+        let mut lines: Vec<DiffLine> = Vec::new();
+        let _ = diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
+            lines.push(Self::diff_line(&line));
+            true
+        });
+        lines
     }
 
-    pub fn diff_scroll_down(&mut self) {
-        if self.diff_scroll < self.diff_content.len().saturating_sub(1) {
-            self.diff_scroll += 1;
+    /// Append `diff` under a `title` row, recording where each hunk lands.
+    fn collect_diff(
+        diff: &Diff,
+        title: &str,
+        staged: bool,
+        lines: &mut Vec<DiffLine>,
+        hunks: &mut Vec<Hunk>,
+    ) {
+        if diff.deltas().len() == 0 {
+            return;
         }
-    }
 
-    pub fn diff_scroll_up(&mut self) {
-        self.diff_scroll = self.diff_scroll.saturating_sub(1);
+        lines.push(DiffLine {
+            kind: DiffLineKind::Header,
+            content: title.to_string(),
+        });
+
+        let _ = diff.print(DiffFormat::Patch, |_delta, hunk, line| {
+            match hunk {
+                Some(h) if line.origin() == 'H' => hunks.push(Hunk {
+                    start: lines.len(),
+                    end: lines.len() + 1,
+                    old_start: h.old_start(),
+                    old_lines: h.old_lines(),
+                    new_start: h.new_start(),
+                    new_lines: h.new_lines(),
+                    staged,
+                }),
+                Some(_) => {
+                    if let Some(last) = hunks.last_mut() {
+                        last.end = lines.len() + 1;
+                    }
+                }
+                None => {}
+            }
+
+            lines.push(Self::diff_line(&line));
+            true
+        });
     }
 
-    pub fn branch_next(&mut self) {
-        if self.branches.is_empty() {
-            self.branch_state.select(None);
-            return;
-        }
+    /// The hunk the diff cursor is on, if any.
+    pub fn hunk_at_cursor(&self) -> Option<&Hunk> {
+        self.diff_hunks
+            .iter()
+            .find(|h| h.contains(self.diff_cursor))
+    }
 
-        let next = match self.branch_state.selected() {
-            Some(index) if index + 1 < self.branches.len() => index + 1,
-            _ => 0,
+    /// Stage the hunk under the diff cursor, or unstage it if it is
+    /// already in the index. Only the selected file's diff is touched.
+    pub fn toggle_stage_hunk(&mut self) -> Result<(), Error> {
+        let path = match &self.selected_file {
+            Some(p) => p.clone(),
+            None => return Ok(()),
+        };
+        let hunk = match self.hunk_at_cursor() {
+            Some(h) => h.clone(),
+            None => return Ok(()),
+        };
+        let path_str = match path.to_str() {
+            Some(s) => s.to_string(),
+            None => return Ok(()),
         };
 
-        self.branch_state.select(Some(next));
-    }
+        let repo = Repository::open(&self.cur_dir)?;
 
-    pub fn branch_previous(&mut self) {
-        if self.branches.is_empty() {
-            self.branch_state.select(None);
-            return;
+        // An untracked file is one hunk with nothing in the index to patch.
+        let status = repo.status_file(&path).unwrap_or(Status::CURRENT);
+        if !hunk.staged && status.contains(Status::WT_NEW) {
+            let mut index = repo.index()?;
+            index.add_path(&path)?;
+            index.write()?;
+            self.update_file_status(&repo, &path);
+            self.load_diff();
+            return Ok(());
         }
 
-        let prev = match self.branch_state.selected() {
-            Some(0) | None => self.branches.len() - 1,
-            Some(index) => index - 1,
+        let mut opts = Self::file_diff_options(&path_str);
+
+        // Unstaging applies the HEAD -> index hunk in reverse.
+        let diff = if hunk.staged {
+            opts.reverse(true);
+            let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+            repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut opts))?
+        } else {
+            repo.diff_index_to_workdir(None, Some(&mut opts))?
         };
 
-        self.branch_state.select(Some(prev));
+        let mut apply_opts = ApplyOptions::new();
+        apply_opts.hunk_callback(|h| h.is_some_and(|h| hunk.matches(&h, hunk.staged)));
+        repo.apply(&diff, ApplyLocation::Index, Some(&mut apply_opts))?;
+
+        self.update_file_status(&repo, &path);
+        self.load_diff();
+
+        Ok(())
     }
 
-    pub fn commit_graph_next(&mut self) {
-        if self.commit_graph.is_empty() {
-            return;
+    /// Start a line selection at the diff cursor, or drop the current one.
+    pub fn toggle_diff_selection(&mut self) {
+        if self.diff_select_anchor.is_some() {
+            self.diff_select_anchor = None;
+        } else if self.hunk_at_cursor().is_some() {
+            self.diff_select_anchor = Some(self.diff_cursor);
         }
-        let next = match self.commit_graph_state.selected() {
-            Some(i) if i + 1 < self.commit_graph.len() => i + 1,
-            _ => 0,
-        };
-        self.commit_graph_state.select(Some(next));
-        self.load_commit_diff(next);
     }
 
-    pub fn commit_graph_previous(&mut self) {
-        if self.commit_graph.is_empty() {
-            return;
-        }
-        let prev = match self.commit_graph_state.selected() {
-            Some(0) | None => self.commit_graph.len() - 1,
-            Some(i) => i - 1,
+    /// Whether `row` is a `+`/`-` line inside the active line selection.
+    ///
+    /// The selection only covers hunks on the same side (staged or
+    /// unstaged) as the one under the cursor.
+    pub fn is_diff_row_selected(&self, row: usize) -> bool {
+        let Some(anchor) = self.diff_select_anchor else {
+            return false;
+        };
+        let Some(cursor_hunk) = self.hunk_at_cursor() else {
+            return false;
         };
-        self.commit_graph_state.select(Some(prev));
-        self.load_commit_diff(prev);
-    }
 
-    pub fn increase_window(&mut self) {
-        if self.window_index == 3 {
-            return self.window_index = 0;
+        let (lo, hi) = (anchor.min(self.diff_cursor), anchor.max(self.diff_cursor));
+        if row < lo || row > hi {
+            return false;
         }
-        self.window_index += 1;
-    }
 
-    pub fn get_path(&mut self) {
-        if let Ok(ok) = current_dir() {
-            self.cur_dir = ok.display().to_string();
-        }
-    }
+        let changed = self
+            .diff_content
+            .get(row)
+            .is_some_and(|l| matches!(l.kind, DiffLineKind::Add | DiffLineKind::Delete));
 
-    pub fn scan_git(&mut self) {
-        let dir = format!("{}/.git", self.cur_dir);
-        if Path::new(dir.as_str()).is_dir() {
-            self.has_git = true;
-            self.refresh_current_branch();
-            self.refresh_commit_graph();
-            return;
-        }
-        self.has_git = false;
-        self.current_branch = "-".to_string();
-        self.commit_graph.clear();
-        self.commit_graph_oids.clear();
-        self.commit_graph_state.select(None);
+        changed
+            && self
+                .diff_hunks
+                .iter()
+                .any(|h| h.staged == cursor_hunk.staged && h.contains(row))
     }
 
-    pub fn refresh_current_branch(&mut self) {
-        if !self.has_git {
-            self.current_branch = "-".to_string();
-            return;
-        }
+    /// Stage the selected lines, or unstage them when the selection is in
+    /// the staged section. The rest of each hunk is left untouched.
+    pub fn toggle_stage_lines(&mut self) -> Result<(), Error> {
+        let path = match &self.selected_file {
+            Some(p) => p.clone(),
+            None => return Ok(()),
+        };
+        let path_str = match path.to_str() {
+            Some(s) => s.to_string(),
+            None => return Ok(()),
+        };
+        let staged = match self.hunk_at_cursor() {
+            Some(h) => h.staged,
+            None => return Ok(()),
+        };
 
-        let branch = if let Ok(repo) = Repository::open(&self.cur_dir) {
-            if let Ok(head) = repo.head() {
-                head.shorthand().map(|name| name.to_string())
-            } else {
-                None
-            }
+        let repo = Repository::open(&self.cur_dir)?;
+        let status = repo.status_file(&path).unwrap_or(Status::CURRENT);
+        let new_file = !staged && status.contains(Status::WT_NEW);
+
+        let mut opts = Self::file_diff_options(&path_str);
+        let diff = if staged {
+            let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+            repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut opts))?
         } else {
-            None
+            repo.diff_index_to_workdir(None, Some(&mut opts))?
         };
 
-        self.current_branch = branch.unwrap_or_else(|| "detached".to_string());
-    }
+        // The work tree diff has the untracked file's mode, exec bit included.
+        let new_file = diff
+            .deltas()
+            .next()
+            .map(|delta| delta.new_file().mode())
+            .filter(|_| new_file);
+        let hunks = self.selected_hunk_lines(&diff, staged)?;
+        let Some(text) = partial_patch(&path_str, new_file, &hunks, staged) else {
+            return Ok(());
+        };
 
-    pub fn refresh_commit_graph(&mut self) {
-        if !self.has_git {
-            self.commit_graph.clear();
-            self.commit_graph_oids.clear();
-            self.commit_graph_state.select(None);
-            self.commit_diff_label = None;
-            return;
-        }
+        let patch = Diff::from_buffer(text.as_bytes())?;
+        repo.apply(&patch, ApplyLocation::Index, None)?;
 
-        let was_showing_commit_diff = self.commit_diff_label.is_some() && self.selected_file.is_none();
-        let prev_selected_index = self.commit_graph_state.selected();
-        let prev_selected_oid = prev_selected_index
-            .and_then(|idx| self.commit_graph_oids.get(idx))
-            .cloned();
+        self.diff_select_anchor = None;
+        self.update_file_status(&repo, &path);
+        self.load_diff();
 
-        let mut lines = Vec::new();
-        let mut oids = Vec::new();
+        Ok(())
+    }
 
-        if let Ok(repo) = Repository::open(&self.cur_dir)
-            && let Ok(mut revwalk) = repo.revwalk()
-            && revwalk.push_head().is_ok()
-        {
-            let _ = revwalk.set_sorting(Sort::TIME);
-
-            for oid in revwalk.flatten() {
-                if let Ok(commit) = repo.find_commit(oid) {
-                    let short_id = commit.id().to_string().chars().take(7).collect::<String>();
-                    let message = commit.summary().unwrap_or("(no message)");
-                    lines.push(format!("* {} {}", short_id, message));
-                    oids.push(commit.id().to_string());
-                }
-            }
-        }
+    /// Pair every hunk of `diff` that shows up in `diff_hunks` with the
+    /// selection mask of its rows.
+    fn selected_hunk_lines(&self, diff: &Diff, staged: bool) -> Result<Vec<HunkLines>, Error> {
+        let mut hunks = Vec::new();
+        let Some(patch) = Patch::from_diff(diff, 0)? else {
+            return Ok(hunks);
+        };
 
-        self.commit_graph = lines;
-        self.commit_graph_oids = oids;
+        for hunk_idx in 0..patch.num_hunks() {
+            let (git_hunk, line_count) = patch.hunk(hunk_idx)?;
+            let Some(view) = self
+                .diff_hunks
+                .iter()
+                .find(|h| h.staged == staged && h.matches(&git_hunk, false))
+            else {
+                continue;
+            };
 
-        if self.commit_graph.is_empty() {
-            self.commit_graph_state.select(None);
-            if was_showing_commit_diff {
-                self.diff_content.clear();
-                self.commit_diff_label = None;
+            let mut lines = Vec::with_capacity(line_count);
+            let mut selected = Vec::with_capacity(line_count);
+            for line_idx in 0..line_count {
+                let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+                lines.push((
+                    line.origin(),
+                    String::from_utf8_lossy(line.content()).to_string(),
+                ));
+                // Row 0 of a hunk is its `@@` header.
+                selected.push(self.is_diff_row_selected(view.start + 1 + line_idx));
             }
-            return;
+
+            hunks.push(HunkLines {
+                old_start: git_hunk.old_start(),
+                new_start: git_hunk.new_start(),
+                lines,
+                selected,
+            });
         }
 
-        let selected_idx = if let Some(oid) = prev_selected_oid {
-            self.commit_graph_oids.iter().position(|o| o == &oid).unwrap_or(0)
+        Ok(hunks)
+    }
+
+    /// Re-read the status of `path` and keep `staged_count` in sync.
+    fn update_file_status(&mut self, repo: &Repository, path: &Path) {
+        let updated_status = repo.status_file(path).unwrap_or(Status::CURRENT);
+        let key = path.to_path_buf();
+        if updated_status == Status::CURRENT {
+            self.file_statuses.remove(&key);
         } else {
-            prev_selected_index
-                .map(|idx| idx.min(self.commit_graph.len() - 1))
-                .unwrap_or(0)
-        };
+            self.file_statuses.insert(key, updated_status);
+        }
 
-        self.commit_graph_state.select(Some(selected_idx));
+        self.staged_count = self
+            .file_statuses
+            .values()
+            .filter(|st| st.intersects(STAGED_MASK))
+            .count() as u32;
+    }
+
+    /// Toggle the staged status of a file.
+    /// If the file is currently staged, it will be unstaged,
+    /// and vice versa.
+    pub fn toggle_stage(&mut self, path: &Path) -> Result<(), Error> {
+        let repo = Repository::open(&self.cur_dir)?;
+        let status = repo.status_file(path).unwrap_or(Status::WT_NEW);
+        drop(repo);
+
+        self.set_staged(&[path.to_path_buf()], !status.intersects(STAGED_MASK))
+    }
+
+    /// Stage every changed path under `dir`, or unstage them all when
+    /// nothing under it is left to stage.
+    pub fn toggle_stage_dir(&mut self, dir: &Path) -> Result<(), Error> {
+        let under_dir: Vec<(PathBuf, Status)> = self
+            .file_statuses
+            .iter()
+            .filter(|(p, _)| p.starts_with(dir))
+            .map(|(p, st)| (p.clone(), *st))
+            .collect();
+
+        let stage = under_dir.iter().any(|(_, st)| st.intersects(UNSTAGED_MASK));
+        let mask = if stage { UNSTAGED_MASK } else { STAGED_MASK };
+        let paths: Vec<PathBuf> = under_dir
+            .into_iter()
+            .filter(|(_, st)| st.intersects(mask))
+            .map(|(p, _)| p)
+            .collect();
+
+        self.set_staged(&paths, stage)
+    }
+
+    pub fn stage_all(&mut self) -> Result<(), Error> {
+        let paths = self.paths_with_status(UNSTAGED_MASK);
+        self.set_staged(&paths, true)
+    }
+
+    pub fn unstage_all(&mut self) -> Result<(), Error> {
+        let paths = self.paths_with_status(STAGED_MASK);
+        self.set_staged(&paths, false)
+    }
+
+    fn paths_with_status(&self, mask: Status) -> Vec<PathBuf> {
+        self.file_statuses
+            .iter()
+            .filter(|(_, st)| st.intersects(mask))
+            .map(|(p, _)| p.clone())
+            .collect()
+    }
+
+    /// Stage or unstage `paths` with a single index write.
+    pub fn set_staged(&mut self, paths: &[PathBuf], stage: bool) -> Result<(), Error> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let repo = Repository::open(&self.cur_dir)?;
+        let mut index = repo.index()?;
+
+        if stage {
+            for path in paths {
+                if Path::new(&self.cur_dir).join(path).exists() {
+                    index.add_path(path)?;
+                } else {
+                    index.remove_path(path)?;
+                }
+            }
+            index.write()?;
+        } else if let Ok(head) = repo.head().and_then(|h| h.peel(ObjectType::Any)) {
+            repo.reset_default(Some(&head), paths)?;
+        } else {
+            for path in paths {
+                index.remove_path(path)?;
+            }
+            index.write()?;
+        }
+
+        for path in paths {
+            self.update_file_status(&repo, path);
+        }
+
+        if self.selected_file.is_some() && self.commit_diff_label.is_none() {
+            self.load_diff();
+        }
+
+        Ok(())
+    }
+
+    /// Changed paths covered by the tree marks; a marked directory covers
+    /// every changed file under it.
+    pub fn marked_paths(&self) -> Vec<PathBuf> {
+        let marks: Vec<PathBuf> = self
+            .tree
+            .marked
+            .iter()
+            .map(|p| p.strip_prefix(".").unwrap_or(p).to_path_buf())
+            .collect();
+
+        let mut paths: Vec<PathBuf> = self
+            .file_statuses
+            .keys()
+            .filter(|p| marks.iter().any(|m| p.starts_with(m)))
+            .cloned()
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    /// Stage every marked path, or unstage them all when all of them are
+    /// already fully staged.
+    pub fn toggle_stage_marked(&mut self) -> Result<(), Error> {
+        let paths = self.marked_paths();
+        let stage = paths.iter().any(|p| {
+            self.file_statuses
+                .get(p)
+                .is_some_and(|st| st.intersects(UNSTAGED_MASK))
+        });
+
+        self.set_staged(&paths, stage)?;
+        self.tree.clear_marks();
+        Ok(())
+    }
+
+    /// The marked paths, or the selected file when nothing is marked.
+    fn ignore_paths(&self) -> Vec<PathBuf> {
+        if self.tree.marked.is_empty() {
+            self.selected_file.iter().cloned().collect()
+        } else {
+            self.marked_paths()
+        }
+    }
+
+    fn ignore_pattern(path: &Path) -> String {
+        format!("/{}", path.to_string_lossy().replace('\\', "/"))
+    }
+
+    /// Ask before writing to `.gitignore`, showing the patterns to add.
+    pub fn open_ignore_dialog(&mut self) {
+        let patterns: Vec<String> = self
+            .ignore_paths()
+            .iter()
+            .map(|p| Self::ignore_pattern(p))
+            .collect();
+        if !patterns.is_empty() {
+            self.ignore_confirm = Some(patterns);
+        }
+    }
+
+    pub fn cancel_ignore(&mut self) {
+        self.ignore_confirm = None;
+    }
+
+    pub fn confirm_ignore(&mut self) -> Result<(), Error> {
+        if self.ignore_confirm.take().is_none() {
+            return Ok(());
+        }
+        self.ignore_selected()
+    }
+
+    /// Append the marked paths, or the selected file when nothing is
+    /// marked, to the repository's top-level `.gitignore`.
+    pub fn ignore_selected(&mut self) -> Result<(), Error> {
+        let paths = self.ignore_paths();
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let gitignore = Path::new(&self.cur_dir).join(".gitignore");
+        let mut content = std::fs::read_to_string(&gitignore).unwrap_or_default();
+        let existing: Vec<String> = content.lines().map(str::to_string).collect();
+
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        for path in &paths {
+            let pattern = Self::ignore_pattern(path);
+            if !existing.contains(&pattern) {
+                content.push_str(&pattern);
+                content.push('\n');
+            }
+        }
+
+        std::fs::write(&gitignore, content).map_err(|e| Error::from_str(&e.to_string()))?;
+        self.tree.clear_marks();
+        self.refresh_repository_view();
+        Ok(())
+    }
+
+    /// The directory highlighted in the tree, relative to the repo root.
+    pub fn selected_tree_dir(&self) -> Option<PathBuf> {
+        let i = self.tree.state.selected()?;
+        let (path, _, is_dir) = self.tree.items.get(i)?;
+        if !is_dir {
+            return None;
+        }
+        Some(path.strip_prefix(".").unwrap_or(path).to_path_buf())
+    }
+
+    /// Ask for confirmation before discarding worktree changes.
+    ///
+    /// In the diff panel this targets the selected lines or the unstaged
+    /// hunk under the cursor; elsewhere it targets the selected file.
+    pub fn open_discard_dialog(&mut self) {
+        if !self.focused && !self.tree.marked.is_empty() {
+            let paths = self.marked_paths();
+            if !paths.is_empty() {
+                self.discard_confirm = Some(DiscardTarget::Marked(paths));
+                self.discard_delete_confirm = false;
+            }
+            return;
+        }
+
+        let Some(path) = self.selected_file.clone() else {
+            return;
+        };
+
+        let target = if self.focused && self.commit_diff_label.is_none() {
+            match self.hunk_at_cursor() {
+                Some(h) if h.staged => return,
+                Some(_) if self.diff_select_anchor.is_some() => DiscardTarget::Lines(path),
+                Some(_) => DiscardTarget::Hunk(path),
+                None => return,
+            }
+        } else {
+            let on_file = self
+                .tree
+                .state
+                .selected()
+                .and_then(|i| self.tree.items.get(i))
+                .is_some_and(|(_, _, is_dir)| !is_dir);
+            if !on_file {
+                return;
+            }
+            DiscardTarget::File(path)
+        };
+
+        self.discard_confirm = Some(target);
+        self.discard_delete_confirm = false;
+    }
+
+    pub fn cancel_discard(&mut self) {
+        self.discard_confirm = None;
+        self.discard_delete_confirm = false;
+    }
+
+    /// Whether discarding `path` removes the file from disk, i.e. it has
+    /// no version in the index or HEAD to go back to.
+    fn discard_deletes_file(&self, path: &Path) -> bool {
+        let status = self
+            .file_statuses
+            .get(path)
+            .copied()
+            .unwrap_or(Status::CURRENT);
+        let wt_changes = Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_TYPECHANGE;
+
+        status.contains(Status::WT_NEW)
+            || (status.contains(Status::INDEX_NEW) && !status.intersects(wt_changes))
+    }
+
+    /// Handle the confirm key of the discard dialog. Deleting a file that
+    /// git cannot restore needs a second confirmation.
+    pub fn confirm_discard(&mut self) {
+        let Some(target) = self.discard_confirm.clone() else {
+            return;
+        };
+
+        let deletes = match &target {
+            DiscardTarget::File(p) | DiscardTarget::Hunk(p) => self.discard_deletes_file(p),
+            DiscardTarget::Marked(paths) => paths.iter().any(|p| self.discard_deletes_file(p)),
+            DiscardTarget::Lines(_) => false,
+        };
+        if deletes && !self.discard_delete_confirm {
+            self.discard_delete_confirm = true;
+            return;
+        }
+
+        self.cancel_discard();
+
+        let result = match &target {
+            DiscardTarget::File(p) => self.discard_file(p),
+            DiscardTarget::Marked(paths) => {
+                self.tree.clear_marks();
+                paths.iter().try_for_each(|p| self.discard_file(p))
+            }
+            DiscardTarget::Hunk(p) if deletes => self.discard_file(p),
+            DiscardTarget::Hunk(_) => self.discard_hunk(),
+            DiscardTarget::Lines(_) => self.discard_lines(),
+        };
+
+        match result {
+            Ok(()) => self.refresh_repository_view(),
+            Err(e) => self.discard_error = Some(e.message().to_string()),
+        }
+    }
+
+    /// Throw away the changes to one file: worktree edits go back to the
+    /// index version, staged-only edits go back to HEAD, and files git has
+    /// no other copy of are deleted.
+    fn discard_file(&mut self, path: &Path) -> Result<(), Error> {
+        let repo = Repository::open(&self.cur_dir)?;
+        let status = repo.status_file(path).unwrap_or(Status::CURRENT);
+        let wt_changes = Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_TYPECHANGE;
+
+        if status.contains(Status::WT_NEW) {
+            std::fs::remove_file(Path::new(&self.cur_dir).join(path))
+                .map_err(|e| Error::from_str(&e.to_string()))?;
+        } else if status.intersects(wt_changes) {
+            let mut checkout = build::CheckoutBuilder::new();
+            checkout.force().path(path);
+            repo.checkout_index(None, Some(&mut checkout))?;
+        } else if status.contains(Status::INDEX_NEW) {
+            let mut index = repo.index()?;
+            index.remove_path(path)?;
+            index.write()?;
+            std::fs::remove_file(Path::new(&self.cur_dir).join(path))
+                .map_err(|e| Error::from_str(&e.to_string()))?;
+        } else {
+            let mut checkout = build::CheckoutBuilder::new();
+            checkout.force().path(path);
+            repo.checkout_head(Some(&mut checkout))?;
+        }
+
+        Ok(())
+    }
+
+    /// Revert the unstaged hunk under the diff cursor in the worktree.
+    fn discard_hunk(&mut self) -> Result<(), Error> {
+        let (Some(path), Some(hunk)) = (self.selected_file.clone(), self.hunk_at_cursor().cloned())
+        else {
+            return Ok(());
+        };
+        let Some(path_str) = path.to_str() else {
+            return Ok(());
+        };
+
+        let repo = Repository::open(&self.cur_dir)?;
+        let mut opts = Self::file_diff_options(path_str);
+        opts.reverse(true);
+        let diff = repo.diff_index_to_workdir(None, Some(&mut opts))?;
+
+        let mut apply_opts = ApplyOptions::new();
+        apply_opts.hunk_callback(|h| h.is_some_and(|h| hunk.matches(&h, true)));
+        repo.apply(&diff, ApplyLocation::WorkDir, Some(&mut apply_opts))?;
+
+        Ok(())
+    }
+
+    /// Revert only the selected unstaged lines in the worktree.
+    fn discard_lines(&mut self) -> Result<(), Error> {
+        let Some(path) = self.selected_file.clone() else {
+            return Ok(());
+        };
+        let Some(path_str) = path.to_str() else {
+            return Ok(());
+        };
+
+        let repo = Repository::open(&self.cur_dir)?;
+        let mut opts = Self::file_diff_options(path_str);
+        let diff = repo.diff_index_to_workdir(None, Some(&mut opts))?;
+
+        let hunks = self.selected_hunk_lines(&diff, false)?;
+        if let Some(text) = partial_patch(path_str, None, &hunks, true) {
+            let patch = Diff::from_buffer(text.as_bytes())?;
+            repo.apply(&patch, ApplyLocation::WorkDir, None)?;
+        }
+
+        self.diff_select_anchor = None;
+        Ok(())
+    }
+
+    pub fn diff_scroll_down(&mut self) {
+        if self.diff_scroll < self.diff_content.len().saturating_sub(1) {
+            self.diff_scroll += 1;
+        }
+    }
+
+    pub fn diff_scroll_up(&mut self) {
+        self.diff_scroll = self.diff_scroll.saturating_sub(1);
+    }
+
+    pub fn diff_cursor_down(&mut self) {
+        if self.diff_cursor + 1 >= self.diff_content.len() {
+            return;
+        }
+        self.diff_cursor += 1;
+        if self.diff_view_height > 0 && self.diff_cursor >= self.diff_scroll + self.diff_view_height
+        {
+            self.diff_scroll_down();
+        }
+    }
+
+    pub fn diff_cursor_up(&mut self) {
+        if self.diff_cursor == 0 {
+            return;
+        }
+        self.diff_cursor -= 1;
+        if self.diff_cursor < self.diff_scroll {
+            self.diff_scroll_up();
+        }
+    }
+
+    fn clamp_diff_cursor(&mut self) {
+        self.diff_cursor = self
+            .diff_cursor
+            .min(self.diff_content.len().saturating_sub(1));
+        self.diff_scroll = self.diff_scroll.min(self.diff_cursor);
+        if self
+            .diff_select_anchor
+            .is_some_and(|a| a >= self.diff_content.len())
+        {
+            self.diff_select_anchor = None;
+        }
+    }
+
+    pub fn branch_next(&mut self) {
+        if self.branches.is_empty() {
+            self.branch_state.select(None);
+            return;
+        }
+
+        let next = match self.branch_state.selected() {
+            Some(index) if index + 1 < self.branches.len() => index + 1,
+            _ => 0,
+        };
+
+        self.branch_state.select(Some(next));
+    }
+
+    pub fn branch_previous(&mut self) {
+        if self.branches.is_empty() {
+            self.branch_state.select(None);
+            return;
+        }
+
+        let prev = match self.branch_state.selected() {
+            Some(0) | None => self.branches.len() - 1,
+            Some(index) => index - 1,
+        };
+
+        self.branch_state.select(Some(prev));
+    }
+
+    pub fn commit_graph_next(&mut self) {
+        if self.commit_graph.is_empty() {
+            // A filter may not have matched anything yet; search on.
+            self.ensure_graph_loaded(0);
+            if self.commit_graph.is_empty() {
+                return;
+            }
+        }
+        if let Some(i) = self.commit_graph_state.selected() {
+            self.ensure_graph_loaded(i + 1);
+        }
+        let next = match self.commit_graph_state.selected() {
+            Some(i) if i + 1 < self.commit_graph.len() => i + 1,
+            _ => 0,
+        };
+        self.commit_graph_state.select(Some(next));
+        self.load_commit_diff(next);
+    }
+
+    pub fn commit_graph_previous(&mut self) {
+        if self.commit_graph.is_empty() {
+            return;
+        }
+        let prev = match self.commit_graph_state.selected() {
+            Some(0) | None => self.commit_graph.len() - 1,
+            Some(i) => i - 1,
+        };
+        self.commit_graph_state.select(Some(prev));
+        self.load_commit_diff(prev);
+    }
+
+    pub fn increase_window(&mut self) {
+        if self.window_index == 3 {
+            return self.window_index = 0;
+        }
+        self.window_index += 1;
+    }
+
+    pub fn get_path(&mut self) {
+        if let Ok(ok) = current_dir() {
+            self.cur_dir = ok.display().to_string();
+        }
+    }
+
+    pub fn scan_git(&mut self) {
+        let dir = format!("{}/.git", self.cur_dir);
+        if Path::new(dir.as_str()).is_dir() {
+            self.has_git = true;
+            self.refresh_operation();
+            self.refresh_current_branch();
+            self.refresh_commit_graph();
+            self.refresh_stashes();
+            return;
+        }
+        self.has_git = false;
+        self.current_branch = "-".to_string();
+        self.refresh_commit_graph();
+        self.stashes.clear();
+        self.stash_state.select(None);
+    }
+
+    pub fn refresh_current_branch(&mut self) {
+        if !self.has_git {
+            self.current_branch = "-".to_string();
+            return;
+        }
+
+        let branch = if let Ok(repo) = Repository::open(&self.cur_dir) {
+            if let Ok(head) = repo.head() {
+                head.shorthand().map(|name| name.to_string())
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        self.current_branch = branch.unwrap_or_else(|| "detached".to_string());
+    }
+
+    /// Reload the graph when HEAD or any ref moved since the last load.
+    ///
+    /// Only the first page is walked (more if the selection was further
+    /// down); the rest is loaded by `load_graph_page` while scrolling.
+    pub fn refresh_commit_graph(&mut self) {
+        if !self.has_git {
+            self.commit_graph.clear();
+            self.commit_graph_oids.clear();
+            self.commit_graph_rows.clear();
+            self.commit_graph_labels.clear();
+            self.commit_graph_head = None;
+            self.graph_walk = HistoryWalk::default();
+            self.graph_scanned = 0;
+            self.graph_refs_key.clear();
+            self.commit_graph_state.select(None);
+            self.commit_diff_label = None;
+            self.commit_detail = None;
+            self.commit_files.clear();
+            return;
+        }
+
+        let Ok(repo) = Repository::open(&self.cur_dir) else {
+            return;
+        };
+        let key = self.graph_key(&repo);
+        if key == self.graph_refs_key {
+            return;
+        }
+        self.graph_refs_key = key;
+
+        let was_showing_commit_diff = self.graph_tab == GraphTab::Commits
+            && self.commit_diff_label.is_some()
+            && self.selected_file.is_none();
+        let prev_selected_index = self.commit_graph_state.selected();
+        let prev_selected_oid = prev_selected_index
+            .and_then(|idx| self.commit_graph_oids.get(idx))
+            .cloned();
+
+        self.commit_graph.clear();
+        self.commit_graph_oids.clear();
+        self.commit_graph_rows.clear();
+        self.commit_graph_labels = ref_labels(&repo)
+            .into_iter()
+            .map(|(oid, list)| (oid.to_string(), list))
+            .collect();
+        self.commit_graph_head = repo
+            .head()
+            .ok()
+            .and_then(|h| h.target())
+            .map(|oid| oid.to_string());
+        self.graph_walk = HistoryWalk::new(&repo, &self.graph_tips(&repo));
+        self.graph_scanned = 0;
+        if let Some(filter) = self.graph_filter.as_mut() {
+            filter.restart();
+        }
+        self.graph_layout = GraphLayout::new();
+
+        let wanted = prev_selected_index.map(|i| i + 1).unwrap_or(0);
+        loop {
+            let full = self.load_graph_page(&repo);
+            let found = prev_selected_oid
+                .as_ref()
+                .is_some_and(|oid| self.commit_graph_oids.contains(oid));
+            if found || !full || self.commit_graph.len() >= wanted || self.graph_walk.is_done() {
+                break;
+            }
+        }
+
+        if self.commit_graph.is_empty() {
+            self.commit_graph_state.select(None);
+            if was_showing_commit_diff {
+                self.diff_content.clear();
+                self.commit_diff_label = None;
+                self.commit_detail = None;
+                self.commit_files.clear();
+            }
+            return;
+        }
+
+        let selected_idx = if let Some(oid) = prev_selected_oid {
+            self.commit_graph_oids.iter().position(|o| o == &oid).unwrap_or(0)
+        } else {
+            prev_selected_index
+                .map(|idx| idx.min(self.commit_graph.len() - 1))
+                .unwrap_or(0)
+        };
+
+        self.commit_graph_state.select(Some(selected_idx));
 
         if was_showing_commit_diff {
             self.load_commit_diff(selected_idx);
         }
     }
 
-    pub fn load_commit_diff(&mut self, index: usize) {
-        self.diff_content.clear();
-        self.diff_scroll = 0;
+    /// What the graph was built from: the walk mode plus every ref and
+    /// where it points. The graph is only rebuilt when this changes.
+    fn graph_key(&self, repo: &Repository) -> Vec<String> {
+        let mut key = vec![
+            format!("all-refs {}", self.graph_all_refs),
+            format!(
+                "filter {}",
+                self.graph_filter
+                    .as_ref()
+                    .map(|f| f.query.as_str())
+                    .unwrap_or("")
+            ),
+        ];
+        if let Ok(head) = repo.head() {
+            key.push(format!(
+                "HEAD {} {}",
+                head.name().unwrap_or(""),
+                head.target().map(|o| o.to_string()).unwrap_or_default()
+            ));
+        }
+        if let Ok(references) = repo.references() {
+            for reference in references.flatten() {
+                key.push(format!(
+                    "{} {}",
+                    reference.name().unwrap_or(""),
+                    reference
+                        .target()
+                        .map(|o| o.to_string())
+                        .unwrap_or_default()
+                ));
+            }
+        }
+        key
+    }
+
+    /// HEAD, or every ref when `graph_all_refs` is on (a detached HEAD is
+    /// not under refs/, so it is always included).
+    fn graph_tips(&self, repo: &Repository) -> Vec<Oid> {
+        let mut tips: Vec<Oid> = repo
+            .head()
+            .ok()
+            .and_then(|h| h.peel_to_commit().ok())
+            .map(|c| c.id())
+            .into_iter()
+            .collect();
+
+        if self.graph_all_refs {
+            for glob in GRAPH_REF_GLOBS {
+                if let Ok(references) = repo.references_glob(glob) {
+                    tips.extend(
+                        references
+                            .flatten()
+                            .filter_map(|r| r.peel_to_commit().ok())
+                            .map(|c| c.id()),
+                    );
+                }
+            }
+        }
+        tips
+    }
+
+    /// Walk the next `GRAPH_PAGE_SIZE` commits onto the graph. A filter
+    /// looks at no more than `GRAPH_SCAN_BUDGET` commits per page (or
+    /// `GRAPH_DIFF_SCAN_BUDGET` when it diffs them); returns false when
+    /// that ran out first.
+    fn load_graph_page(&mut self, repo: &Repository) -> bool {
+        let budget = self.graph_scan_budget();
+        let mut loaded = 0;
+        let mut scanned = 0;
+        while loaded < GRAPH_PAGE_SIZE {
+            if scanned == budget {
+                return false;
+            }
+            let Some(commit) = self.graph_walk.next_commit(repo) else {
+                break;
+            };
+            scanned += 1;
+            self.graph_scanned += 1;
+            // A filtered history skips commits, so their parents would
+            // leave lanes open that never close; draw it as a plain list.
+            let parents: Vec<Oid> = match self.graph_filter.as_mut() {
+                Some(filter) => {
+                    if !filter.matches(repo, &commit) {
+                        continue;
+                    }
+                    Vec::new()
+                }
+                None => commit.parent_ids().collect(),
+            };
+            loaded += 1;
+
+            let short_id = commit.id().to_string().chars().take(7).collect::<String>();
+            let message = commit.summary().unwrap_or("(no message)");
+            self.commit_graph_rows
+                .push(self.graph_layout.push(commit.id(), &parents));
+            self.commit_graph.push(format!("{} {}", short_id, message));
+            self.commit_graph_oids.push(commit.id().to_string());
+        }
+        true
+    }
+
+    /// How many commits one page may look at under the active filter.
+    fn graph_scan_budget(&self) -> usize {
+        match &self.graph_filter {
+            Some(filter) if filter.needs_diff() => GRAPH_DIFF_SCAN_BUDGET,
+            Some(_) => GRAPH_SCAN_BUDGET,
+            None => usize::MAX,
+        }
+    }
+
+    pub fn graph_has_more(&self) -> bool {
+        !self.graph_walk.is_done()
+    }
+
+    /// Load another page when `index` is close to the end of what is loaded.
+    fn ensure_graph_loaded(&mut self, index: usize) {
+        if index + GRAPH_PAGE_MARGIN < self.commit_graph.len() || !self.graph_has_more() {
+            return;
+        }
+        if let Ok(repo) = Repository::open(&self.cur_dir) {
+            self.load_graph_page(&repo);
+        }
+    }
+
+    /// Show the history of the file selected in the tree, following
+    /// renames; a directory shows every commit that touched it.
+    pub fn open_file_history(&mut self) {
+        let Some(i) = self.tree.state.selected() else {
+            return;
+        };
+        let Some((path, _, is_dir)) = self.tree.items.get(i) else {
+            return;
+        };
+        let path = path.strip_prefix(".").unwrap_or(path).display().to_string();
+        if path.is_empty() {
+            return;
+        }
+        let key = if *is_dir { "path" } else { "follow" };
+        self.graph_filter_input = if path.contains(char::is_whitespace) {
+            format!("{}:\"{}\"", key, path)
+        } else {
+            format!("{}:{}", key, path)
+        };
+        self.apply_graph_filter();
+
+        self.graph_tab = GraphTab::Commits;
+        self.window_index = 1;
+        self.focused = false;
+        if !self.commit_graph.is_empty() {
+            self.commit_graph_state.select(Some(0));
+            self.load_commit_diff(0);
+        }
+    }
+
+    /// Blame the selected file as of HEAD.
+    pub fn open_blame(&mut self) {
+        let path = match self.selected_tree_file() {
+            Some(path) => path,
+            None => match &self.selected_file {
+                Some(path) => path.display().to_string(),
+                None => return,
+            },
+        };
+        let result =
+            Repository::open(&self.cur_dir).and_then(|repo| Blame::load(&repo, &path, None));
+        match result {
+            Ok(blame) => {
+                self.blame = Some(blame);
+                self.blame_state.select(Some(0));
+                self.blame_stack.clear();
+                self.window_index = 3;
+                self.focused = true;
+            }
+            Err(e) => self.blame_error = Some(e.message().to_string()),
+        }
+    }
+
+    pub fn close_blame(&mut self) {
+        self.blame = None;
+        self.blame_stack.clear();
+        self.blame_state.select(None);
+    }
+
+    pub fn blame_next(&mut self) {
+        let Some(blame) = &self.blame else {
+            return;
+        };
+        let next = match self.blame_state.selected() {
+            Some(i) if i + 1 < blame.lines.len() => i + 1,
+            Some(i) => i,
+            None => 0,
+        };
+        self.blame_state.select(Some(next));
+    }
+
+    pub fn blame_previous(&mut self) {
+        let prev = self.blame_state.selected().unwrap_or(0).saturating_sub(1);
+        self.blame_state.select(Some(prev));
+    }
+
+    fn blame_line(&self) -> Option<&crate::blame::BlameLine> {
+        let i = self.blame_state.selected()?;
+        self.blame.as_ref()?.lines.get(i)
+    }
+
+    /// Blame the file again as it was just before the commit that last
+    /// changed the selected line, to look past that change.
+    pub fn blame_parent(&mut self) {
+        let Some(line) = self.blame_line().cloned() else {
+            return;
+        };
+        let result = Repository::open(&self.cur_dir).and_then(|repo| {
+            let commit = repo.find_commit(line.oid)?;
+            let parent = commit.parent_id(0).map_err(|_| {
+                Error::from_str(&format!(
+                    "{} is the root commit; the line has no earlier history",
+                    line.short_id()
+                ))
+            })?;
+            Blame::load(&repo, &line.orig_path, Some(parent))
+        });
+
+        match result {
+            Ok(blame) => {
+                let row = self.blame_state.selected().unwrap_or(0);
+                if let Some(current) = self.blame.replace(blame) {
+                    self.blame_stack.push((current, row));
+                }
+                let last = self
+                    .blame
+                    .as_ref()
+                    .map(|b| b.lines.len().saturating_sub(1))
+                    .unwrap_or(0);
+                self.blame_state.select(Some(row.min(last)));
+            }
+            Err(e) => self.blame_error = Some(e.message().to_string()),
+        }
+    }
+
+    /// Go back to the blame before the last `blame_parent`.
+    pub fn blame_back(&mut self) {
+        if let Some((blame, row)) = self.blame_stack.pop() {
+            self.blame = Some(blame);
+            self.blame_state.select(Some(row));
+        }
+    }
+
+    /// Leave the blame for the commit of the selected line, showing its
+    /// change to the file.
+    pub fn blame_show_commit(&mut self) {
+        let Some(line) = self.blame_line().cloned() else {
+            return;
+        };
+        if let Err(e) = self.select_graph_commit(&line.oid.to_string()) {
+            self.blame_error = Some(e.message().to_string());
+            return;
+        }
+        self.close_blame();
+        if let Some(i) = self
+            .commit_files
+            .iter()
+            .position(|f| f.path == line.orig_path)
+        {
+            self.select_commit_file(i);
+        }
+        self.graph_tab = GraphTab::Commits;
+        self.window_index = 1;
+        self.focused = false;
+    }
+
+    /// Select `oid` in the commit graph and show its diff, loading more
+    /// pages as needed. The active filter is kept: a commit it hides is
+    /// reported without walking the history, and the search gives up after
+    /// one scan budget like a scroll would.
+    pub fn select_graph_commit(&mut self, oid: &str) -> Result<(), Error> {
+        let repo = Repository::open(&self.cur_dir)?;
+        let short_id = &oid[..7.min(oid.len())];
+        let hidden = |filter: &HistoryFilter| {
+            Error::from_str(&format!(
+                "{} is hidden by the history filter \"{}\"; clear it to show the commit",
+                short_id, filter.query
+            ))
+        };
+
+        // A followed path depends on the renames seen newer in the walk, so
+        // only the walk itself can tell.
+        if let Some(filter) = self.graph_filter.as_ref().filter(|f| f.follow.is_none()) {
+            let commit = repo.find_commit(Oid::from_str(oid)?)?;
+            if !filter.clone().matches(&repo, &commit) {
+                return Err(hidden(filter));
+            }
+        }
+
+        let budget = self.graph_scan_budget();
+        let scanned = self.graph_scanned;
+        loop {
+            if let Some(i) = self.commit_graph_oids.iter().position(|o| o == oid) {
+                self.commit_graph_state.select(Some(i));
+                self.load_commit_diff(i);
+                return Ok(());
+            }
+            if !self.graph_has_more() {
+                return Err(match &self.graph_filter {
+                    Some(filter) => hidden(filter),
+                    None => Error::from_str(&format!("{} is not in the commit graph", short_id)),
+                });
+            }
+            if self.graph_scanned - scanned >= budget {
+                return Err(Error::from_str(&format!(
+                    "{} is not in the {} commits searched so far; scroll to search on",
+                    short_id, self.graph_scanned
+                )));
+            }
+            self.load_graph_page(&repo);
+        }
+    }
+
+    fn selected_tree_file(&self) -> Option<String> {
+        let i = self.tree.state.selected()?;
+        let (path, _, is_dir) = self.tree.items.get(i)?;
+        if *is_dir {
+            return None;
+        }
+        Some(path.strip_prefix(".").unwrap_or(path).display().to_string())
+    }
+
+    /// Open the filter prompt with the active query in it.
+    pub fn open_graph_filter_dialog(&mut self) {
+        self.show_graph_filter_dialog = true;
+        self.graph_filter_error = None;
+        self.graph_filter_input = self
+            .graph_filter
+            .as_ref()
+            .map(|f| f.query.clone())
+            .unwrap_or_default();
+    }
+
+    pub fn close_graph_filter_dialog(&mut self) {
+        self.show_graph_filter_dialog = false;
+        self.graph_filter_error = None;
+        self.graph_filter_input.clear();
+    }
+
+    /// Re-run the history walk with the query from the prompt; an empty
+    /// query removes the filter. Parse errors keep the prompt open.
+    pub fn apply_graph_filter(&mut self) {
+        match HistoryFilter::parse(&self.graph_filter_input) {
+            Ok(filter) => {
+                self.graph_filter = filter;
+                self.close_graph_filter_dialog();
+                self.commit_graph_state.select(None);
+                self.refresh_commit_graph();
+            }
+            Err(e) => self.graph_filter_error = Some(e.message().to_string()),
+        }
+    }
+
+    pub fn toggle_graph_all_refs(&mut self) {
+        self.graph_all_refs = !self.graph_all_refs;
+        self.refresh_commit_graph();
+    }
+
+    pub fn load_commit_diff(&mut self, index: usize) {
+        // Reloading the same commit keeps the file the user was reading.
+        let prev_file = self
+            .commit_detail
+            .as_ref()
+            .filter(|d| self.commit_graph_oids.get(index) == Some(&d.oid))
+            .and_then(|_| self.commit_file_state.selected())
+            .and_then(|i| self.commit_files.get(i))
+            .map(|f| f.path.clone());
+
+        self.diff_content.clear();
+        self.diff_hunks.clear();
+        self.commit_detail = None;
+        self.commit_files.clear();
+        self.commit_file_state.select(None);
+        self.diff_scroll = 0;
+        self.diff_cursor = 0;
+        self.diff_select_anchor = None;
+
+        let oid_str = match self.commit_graph_oids.get(index) {
+            Some(s) => s.clone(),
+            None => return,
+        };
+
+        let repo = match Repository::open(&self.cur_dir) {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+
+        let oid = match git2::Oid::from_str(&oid_str) {
+            Ok(o) => o,
+            Err(_) => return,
+        };
+
+        let commit = match repo.find_commit(oid) {
+            Ok(c) => c,
+            Err(_) => return,
+        };
+
+        let commit_tree = match commit.tree() {
+            Ok(t) => t,
+            Err(_) => return,
+        };
+
+        let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+
+        let mut diff = match repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit_tree), None)
+        {
+            Ok(d) => d,
+            Err(_) => return,
+        };
+        let _ = diff.find_similar(None);
+
+        let mut detail = CommitDetail::load(&repo, &commit, &diff);
+        if let SignatureStatus::Checking { kind } = &detail.signature {
+            match self.signature_cache.get(&detail.oid) {
+                Some(status) => detail.signature = status.clone(),
+                None => self.request_signature(detail.oid.clone(), kind.clone()),
+            }
+        }
+        self.commit_detail = Some(detail);
+        self.commit_files = Self::commit_files(&diff);
+        // In a file's history only that file is shown, under the name it
+        // had in this commit.
+        if let Some(path) = self
+            .graph_filter
+            .as_ref()
+            .and_then(|f| f.follow_paths.get(&oid))
+        {
+            self.commit_files
+                .retain(|f| &f.path == path || f.old_path.as_ref() == Some(path));
+        }
+        self.selected_file = None;
+
+        // A pickaxe search opens the first file with a matching line.
+        let first_hit = self.graph_filter.as_ref().and_then(|filter| {
+            self.commit_files.iter().position(|file| {
+                file.lines.iter().any(|line| {
+                    matches!(line.kind, DiffLineKind::Add | DiffLineKind::Delete)
+                        && filter.pickaxe_hit(&line.content)
+                })
+            })
+        });
+        let file = prev_file
+            .and_then(|path| self.commit_files.iter().position(|f| f.path == path))
+            .or(first_hit)
+            .unwrap_or(0);
+        self.select_commit_file(file);
+
+        let short = &oid_str[..7.min(oid_str.len())];
+        if let Some(label) = self.commit_graph.get(index) {
+            self.commit_diff_label = Some(format!("Commit {}", label.trim_start_matches("* ")));
+        } else {
+            self.commit_diff_label = Some(format!("Commit {}", short));
+        }
+    }
+
+    /// Show the diff of file `index` of the open commit.
+    pub fn select_commit_file(&mut self, index: usize) {
+        let Some(file) = self.commit_files.get(index) else {
+            return;
+        };
+        self.diff_content = file.lines.clone();
+        self.diff_scroll = 0;
+        self.diff_cursor = 0;
+        self.commit_file_state.select(Some(index));
+    }
+
+    pub fn commit_file_next(&mut self) {
+        if self.commit_files.is_empty() {
+            return;
+        }
+        let next = match self.commit_file_state.selected() {
+            Some(i) if i + 1 < self.commit_files.len() => i + 1,
+            _ => 0,
+        };
+        self.select_commit_file(next);
+    }
+
+    pub fn commit_file_previous(&mut self) {
+        if self.commit_files.is_empty() {
+            return;
+        }
+        let prev = match self.commit_file_state.selected() {
+            Some(0) | None => self.commit_files.len() - 1,
+            Some(i) => i - 1,
+        };
+        self.select_commit_file(prev);
+    }
+
+    pub fn graph_tab_toggle(&mut self) {
+        self.graph_tab = match self.graph_tab {
+            GraphTab::Commits => GraphTab::Stash,
+            GraphTab::Stash => GraphTab::Commits,
+        };
+    }
+
+    /// Reload the stash list, keeping the selection on the same stash.
+    pub fn refresh_stashes(&mut self) {
+        let prev_oid = self
+            .stash_state
+            .selected()
+            .and_then(|i| self.stashes.get(i))
+            .map(|s| s.oid);
+
+        self.stashes.clear();
+        if let Ok(mut repo) = Repository::open(&self.cur_dir) {
+            let mut entries = Vec::new();
+            let _ = repo.stash_foreach(|index, message, oid| {
+                entries.push((index, message.to_string(), *oid));
+                true
+            });
+            for (index, message, oid) in entries {
+                let time = repo
+                    .find_commit(oid)
+                    .map(|c| c.time().seconds())
+                    .unwrap_or(0);
+                self.stashes.push(StashEntry {
+                    index,
+                    message,
+                    oid,
+                    time,
+                });
+            }
+        }
+
+        if self.stashes.is_empty() {
+            self.stash_state.select(None);
+            if self.showing_stash_diff() {
+                self.diff_content.clear();
+                self.commit_diff_label = None;
+                self.commit_detail = None;
+                self.commit_files.clear();
+            }
+            return;
+        }
+
+        let selected = prev_oid
+            .and_then(|oid| self.stashes.iter().position(|s| s.oid == oid))
+            .unwrap_or_else(|| {
+                self.stash_state
+                    .selected()
+                    .unwrap_or(0)
+                    .min(self.stashes.len() - 1)
+            });
+        self.stash_state.select(Some(selected));
+
+        if self.showing_stash_diff() && prev_oid != Some(self.stashes[selected].oid) {
+            self.load_stash_diff(selected);
+        }
+    }
+
+    fn showing_stash_diff(&self) -> bool {
+        self.graph_tab == GraphTab::Stash
+            && self.commit_diff_label.is_some()
+            && self.selected_file.is_none()
+    }
+
+    pub fn stash_next(&mut self) {
+        if self.stashes.is_empty() {
+            return;
+        }
+        let next = match self.stash_state.selected() {
+            Some(i) if i + 1 < self.stashes.len() => i + 1,
+            _ => 0,
+        };
+        self.stash_state.select(Some(next));
+        self.load_stash_diff(next);
+    }
+
+    pub fn stash_previous(&mut self) {
+        if self.stashes.is_empty() {
+            return;
+        }
+        let prev = match self.stash_state.selected() {
+            Some(0) | None => self.stashes.len() - 1,
+            Some(i) => i - 1,
+        };
+        self.stash_state.select(Some(prev));
+        self.load_stash_diff(prev);
+    }
+
+    /// Show what a stash holds: its worktree changes against the commit it
+    /// was made on, followed by any untracked files it saved.
+    pub fn load_stash_diff(&mut self, index: usize) {
+        self.diff_content.clear();
+        self.diff_hunks.clear();
+        self.commit_detail = None;
+        self.commit_files.clear();
+        self.diff_scroll = 0;
+        self.diff_cursor = 0;
+        self.diff_select_anchor = None;
+
+        let Some(entry) = self.stashes.get(index).cloned() else {
+            return;
+        };
+        let Ok(repo) = Repository::open(&self.cur_dir) else {
+            return;
+        };
+        let Ok(commit) = repo.find_commit(entry.oid) else {
+            return;
+        };
+
+        let base_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+        if let Ok(tree) = commit.tree()
+            && let Ok(diff) = repo.diff_tree_to_tree(base_tree.as_ref(), Some(&tree), None)
+        {
+            self.diff_content = Self::diff_lines(&diff);
+        }
+
+        if let Ok(untracked) = commit.parent(2).and_then(|p| p.tree())
+            && let Ok(diff) = repo.diff_tree_to_tree(None, Some(&untracked), None)
+        {
+            self.diff_content.extend(Self::diff_lines(&diff));
+        }
+
+        self.selected_file = None;
+        self.commit_diff_label = Some(format!("stash@{{{}}} {}", entry.index, entry.message));
+    }
+
+    pub fn open_stash_dialog(&mut self) {
+        self.show_stash_dialog = true;
+        self.stash_message.clear();
+        self.stash_include_untracked = false;
+        self.stash_keep_index = false;
+    }
+
+    pub fn close_stash_dialog(&mut self) {
+        self.show_stash_dialog = false;
+        self.stash_message.clear();
+    }
+
+    /// Stash the working tree with the options picked in the stash dialog.
+    pub fn confirm_stash_save(&mut self) {
+        let mut flags = StashFlags::DEFAULT;
+        if self.stash_include_untracked {
+            flags |= StashFlags::INCLUDE_UNTRACKED;
+        }
+        if self.stash_keep_index {
+            flags |= StashFlags::KEEP_INDEX;
+        }
+        let message = self.stash_message.trim().to_string();
+
+        let result = (|| -> Result<Oid, Error> {
+            let mut repo = Repository::open(&self.cur_dir)?;
+            let signature = repo.signature()?;
+            let message = (!message.is_empty()).then_some(message.as_str());
+            repo.stash_save2(&signature, message, Some(flags))
+        })();
+
+        self.close_stash_dialog();
+        match result {
+            Ok(_) => {
+                self.stash_state.select(Some(0));
+                self.refresh_after_stash();
+            }
+            Err(e) => self.stash_error = Some(e.message().to_string()),
+        }
+    }
+
+    /// Apply the selected stash to the worktree; `pop` also drops it.
+    pub fn apply_selected_stash(&mut self, pop: bool) {
+        let Some(index) = self
+            .stash_state
+            .selected()
+            .and_then(|i| self.stashes.get(i))
+            .map(|s| s.index)
+        else {
+            return;
+        };
+
+        let result = Repository::open(&self.cur_dir).and_then(|mut repo| {
+            let mut opts = StashApplyOptions::new();
+            if pop {
+                repo.stash_pop(index, Some(&mut opts))
+            } else {
+                repo.stash_apply(index, Some(&mut opts))
+            }
+        });
+
+        match result {
+            Ok(()) => self.refresh_after_stash(),
+            Err(e) => self.stash_error = Some(e.message().to_string()),
+        }
+    }
+
+    pub fn drop_selected_stash(&mut self) {
+        self.stash_drop_confirm = false;
+        let Some(index) = self
+            .stash_state
+            .selected()
+            .and_then(|i| self.stashes.get(i))
+            .map(|s| s.index)
+        else {
+            return;
+        };
+
+        match Repository::open(&self.cur_dir).and_then(|mut repo| repo.stash_drop(index)) {
+            Ok(()) => self.refresh_after_stash(),
+            Err(e) => self.stash_error = Some(e.message().to_string()),
+        }
+    }
+
+    fn refresh_after_stash(&mut self) {
+        self.refresh_stashes();
+        self.refresh_repository_view();
+        if self.graph_tab == GraphTab::Stash
+            && let Some(i) = self.stash_state.selected()
+        {
+            self.load_stash_diff(i);
+        }
+    }
+
+    pub fn open_commit_dialog(&mut self) {
+        self.show_commit_dialog = true;
+        self.commit_summary.clear();
+        self.commit_description.clear();
+        self.commit_focus_description = false;
+        self.commit_summary_cursor = 0;
+        self.commit_description_cursor = 0;
+        self.commit_summary_scroll = 0;
+        self.commit_description_scroll = 0;
+        self.commit_amend = false;
+        self.commit_amend_pushed = None;
+        self.commit_author_from = None;
+        self.commit_fixup = None;
+        self.commit_reword = None;
+        self.commit_skip_hooks = false;
+    }
+
+    pub fn open_amend_dialog(&mut self) {
+        self.open_commit_dialog();
+        self.toggle_commit_amend();
+    }
+
+    pub fn close_commit_dialog(&mut self) {
+        self.show_commit_dialog = false;
+        self.commit_summary.clear();
+        self.commit_description.clear();
+        self.commit_focus_description = false;
+        self.commit_summary_cursor = 0;
+        self.commit_description_cursor = 0;
+        self.commit_summary_scroll = 0;
+        self.commit_description_scroll = 0;
+        self.commit_amend = false;
+        self.commit_amend_pushed = None;
+        self.commit_author_from = None;
+        self.commit_fixup = None;
+        self.commit_reword = None;
+        self.commit_skip_hooks = false;
+    }
+
+    /// Cycle the commit dialog through a plain commit, a `fixup!` and a
+    /// `squash!` of the commit highlighted in the graph. The summary names
+    /// the target; the description is kept, and a squash adds it to the
+    /// combined message.
+    pub fn toggle_commit_fixup(&mut self) {
+        if self.commit_reword.is_some() {
+            return;
+        }
+        let next = match self.commit_fixup {
+            None => Some(RebaseAction::Fixup),
+            Some((RebaseAction::Fixup, _)) => Some(RebaseAction::Squash),
+            Some(_) => None,
+        };
+        let target = self
+            .commit_graph_state
+            .selected()
+            .and_then(|i| self.commit_graph_oids.get(i))
+            .and_then(|oid| Oid::from_str(oid).ok())
+            .and_then(|oid| {
+                let repo = Repository::open(&self.cur_dir).ok()?;
+                let commit = repo.find_commit(oid).ok()?;
+                Some((oid, commit.summary().unwrap_or("").to_string()))
+            });
+
+        match (next, target) {
+            (Some(action), Some((oid, summary))) => {
+                self.commit_amend = false;
+                self.commit_amend_pushed = None;
+                self.commit_fixup = Some((action, oid));
+                let description = self.commit_description.clone();
+                self.set_commit_message(&format!(
+                    "{}! {}\n\n{}",
+                    action.name(),
+                    summary,
+                    description
+                ));
+            }
+            _ => {
+                if self.commit_fixup.take().is_some() {
+                    self.set_commit_message("");
+                }
+            }
+        }
+    }
+
+    /// Switch the commit dialog between a new commit and amending HEAD.
+    ///
+    /// Turning amend on replaces the typed message with HEAD's message and
+    /// checks whether HEAD is already on its upstream; turning it off clears
+    /// the message again. Nothing happens while HEAD is unborn.
+    pub fn toggle_commit_amend(&mut self) {
+        if self.commit_reword.is_some() || !self.commit_amend_allowed() {
+            return;
+        }
+        self.commit_fixup = None;
+        if self.commit_amend {
+            self.commit_amend = false;
+            self.commit_amend_pushed = None;
+            self.set_commit_message("");
+            return;
+        }
+
+        let Ok(repo) = Repository::open(&self.cur_dir) else {
+            return;
+        };
+        let Ok(head) = repo.head() else {
+            return;
+        };
+        let Ok(commit) = head.peel_to_commit() else {
+            return;
+        };
+
+        self.commit_amend = true;
+        self.commit_amend_pushed = Self::pushed_upstream(&repo, &head, commit.id());
+        self.set_commit_message(commit.message().unwrap_or(""));
+    }
+
+    /// A rebase step waiting for its message decides for itself whether it
+    /// amends; only an edit stop is there to be amended by hand.
+    pub fn commit_amend_allowed(&self) -> bool {
+        self.rebase_stop.is_none_or(|stop| stop == RebaseStop::Edit)
+    }
+
+    /// The upstream name when `oid` is already reachable from the upstream
+    /// of the branch `head` points at.
+    fn pushed_upstream(repo: &Repository, head: &Reference, oid: Oid) -> Option<String> {
+        if !head.is_branch() {
+            return None;
+        }
+        let name = head.shorthand()?;
+        let branch = repo.find_branch(name, BranchType::Local).ok()?;
+        let upstream = branch.upstream().ok()?;
+        let upstream_oid = upstream.get().target()?;
+
+        let pushed =
+            upstream_oid == oid || repo.graph_descendant_of(upstream_oid, oid).unwrap_or(false);
+        if !pushed {
+            return None;
+        }
+        upstream.name().ok().flatten().map(|n| n.to_string())
+    }
+
+    fn set_commit_message(&mut self, message: &str) {
+        let message = message.trim_end();
+        let (summary, description) = match message.split_once('\n') {
+            Some((summary, rest)) => (summary, rest.trim_start_matches('\n')),
+            None => (message, ""),
+        };
+
+        self.commit_summary = summary.to_string();
+        self.commit_description = description.to_string();
+        self.commit_focus_description = false;
+        self.commit_summary_cursor = self.commit_summary.len();
+        self.commit_description_cursor = self.commit_description.len();
+        self.commit_summary_scroll = 0;
+        self.commit_description_scroll = 0;
+    }
+
+    fn active_commit_text_mut(&mut self) -> (&mut String, &mut usize) {
+        if self.commit_focus_description {
+            (
+                &mut self.commit_description,
+                &mut self.commit_description_cursor,
+            )
+        } else {
+            (&mut self.commit_summary, &mut self.commit_summary_cursor)
+        }
+    }
+
+    pub fn commit_message_insert(&mut self, c: char) {
+        let (text, cursor) = self.active_commit_text_mut();
+        text.insert(*cursor, c);
+        *cursor += c.len_utf8();
+    }
+
+    pub fn commit_message_backspace(&mut self) {
+        let (text, cursor) = self.active_commit_text_mut();
+        if *cursor == 0 {
+            return;
+        }
+        let mut start = *cursor - 1;
+        while !text.is_char_boundary(start) {
+            start -= 1;
+        }
+        text.remove(start);
+        *cursor = start;
+    }
+
+    pub fn commit_message_delete(&mut self) {
+        let (text, cursor) = self.active_commit_text_mut();
+        if *cursor >= text.len() {
+            return;
+        }
+        text.remove(*cursor);
+    }
+
+    pub fn commit_cursor_left(&mut self) {
+        let (text, cursor) = self.active_commit_text_mut();
+        if *cursor == 0 {
+            return;
+        }
+        let mut pos = *cursor - 1;
+        while !text.is_char_boundary(pos) {
+            pos -= 1;
+        }
+        *cursor = pos;
+    }
+
+    pub fn commit_cursor_right(&mut self) {
+        let (text, cursor) = self.active_commit_text_mut();
+        if *cursor >= text.len() {
+            return;
+        }
+        let mut pos = *cursor + 1;
+        while pos < text.len() && !text.is_char_boundary(pos) {
+            pos += 1;
+        }
+        *cursor = pos;
+    }
+
+    pub fn commit_cursor_home(&mut self) {
+        if self.commit_focus_description {
+            self.commit_description_cursor = 0;
+        } else {
+            self.commit_summary_cursor = 0;
+        }
+    }
+
+    pub fn commit_cursor_end(&mut self) {
+        if self.commit_focus_description {
+            self.commit_description_cursor = self.commit_description.len();
+        } else {
+            self.commit_summary_cursor = self.commit_summary.len();
+        }
+    }
+
+    pub fn toggle_commit_focus(&mut self) {
+        self.commit_focus_description = !self.commit_focus_description;
+    }
+
+    pub fn commit(&mut self) -> Result<Oid, Error> {
+        let repo = Repository::open(&self.cur_dir)?;
+        let mut index = repo.index()?;
+
+        let tree_oid = index.write_tree()?;
+        let tree = repo.find_tree(tree_oid)?;
+
+        let signature = repo.signature()?;
+
+        let message = self.commit_message();
+
+        let head = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let (author, parents, reflog) = match (&head, self.commit_amend) {
+            (Some(head), true) => (
+                head.author().to_owned(),
+                head.parents().collect::<Vec<_>>(),
+                "commit (amend)",
+            ),
+            (Some(head), false) => (
+                self.commit_author_from
+                    .and_then(|oid| repo.find_commit(oid).ok())
+                    .map(|c| c.author().to_owned())
+                    .unwrap_or_else(|| signature.clone()),
+                vec![head.clone()],
+                "commit",
+            ),
+            (None, true) => return Err(Error::from_str("there is no commit to amend")),
+            (None, false) => (signature.clone(), vec![], "commit (initial)"),
+        };
+        let parent_refs: Vec<&Commit> = parents.iter().collect();
+
+        let oid = Self::create_commit(&repo, &author, &signature, &message, &tree, &parent_refs)
+            .map_err(|e| self.commit_create_failed(e))?;
+        Self::update_head(&repo, oid, &format!("{}: {}", reflog, self.commit_summary))?;
+        // Committing is how a revert started from the graph finishes.
+        if matches!(
+            repo.state(),
+            RepositoryState::Revert | RepositoryState::RevertSequence
+        ) {
+            repo.cleanup_state()?;
+        }
+
+        self.staged_count = 0;
+
+        Ok(oid)
+    }
+
+    fn commit_message(&self) -> String {
+        if self.commit_description.is_empty() {
+            self.commit_summary.clone()
+        } else {
+            format!("{}\n\n{}", self.commit_summary, self.commit_description)
+        }
+    }
+
+    /// Commit from the dialog. pre-commit, prepare-commit-msg and commit-msg
+    /// run first on a worker thread unless hooks are skipped; the commit
+    /// itself happens in `check_hook_result` once they pass.
+    pub fn submit_commit(&mut self) {
+        if self.commit_summary.is_empty() {
+            self.close_commit_dialog();
+            return;
+        }
 
-        let oid_str = match self.commit_graph_oids.get(index) {
-            Some(s) => s.clone(),
-            None => return,
+        let hooks = match Repository::open(&self.cur_dir) {
+            Ok(repo) => Hooks::for_repo(&repo),
+            Err(e) => {
+                self.commit_failed(&e);
+                return;
+            }
         };
 
-        let repo = match Repository::open(&self.cur_dir) {
-            Ok(r) => r,
-            Err(_) => return,
+        if self.commit_skip_hooks || !hooks.any(&["pre-commit", "prepare-commit-msg", "commit-msg"])
+        {
+            self.finish_commit();
+            return;
+        }
+
+        let message = self.commit_message();
+        let amend = self.commit_amend;
+        self.show_commit_dialog = false;
+        self.start_hooks(HookStage::Commit, move |on_event| {
+            hooks.run_commit_hooks(&message, amend, on_event).map(Some)
+        });
+    }
+
+    fn finish_commit(&mut self) {
+        if let Some(target) = self.commit_reword {
+            self.finish_reword(target);
+            return;
+        }
+        match self.commit() {
+            Ok(_oid) => {
+                let resume_rebase = self.rebase_stop == Some(RebaseStop::Message);
+                self.scan_git();
+                self.refresh_repository_view();
+
+                self.commit_success_open = true;
+                let skip_hooks = self.commit_skip_hooks;
+                self.close_commit_dialog();
+
+                if !skip_hooks && let Ok(repo) = Repository::open(&self.cur_dir) {
+                    let hooks = Hooks::for_repo(&repo);
+                    if hooks.find("post-commit").is_some() {
+                        self.start_hooks(HookStage::PostCommit, move |on_event| {
+                            hooks
+                                .run_announced("post-commit", &[], None, on_event)
+                                .map(|_| None)
+                        });
+                    }
+                }
+
+                if resume_rebase {
+                    self.rebase_stop = None;
+                    self.run_rebase();
+                }
+            }
+            Err(e) => self.commit_failed(&e),
+        }
+    }
+
+    /// Open the hook dialog for `stage` and return the sender its worker
+    /// reports to.
+    fn begin_hooks(&mut self, stage: HookStage) -> mpsc::Sender<HookEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.hook_rx = Some(rx);
+        self.hook_stage = Some(stage);
+        self.hook_title = None;
+        self.hook_output.clear();
+        self.hook_error = None;
+        self.show_hook_dialog = true;
+        tx
+    }
+
+    fn start_hooks<F>(&mut self, stage: HookStage, job: F)
+    where
+        F: FnOnce(&mut dyn FnMut(HookEvent)) -> Result<Option<String>, Error> + Send + 'static,
+    {
+        let tx = self.begin_hooks(stage);
+        std::thread::spawn(move || {
+            let result = job(&mut |event| {
+                let _ = tx.send(event);
+            });
+            let _ = tx.send(HookEvent::Finished(
+                result.map_err(|e| e.message().to_string()),
+            ));
+        });
+    }
+
+    /// Drain hook output into the dialog and act on the result once the
+    /// hooks are done. A failed run keeps the dialog open with the error.
+    pub fn check_hook_result(&mut self) {
+        let Some(rx) = self.hook_rx.take() else {
+            return;
         };
 
-        let oid = match git2::Oid::from_str(&oid_str) {
-            Ok(o) => o,
-            Err(_) => return,
+        let mut finished = None;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                HookEvent::Started(name) => {
+                    self.hook_output.push(format!("── {} ──", name));
+                    self.hook_title = Some(name);
+                }
+                HookEvent::Output(line) => self.hook_output.push(line),
+                HookEvent::Finished(result) => {
+                    finished = Some(result);
+                    break;
+                }
+            }
+        }
+
+        let Some(result) = finished else {
+            self.hook_rx = Some(rx);
+            return;
         };
 
-        let commit = match repo.find_commit(oid) {
-            Ok(c) => c,
-            Err(_) => return,
+        match result {
+            Ok(message) => {
+                self.show_hook_dialog = false;
+                if self.hook_stage.take() == Some(HookStage::Commit) {
+                    if let Some(message) = message {
+                        self.set_commit_message(&message);
+                    }
+                    self.finish_commit();
+                }
+            }
+            Err(err) => self.hook_error = Some(err),
+        }
+    }
+
+    /// Close a finished hook dialog. After a rejected commit the commit
+    /// dialog comes back with the message intact.
+    pub fn dismiss_hook_dialog(&mut self) {
+        if self.hook_rx.is_some() {
+            return;
+        }
+
+        self.show_hook_dialog = false;
+        self.hook_error = None;
+        if self.hook_stage.take() == Some(HookStage::Commit) {
+            self.show_commit_dialog = true;
+        }
+    }
+
+    /// Park the commit dialog after a failed `commit`, keeping the typed
+    /// message so it can be retried once the error is dismissed.
+    ///
+    /// Signing failures already have their own dialog and are not repeated.
+    pub fn commit_failed(&mut self, err: &Error) {
+        self.show_commit_dialog = false;
+        if self.commit_sign_error.is_none() {
+            self.commit_error = Some((err.class(), err.message().to_string()));
+        }
+    }
+
+    /// Close the commit or signing error and reopen the dialog for a retry.
+    pub fn dismiss_commit_error(&mut self) {
+        self.commit_error = None;
+        self.commit_sign_error = None;
+        self.show_commit_dialog = true;
+    }
+
+    /// Note a signing failure from `create_commit` for its own dialog,
+    /// and return the error for `commit_failed`.
+    fn commit_create_failed(&mut self, err: CommitError) -> Error {
+        if let CommitError::Sign(e) = &err {
+            self.commit_sign_error = Some(e.to_string());
+        }
+        err.into()
+    }
+
+    /// Write a commit object without moving any ref, signing it when
+    /// `commit.gpgsign` asks for it.
+    pub fn create_commit(
+        repo: &Repository,
+        author: &Signature,
+        committer: &Signature,
+        message: &str,
+        tree: &Tree,
+        parents: &[&Commit],
+    ) -> Result<Oid, CommitError> {
+        let sign = SignConfig::from_config(&repo.config()?)
+            .map_err(|e| CommitError::Sign(SignError(e.message().to_string())))?;
+        let Some(sign) = sign else {
+            return Ok(repo.commit(None, author, committer, message, tree, parents)?);
+        };
+
+        let buffer = repo.commit_create_buffer(author, committer, message, tree, parents)?;
+        let buffer = buffer
+            .as_str()
+            .ok_or_else(|| Error::from_str("commit buffer is not valid UTF-8"))?;
+
+        let identity = format!(
+            "{} <{}>",
+            committer.name().unwrap_or(""),
+            committer.email().unwrap_or("")
+        );
+        let signature = sign.sign(buffer, &identity).map_err(CommitError::Sign)?;
+
+        Ok(repo.commit_signed(buffer, &signature, None)?)
+    }
+
+    /// Mark or unmark the highlighted commit for a batch action.
+    pub fn toggle_graph_mark(&mut self) {
+        let Some(oid) = self
+            .commit_graph_state
+            .selected()
+            .and_then(|i| self.commit_graph_oids.get(i))
+            .cloned()
+        else {
+            return;
+        };
+        match self.graph_marked.iter().position(|o| *o == oid) {
+            Some(i) => {
+                self.graph_marked.remove(i);
+            }
+            None => self.graph_marked.push(oid),
+        }
+    }
+
+    /// The marked commits, or the highlighted one, oldest first.
+    fn graph_selection(&self) -> Vec<Oid> {
+        let mut rows: Vec<usize> = if self.graph_marked.is_empty() {
+            self.commit_graph_state.selected().into_iter().collect()
+        } else {
+            self.graph_marked
+                .iter()
+                .filter_map(|oid| self.commit_graph_oids.iter().position(|o| o == oid))
+                .collect()
+        };
+        rows.sort_unstable_by(|a, b| b.cmp(a));
+        rows.iter()
+            .filter_map(|&i| self.commit_graph_oids.get(i))
+            .filter_map(|oid| Oid::from_str(oid).ok())
+            .collect()
+    }
+
+    /// Apply the marked commits (or the highlighted one) on top of HEAD.
+    /// Each clean pick is committed with its original author; a conflict
+    /// stops the run until it is continued or aborted.
+    pub fn cherry_pick_selected(&mut self) {
+        let commits = self.graph_selection();
+        if commits.is_empty() {
+            return;
+        }
+        let result = Repository::open(&self.cur_dir).and_then(|repo| {
+            if self.operation.is_some() || repo.state() != RepositoryState::Clean {
+                return Err(Error::from_str(
+                    "another operation is in progress; continue or abort it first",
+                ));
+            }
+            if let Some(merge) = commits
+                .iter()
+                .filter_map(|oid| repo.find_commit(*oid).ok())
+                .find(|c| c.parent_count() > 1)
+            {
+                return Err(Error::from_str(&format!(
+                    "{} is a merge commit and cannot be cherry-picked",
+                    &merge.id().to_string()[..7]
+                )));
+            }
+            Self::ensure_clean_tree(&repo)?;
+            repo.head()?.peel_to_commit().map(|c| c.id())
+        });
+
+        match result {
+            Ok(head) => {
+                self.graph_marked.clear();
+                self.operation = Some(Operation::new(OperationKind::CherryPick, commits, head));
+                self.run_operation();
+            }
+            Err(e) => self.operation_error = Some(e.message().to_string()),
+        }
+    }
+
+    /// Revert the highlighted commit. A merge first asks which parent is
+    /// the mainline; the result is left staged with the commit dialog
+    /// open, and a conflict stops it like any other operation.
+    pub fn revert_selected(&mut self) {
+        let Some(oid) = self
+            .commit_graph_state
+            .selected()
+            .and_then(|i| self.commit_graph_oids.get(i))
+            .and_then(|oid| Oid::from_str(oid).ok())
+        else {
+            return;
+        };
+        let result = Repository::open(&self.cur_dir).and_then(|repo| {
+            if self.operation.is_some() || repo.state() != RepositoryState::Clean {
+                return Err(Error::from_str(
+                    "another operation is in progress; continue or abort it first",
+                ));
+            }
+            Self::ensure_clean_tree(&repo)?;
+            let commit = repo.find_commit(oid)?;
+            Ok(commit
+                .parents()
+                .map(|p| format!("{} {}", &p.id().to_string()[..7], p.summary().unwrap_or("")))
+                .collect::<Vec<_>>())
+        });
+
+        match result {
+            Ok(parents) if parents.len() > 1 => self.revert_mainline = Some((oid, parents)),
+            Ok(_) => self.revert_commit(oid, None),
+            Err(e) => self.operation_error = Some(e.message().to_string()),
+        }
+    }
+
+    /// Revert the merge waiting in `revert_mainline` against its
+    /// `n`th parent, counting from 1.
+    pub fn choose_revert_mainline(&mut self, n: usize) {
+        let Some((oid, parents)) = self.revert_mainline.take() else {
+            return;
+        };
+        if n == 0 || n > parents.len() {
+            self.revert_mainline = Some((oid, parents));
+            return;
+        }
+        self.revert_commit(oid, Some(n as u32));
+    }
+
+    pub fn cancel_revert_mainline(&mut self) {
+        self.revert_mainline = None;
+    }
+
+    fn revert_commit(&mut self, oid: Oid, mainline: Option<u32>) {
+        let result = Repository::open(&self.cur_dir).and_then(|repo| {
+            let head = repo.head()?.peel_to_commit()?.id();
+            let commit = repo.find_commit(oid)?;
+            let mut options = RevertOptions::new();
+            if let Some(mainline) = mainline {
+                options.mainline(mainline);
+            }
+            repo.revert(&commit, Some(&mut options))?;
+            let conflicts = conflicted_paths(&repo.index()?);
+            Ok((head, conflicts))
+        });
+
+        match result {
+            Ok((head, conflicts)) => {
+                let clean = conflicts.is_empty();
+                self.operation = Some(Operation {
+                    kind: OperationKind::Revert,
+                    queue: Default::default(),
+                    current: Some(oid),
+                    orig_head: Some(head),
+                    conflicts,
+                    resumable: true,
+                });
+                self.after_operation_step();
+                if clean {
+                    self.open_revert_dialog();
+                }
+            }
+            Err(e) => self.operation_error = Some(format!("revert stopped: {}", e.message())),
+        }
+    }
+
+    /// Open the commit dialog with the message libgit2 prepared for the
+    /// revert in progress.
+    fn open_revert_dialog(&mut self) {
+        let Some(oid) = self.operation.as_ref().and_then(|op| op.current) else {
+            return;
+        };
+        let message = Repository::open(&self.cur_dir)
+            .ok()
+            .and_then(|repo| {
+                let prepared = repo
+                    .message()
+                    .map(|m| cleanup_message(&m))
+                    .ok()
+                    .filter(|m| !m.is_empty());
+                prepared.or_else(|| {
+                    repo.find_commit(oid).ok().map(|c| {
+                        format!(
+                            "Revert \"{}\"\n\nThis reverts commit {}.",
+                            c.summary().unwrap_or(""),
+                            oid
+                        )
+                    })
+                })
+            })
+            .unwrap_or_default();
+        self.open_commit_dialog();
+        self.set_commit_message(&message);
+    }
+
+    /// Ask how to reset the current branch to the highlighted commit.
+    pub fn open_reset_dialog(&mut self) {
+        let busy = Repository::open(&self.cur_dir).and_then(|repo| {
+            if self.operation.is_some() || repo.state() != RepositoryState::Clean {
+                return Err(Error::from_str(
+                    "another operation is in progress; continue or abort it first",
+                ));
+            }
+            Ok(())
+        });
+        if let Err(e) = busy {
+            self.reset_error = Some(e.message().to_string());
+            return;
+        }
+        self.reset_target = self
+            .commit_graph_state
+            .selected()
+            .and_then(|i| self.commit_graph_oids.get(i))
+            .and_then(|oid| Oid::from_str(oid).ok());
+    }
+
+    pub fn cancel_reset(&mut self) {
+        self.reset_target = None;
+        self.reset_hard_confirm = None;
+    }
+
+    /// Reset to the chosen commit. Soft and mixed happen at once; hard
+    /// first lists the uncommitted changes it would destroy.
+    pub fn choose_reset(&mut self, mode: ResetType) {
+        let Some(target) = self.reset_target else {
+            return;
+        };
+        if mode != ResetType::Hard {
+            self.reset_target = None;
+            self.reset_to(target, mode);
+            return;
+        }
+
+        let lost = Repository::open(&self.cur_dir).and_then(|repo| {
+            let mut options = StatusOptions::new();
+            options.include_untracked(false).include_ignored(false);
+            let statuses = repo.statuses(Some(&mut options))?;
+            Ok(statuses
+                .iter()
+                .filter_map(|entry| {
+                    let status = entry.status();
+                    let code = if status.intersects(Status::INDEX_NEW) {
+                        'A'
+                    } else if status.intersects(Status::INDEX_DELETED | Status::WT_DELETED) {
+                        'D'
+                    } else if status.is_conflicted() {
+                        'U'
+                    } else {
+                        'M'
+                    };
+                    entry.path().map(|path| format!("{} {}", code, path))
+                })
+                .collect::<Vec<_>>())
+        });
+        match lost {
+            Ok(lost) => self.reset_hard_confirm = Some(lost),
+            Err(e) => {
+                self.reset_target = None;
+                self.reset_error = Some(e.message().to_string());
+            }
+        }
+    }
+
+    /// Run the hard reset once the list of lost changes was accepted.
+    pub fn confirm_hard_reset(&mut self) {
+        let Some(target) = self.reset_target.take() else {
+            return;
+        };
+        self.reset_hard_confirm = None;
+        self.reset_to(target, ResetType::Hard);
+    }
+
+    /// Put the branch back where it was before the last reset.
+    pub fn undo_reset(&mut self) {
+        if let Some(done) = self.reset_done.take() {
+            self.reset_to(done.previous, done.mode);
+        }
+    }
+
+    fn reset_to(&mut self, target: Oid, mode: ResetType) {
+        let result = Repository::open(&self.cur_dir).and_then(|repo| {
+            // A mixed or hard reset would clear the cherry-pick or rebase
+            // state from under the operation banner.
+            if self.operation.is_some() || repo.state() != RepositoryState::Clean {
+                return Err(Error::from_str(
+                    "another operation is in progress; continue or abort it first",
+                ));
+            }
+            let previous = repo.head()?.peel_to_commit()?;
+            let commit = repo.find_commit(target)?;
+            repo.reset(commit.as_object(), mode, None)?;
+            Ok(ResetDone {
+                mode,
+                previous: previous.id(),
+                previous_summary: previous.summary().unwrap_or("").to_string(),
+                target,
+            })
+        });
+
+        match result {
+            Ok(done) => self.reset_done = Some(done),
+            Err(e) => self.reset_error = Some(e.message().to_string()),
+        }
+        self.scan_git();
+        self.refresh_repository_view();
+    }
+
+    /// Plan an interactive rebase of the highlighted commit and everything
+    /// after it up to HEAD.
+    pub fn open_rebase_planner(&mut self) {
+        let Some(from) = self
+            .commit_graph_state
+            .selected()
+            .and_then(|i| self.commit_graph_oids.get(i))
+            .and_then(|oid| Oid::from_str(oid).ok())
+        else {
+            return;
+        };
+        let result = Repository::open(&self.cur_dir).and_then(|repo| {
+            if self.operation.is_some() || repo.state() != RepositoryState::Clean {
+                return Err(Error::from_str(
+                    "another operation is in progress; continue or abort it first",
+                ));
+            }
+            RebasePlan::load(&repo, from)
+        });
+
+        match result {
+            Ok(plan) => {
+                self.rebase_plan = Some(plan);
+                self.rebase_plan_state.select(Some(0));
+                self.rebase_plan_error = None;
+            }
+            Err(e) => self.operation_error = Some(e.message().to_string()),
+        }
+    }
+
+    pub fn close_rebase_planner(&mut self) {
+        self.rebase_plan = None;
+        self.rebase_plan_error = None;
+    }
+
+    pub fn rebase_plan_next(&mut self) {
+        let len = self.rebase_plan.as_ref().map_or(0, |p| p.steps.len());
+        let i = self.rebase_plan_state.selected().unwrap_or(0);
+        if i + 1 < len {
+            self.rebase_plan_state.select(Some(i + 1));
+        }
+    }
+
+    pub fn rebase_plan_previous(&mut self) {
+        let i = self.rebase_plan_state.selected().unwrap_or(0);
+        self.rebase_plan_state.select(Some(i.saturating_sub(1)));
+    }
+
+    /// Move the highlighted step one place earlier (`up`) or later.
+    pub fn rebase_plan_move(&mut self, up: bool) {
+        let (Some(plan), Some(i)) = (self.rebase_plan.as_mut(), self.rebase_plan_state.selected())
+        else {
+            return;
+        };
+        let j = plan.move_step(i, up);
+        self.rebase_plan_state.select(Some(j));
+        self.rebase_plan_error = None;
+    }
+
+    pub fn rebase_plan_set(&mut self, action: RebaseAction) {
+        let (Some(plan), Some(i)) = (self.rebase_plan.as_mut(), self.rebase_plan_state.selected())
+        else {
+            return;
+        };
+        if let Some(step) = plan.steps.get_mut(i) {
+            step.action = action;
+        }
+        self.rebase_plan_error = None;
+    }
+
+    /// Start the planned rebase and run it until it stops or finishes.
+    pub fn start_rebase(&mut self) {
+        let Some(plan) = self.rebase_plan.clone() else {
+            return;
+        };
+        let result = Repository::open(&self.cur_dir).and_then(|repo| {
+            let head = repo.head()?.peel_to_commit()?.id();
+            plan.start(&repo)?;
+            Ok(head)
+        });
+
+        match result {
+            Ok(head) => {
+                self.close_rebase_planner();
+                self.rebase_actions = plan.actions();
+                self.rebase_stop = None;
+                self.operation = Some(Operation::new(OperationKind::Rebase, Vec::new(), head));
+                self.run_rebase();
+            }
+            Err(e) => self.rebase_plan_error = Some(e.message().to_string()),
+        }
+    }
+
+    /// Rebase the `fixup!` and `squash!` commits on the current branch into
+    /// the commits they name.
+    pub fn autosquash(&mut self) {
+        let result = Repository::open(&self.cur_dir).and_then(|repo| {
+            if self.operation.is_some() || repo.state() != RepositoryState::Clean {
+                return Err(Error::from_str(
+                    "another operation is in progress; continue or abort it first",
+                ));
+            }
+            let Some(base) = autosquash_base(&repo)? else {
+                return Err(Error::from_str(
+                    "no fixup! or squash! commits with a target on this branch",
+                ));
+            };
+            let mut plan = RebasePlan::load(&repo, base)?;
+            plan.autosquash();
+            Ok(plan)
+        });
+
+        match result {
+            Ok(plan) => {
+                self.rebase_plan = Some(plan);
+                self.rebase_plan_state.select(Some(0));
+                self.rebase_plan_error = None;
+                self.start_rebase();
+            }
+            Err(e) => self.operation_error = Some(e.message().to_string()),
+        }
+    }
+
+    /// Rewrite the message of the highlighted commit. A commit that is
+    /// already on a remote-tracking branch asks for confirmation first.
+    pub fn open_reword_dialog(&mut self) {
+        let Some(target) = self
+            .commit_graph_state
+            .selected()
+            .and_then(|i| self.commit_graph_oids.get(i))
+            .and_then(|oid| Oid::from_str(oid).ok())
+        else {
+            return;
         };
+        let result = Repository::open(&self.cur_dir).and_then(|repo| {
+            if self.operation.is_some() || repo.state() != RepositoryState::Clean {
+                return Err(Error::from_str(
+                    "another operation is in progress; continue or abort it first",
+                ));
+            }
+            // Checks that the commit is on HEAD with no merges after it.
+            let plan = RebasePlan::replay(&repo, target, target)?;
+            if !plan.steps.is_empty() {
+                let mut options = StatusOptions::new();
+                options.include_untracked(false);
+                if !repo.statuses(Some(&mut options))?.is_empty() {
+                    return Err(Error::from_str(
+                        "commit or stash your changes before rewording an older commit",
+                    ));
+                }
+            }
+            Ok(Self::published_on(&repo, target))
+        });
+
+        match result {
+            Ok(Some(remote)) => self.reword_confirm = Some((target, remote)),
+            Ok(None) => self.reword(target, None),
+            Err(e) => self.operation_error = Some(e.message().to_string()),
+        }
+    }
+
+    /// Go ahead with rewording a published commit.
+    pub fn confirm_reword(&mut self) {
+        if let Some((target, remote)) = self.reword_confirm.take() {
+            self.reword(target, Some(remote));
+        }
+    }
+
+    fn reword(&mut self, target: Oid, published: Option<String>) {
+        let message = Repository::open(&self.cur_dir).and_then(|repo| {
+            repo.find_commit(target)
+                .map(|c| c.message().map(String::from))
+        });
+        match message {
+            Ok(message) => {
+                self.open_commit_dialog();
+                self.set_commit_message(message.as_deref().unwrap_or(""));
+                self.commit_reword = Some(target);
+                self.commit_amend_pushed = published;
+            }
+            Err(e) => self.operation_error = Some(e.message().to_string()),
+        }
+    }
+
+    /// The first remote-tracking branch that already contains `oid`.
+    fn published_on(repo: &Repository, oid: Oid) -> Option<String> {
+        let refs = repo.references_glob("refs/remotes/*").ok()?;
+        for reference in refs.flatten() {
+            let Some(tip) = reference.target() else {
+                continue;
+            };
+            if tip == oid || repo.graph_descendant_of(tip, oid).unwrap_or(false) {
+                return reference.shorthand().map(String::from);
+            }
+        }
+        None
+    }
+
+    /// Write the reworded commit and replay its descendants onto it.
+    fn finish_reword(&mut self, target: Oid) {
+        let result = (|| -> Result<Option<(Oid, RebasePlan)>, CommitError> {
+            let repo = Repository::open(&self.cur_dir)?;
+            let original = repo.find_commit(target)?;
+            let head = repo.head()?.peel_to_commit()?.id();
+            let parents: Vec<Commit> = original.parents().collect();
+            let parent_refs: Vec<&Commit> = parents.iter().collect();
+            let message = self.commit_message();
+            let reworded = Self::create_commit(
+                &repo,
+                &original.author(),
+                &repo.signature()?,
+                &message,
+                &original.tree()?,
+                &parent_refs,
+            )?;
+
+            if target == head {
+                Self::update_head(&repo, reworded, &format!("reword: {}", self.commit_summary))?;
+                return Ok(None);
+            }
+            let plan = RebasePlan::replay(&repo, target, reworded)?;
+            plan.start(&repo)?;
+            Ok(Some((head, plan)))
+        })();
+
+        match result {
+            Ok(replay) => {
+                self.close_commit_dialog();
+                match replay {
+                    Some((head, plan)) => {
+                        self.rebase_actions = plan.actions();
+                        self.rebase_stop = None;
+                        self.operation =
+                            Some(Operation::new(OperationKind::Rebase, Vec::new(), head));
+                        self.run_rebase();
+                    }
+                    None => self.after_operation_step(),
+                }
+            }
+            Err(e) => {
+                let e = self.commit_create_failed(e);
+                self.commit_failed(&e);
+            }
+        }
+    }
+
+    /// Apply the rebase's remaining steps until one stops or it is done.
+    fn run_rebase(&mut self) {
+        let result = Repository::open(&self.cur_dir).and_then(|repo| {
+            loop {
+                let mut rebase = repo.open_rebase(None)?;
+                let Some(step) = rebase.next() else {
+                    rebase.finish(Some(&repo.signature()?))?;
+                    self.operation = None;
+                    self.rebase_actions.clear();
+                    return Ok(());
+                };
+                let oid = step?.id();
+
+                let done = rebase.operation_current().map_or(0, |i| i + 1);
+                let queue = (done..rebase.len())
+                    .filter_map(|i| rebase.nth(i).map(|op| op.id()))
+                    .collect();
+                if let Some(op) = self.operation.as_mut() {
+                    op.current = Some(oid);
+                    op.queue = queue;
+                }
 
-        let commit_tree = match commit.tree() {
-            Ok(t) => t,
-            Err(_) => return,
-        };
+                let index = repo.index()?;
+                if index.has_conflicts() {
+                    if let Some(op) = self.operation.as_mut() {
+                        op.conflicts = conflicted_paths(&index);
+                    }
+                    self.rebase_stop = Some(RebaseStop::Conflicts);
+                    return Ok(());
+                }
+                if !self.apply_rebase_step(&repo, oid)? {
+                    return Ok(());
+                }
+            }
+        });
 
-        let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+        if let Err(e) = result {
+            self.operation_error = Some(format!("rebase stopped: {}", e.message()));
+        }
+        self.after_operation_step();
+    }
 
-        let diff = match repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit_tree), None) {
-            Ok(d) => d,
-            Err(_) => return,
-        };
+    /// Commit the change replayed for `oid` as its action asks. Returns
+    /// false when the rebase stops here for the user.
+    fn apply_rebase_step(&mut self, repo: &Repository, oid: Oid) -> Result<bool, Error> {
+        let original = repo.find_commit(oid)?;
+        let message = original.message().unwrap_or("").to_string();
+        let action = self
+            .rebase_actions
+            .get(&oid)
+            .copied()
+            .unwrap_or(RebaseAction::Pick);
+
+        match action {
+            RebaseAction::Pick => {
+                Self::commit_replayed(repo, &original, &message, "rebase (pick)")?;
+                Ok(true)
+            }
+            // Dropped commits are left out of the todo list when the rebase
+            // starts, so one turning up here means the plan was lost.
+            RebaseAction::Drop => Err(Error::from_str(&format!(
+                "{} was planned to be dropped; skip it or abort the rebase",
+                &oid.to_string()[..7]
+            ))),
+            RebaseAction::Edit => {
+                Self::commit_replayed(repo, &original, &message, "rebase (edit)")?;
+                self.rebase_stop = Some(RebaseStop::Edit);
+                Ok(false)
+            }
+            RebaseAction::Fixup => {
+                let head = repo.head()?.peel_to_commit()?;
+                let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+                let parents: Vec<Commit> = head.parents().collect();
+                let parent_refs: Vec<&Commit> = parents.iter().collect();
+                let new = Self::create_commit(
+                    repo,
+                    &head.author(),
+                    &repo.signature()?,
+                    head.message().unwrap_or(""),
+                    &tree,
+                    &parent_refs,
+                )?;
+                let summary = original.summary().unwrap_or("");
+                Self::update_head(repo, new, &format!("rebase (fixup): {}", summary))?;
+                Ok(true)
+            }
+            RebaseAction::Reword => {
+                self.rebase_stop = Some(RebaseStop::Message);
+                self.open_commit_dialog();
+                self.set_commit_message(&message);
+                self.commit_author_from = Some(oid);
+                Ok(false)
+            }
+            RebaseAction::Squash => {
+                let head = repo.head()?.peel_to_commit()?;
+                // A "squash! subject" line only named the target.
+                let message = match fixup_target(original.summary().unwrap_or("")) {
+                    Some(_) => message.split_once('\n').map_or("", |(_, body)| body.trim()),
+                    None => message.trim_end(),
+                };
+                let head_message = head.message().unwrap_or("").trim_end();
+                let combined = if message.is_empty() {
+                    head_message.to_string()
+                } else {
+                    format!("{}\n\n{}", head_message, message)
+                };
+                self.rebase_stop = Some(RebaseStop::Message);
+                self.open_commit_dialog();
+                self.set_commit_message(&combined);
+                self.commit_amend = true;
+                Ok(false)
+            }
+        }
+    }
 
-        let mut lines: Vec<DiffLine> = Vec::new();
-        let _ = diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
-            let content = String::from_utf8_lossy(line.content()).to_string();
-            let kind = match line.origin() {
-                '+' => DiffLineKind::Add,
-                '-' => DiffLineKind::Delete,
-                'H' | 'F' => DiffLineKind::Header,
-                _ => DiffLineKind::Context,
-            };
-            let kind = if content.starts_with("@@") {
-                DiffLineKind::Header
-            } else {
-                kind
-            };
-            lines.push(DiffLine {
-                kind,
-                content: content.trim_end_matches('\n').to_string(),
-            });
-            true
+    /// Leave out the rebase step that stopped, throwing away its changes.
+    pub fn skip_operation(&mut self) {
+        let Some(op) = self.operation.clone() else {
+            return;
+        };
+        if !op.resumable {
+            self.operation_error = Some(NOT_RESUMABLE.to_string());
+            return;
+        }
+        if op.kind == OperationKind::Revert {
+            self.abort_operation();
+            return;
+        }
+        // Only the paths the step touched go back to HEAD; a hard reset
+        // would take other changes with it, and the rebase's state too.
+        let result = Repository::open(&self.cur_dir).and_then(|repo| {
+            let head = repo.head()?.peel_to_commit()?;
+            Self::reset_merge(&repo, &head)?;
+            if op.kind == OperationKind::CherryPick {
+                repo.cleanup_state()?;
+            }
+            Ok(())
         });
+        if let Err(e) = result {
+            self.operation_error = Some(e.message().to_string());
+            return;
+        }
 
-        self.diff_content = lines;
-        self.selected_file = None;
-
-        let short = &oid_str[..7.min(oid_str.len())];
-        if let Some(label) = self.commit_graph.get(index) {
-            self.commit_diff_label = Some(format!("Commit {}", label.trim_start_matches("* ")));
+        if op.kind == OperationKind::Rebase {
+            if self.rebase_stop == Some(RebaseStop::Message) {
+                self.close_commit_dialog();
+            }
+            self.rebase_stop = None;
+            self.run_rebase();
         } else {
-            self.commit_diff_label = Some(format!("Commit {}", short));
+            if let Some(op) = self.operation.as_mut() {
+                op.current = None;
+                op.conflicts.clear();
+            }
+            self.run_operation();
         }
     }
 
-    pub fn open_commit_dialog(&mut self) {
-        self.show_commit_dialog = true;
-        self.commit_summary.clear();
-        self.commit_description.clear();
-        self.commit_focus_description = false;
-        self.commit_summary_cursor = 0;
-        self.commit_description_cursor = 0;
-        self.commit_summary_scroll = 0;
-        self.commit_description_scroll = 0;
-    }
+    /// Work through the operation's queue until it is empty or a step
+    /// stops on conflicts.
+    fn run_operation(&mut self) {
+        let result = Repository::open(&self.cur_dir).and_then(|repo| {
+            while let Some(op) = self.operation.as_mut() {
+                let Some(oid) = op.queue.pop_front() else {
+                    self.operation = None;
+                    break;
+                };
+                op.current = Some(oid);
+
+                let commit = repo.find_commit(oid)?;
+                repo.cherrypick(&commit, None)?;
+                let index = repo.index()?;
+                if index.has_conflicts() {
+                    op.conflicts = conflicted_paths(&index);
+                    break;
+                }
+                let message = commit.message().unwrap_or("").to_string();
+                Self::commit_picked(&repo, &commit, &message)?;
+            }
+            Ok(())
+        });
 
-    pub fn close_commit_dialog(&mut self) {
-        self.show_commit_dialog = false;
-        self.commit_summary.clear();
-        self.commit_description.clear();
-        self.commit_focus_description = false;
-        self.commit_summary_cursor = 0;
-        self.commit_description_cursor = 0;
-        self.commit_summary_scroll = 0;
-        self.commit_description_scroll = 0;
+        if let Err(e) = result {
+            let kind = self
+                .operation
+                .take()
+                .map(|op| op.kind.name())
+                .unwrap_or("operation");
+            self.operation_error = Some(format!("{} stopped: {}", kind, e.message()));
+        }
+        self.after_operation_step();
     }
 
-    fn active_commit_text_mut(&mut self) -> (&mut String, &mut usize) {
-        if self.commit_focus_description {
-            (
-                &mut self.commit_description,
-                &mut self.commit_description_cursor,
-            )
-        } else {
-            (&mut self.commit_summary, &mut self.commit_summary_cursor)
-        }
+    /// Commit the index on HEAD as `original`, keeping its author.
+    fn commit_picked(repo: &Repository, original: &Commit, message: &str) -> Result<(), Error> {
+        Self::commit_replayed(repo, original, message, "cherry-pick")?;
+        repo.cleanup_state()
     }
 
-    pub fn commit_message_insert(&mut self, c: char) {
-        let (text, cursor) = self.active_commit_text_mut();
-        text.insert(*cursor, c);
-        *cursor += c.len_utf8();
+    /// Commit the index on HEAD as a copy of `original`, keeping its
+    /// author. Nothing is committed when HEAD already has the change.
+    fn commit_replayed(
+        repo: &Repository,
+        original: &Commit,
+        message: &str,
+        reflog: &str,
+    ) -> Result<(), Error> {
+        let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+        let head = repo.head()?.peel_to_commit()?;
+
+        // A change that is already on HEAD leaves nothing to commit.
+        if tree.id() != head.tree_id() {
+            let committer = repo.signature()?;
+            let oid = Self::create_commit(
+                repo,
+                &original.author(),
+                &committer,
+                message,
+                &tree,
+                &[&head],
+            )?;
+            let summary = original.summary().unwrap_or("");
+            Self::update_head(repo, oid, &format!("{}: {}", reflog, summary))?;
+        }
+        Ok(())
     }
 
-    pub fn commit_message_backspace(&mut self) {
-        let (text, cursor) = self.active_commit_text_mut();
-        if *cursor == 0 {
+    /// Commit the resolved conflicts and carry on with the operation.
+    pub fn continue_operation(&mut self) {
+        let Some(op) = self.operation.clone() else {
+            return;
+        };
+        if !op.resumable {
+            self.operation_error = Some(NOT_RESUMABLE.to_string());
             return;
         }
-        let mut start = *cursor - 1;
-        while !text.is_char_boundary(start) {
-            start -= 1;
+        let result = Repository::open(&self.cur_dir).and_then(|repo| {
+            let index = repo.index()?;
+            if index.has_conflicts() {
+                return Err(Error::from_str(&format!(
+                    "resolve and stage every conflict first: {}",
+                    conflicted_paths(&index).join(", ")
+                )));
+            }
+            if op.kind != OperationKind::CherryPick {
+                return Ok(());
+            }
+            let Some(current) = op.current else {
+                return repo.cleanup_state();
+            };
+            let original = repo.find_commit(current)?;
+            // libgit2 leaves the message in MERGE_MSG, with the conflict
+            // list commented out.
+            let message = repo
+                .message()
+                .map(|m| cleanup_message(&m))
+                .ok()
+                .filter(|m| !m.is_empty())
+                .unwrap_or_else(|| original.message().unwrap_or("").to_string());
+            Self::commit_picked(&repo, &original, &message)
+        });
+
+        match result {
+            // A revert is committed from the dialog, where its message
+            // can be edited.
+            Ok(()) if op.kind == OperationKind::Revert => {
+                if let Some(op) = self.operation.as_mut() {
+                    op.conflicts.clear();
+                }
+                self.open_revert_dialog();
+            }
+            Ok(()) if op.kind == OperationKind::Rebase => {
+                if let Some(op) = self.operation.as_mut() {
+                    op.conflicts.clear();
+                }
+                self.continue_rebase(op.current);
+            }
+            Ok(()) => {
+                if let Some(op) = self.operation.as_mut() {
+                    op.current = None;
+                    op.conflicts.clear();
+                }
+                self.run_operation();
+            }
+            Err(e) => self.operation_error = Some(e.message().to_string()),
         }
-        text.remove(start);
-        *cursor = start;
     }
 
-    pub fn commit_message_delete(&mut self) {
-        let (text, cursor) = self.active_commit_text_mut();
-        if *cursor >= text.len() {
+    /// Finish the step the rebase stopped at, then carry on. An edit was
+    /// already committed; anything else is committed as its action asks.
+    fn continue_rebase(&mut self, current: Option<Oid>) {
+        let stop = self.rebase_stop.take();
+        let Some(current) = current.filter(|_| stop != Some(RebaseStop::Edit)) else {
+            self.run_rebase();
             return;
+        };
+
+        let result =
+            Repository::open(&self.cur_dir).and_then(|repo| self.apply_rebase_step(&repo, current));
+        match result {
+            Ok(true) => self.run_rebase(),
+            Ok(false) => self.after_operation_step(),
+            Err(e) => {
+                self.rebase_stop = stop;
+                self.operation_error = Some(e.message().to_string());
+            }
         }
-        text.remove(*cursor);
     }
 
-    pub fn commit_cursor_left(&mut self) {
-        let (text, cursor) = self.active_commit_text_mut();
-        if *cursor == 0 {
+    /// Throw away the operation's changes and return to where HEAD was
+    /// before it started.
+    pub fn abort_operation(&mut self) {
+        let Some(op) = self.operation.clone() else {
+            return;
+        };
+        if !op.resumable {
+            self.operation_error = Some(NOT_RESUMABLE.to_string());
             return;
         }
-        let mut pos = *cursor - 1;
-        while !text.is_char_boundary(pos) {
-            pos -= 1;
-        }
-        *cursor = pos;
-    }
+        let result = Repository::open(&self.cur_dir).and_then(|repo| {
+            // libgit2 puts the branch and HEAD back itself.
+            if op.kind == OperationKind::Rebase {
+                return repo.open_rebase(None)?.abort();
+            }
+            // The branch also goes back over commits already made by the
+            // operation.
+            let head = repo.head()?.peel_to_commit()?;
+            let target = match op.orig_head {
+                Some(oid) => repo.find_commit(oid)?,
+                None => head.clone(),
+            };
+            Self::reset_merge(&repo, &target)?;
+            if target.id() != head.id() {
+                let reflog = format!("{}: abort", op.kind.name());
+                Self::update_head(&repo, target.id(), &reflog)?;
+            }
+            repo.cleanup_state()
+        });
 
-    pub fn commit_cursor_right(&mut self) {
-        let (text, cursor) = self.active_commit_text_mut();
-        if *cursor >= text.len() {
-            return;
+        if let Err(e) = result {
+            self.operation_error = Some(e.message().to_string());
         }
-        let mut pos = *cursor + 1;
-        while pos < text.len() && !text.is_char_boundary(pos) {
-            pos += 1;
+        if self.rebase_stop.take() == Some(RebaseStop::Message) {
+            self.close_commit_dialog();
         }
-        *cursor = pos;
+        self.rebase_actions.clear();
+        self.operation = None;
+        self.after_operation_step();
     }
 
-    pub fn commit_cursor_home(&mut self) {
-        if self.commit_focus_description {
-            self.commit_description_cursor = 0;
+    /// Refuse to start an operation over uncommitted changes to tracked
+    /// files, which an abort could not tell apart from its own.
+    fn ensure_clean_tree(repo: &Repository) -> Result<(), Error> {
+        let mut options = StatusOptions::new();
+        options.include_untracked(false).include_ignored(false);
+        if repo.statuses(Some(&mut options))?.is_empty() {
+            Ok(())
         } else {
-            self.commit_summary_cursor = 0;
+            Err(Error::from_str("commit or stash your changes first"))
         }
     }
 
-    pub fn commit_cursor_end(&mut self) {
-        if self.commit_focus_description {
-            self.commit_description_cursor = self.commit_description.len();
-        } else {
-            self.commit_summary_cursor = self.commit_summary.len();
+    /// Put back, as they are in `target`, only the paths an operation
+    /// touched, like `git reset --merge`: conflicts, what it staged, and
+    /// what it committed since `target`. Unrelated changes in the work
+    /// tree are kept.
+    fn reset_merge(repo: &Repository, target: &Commit) -> Result<(), Error> {
+        let head_tree = repo.head()?.peel_to_tree()?;
+        let target_tree = target.tree()?;
+        let mut index = repo.index()?;
+
+        let mut paths = conflicted_paths(&index);
+        let staged = repo.diff_tree_to_index(Some(&head_tree), Some(&index), None)?;
+        let committed = repo.diff_tree_to_tree(Some(&target_tree), Some(&head_tree), None)?;
+        for diff in [&staged, &committed] {
+            for delta in diff.deltas() {
+                for file in [delta.old_file(), delta.new_file()] {
+                    if let Some(path) = file.path().map(|p| p.display().to_string())
+                        && !paths.contains(&path)
+                    {
+                        paths.push(path);
+                    }
+                }
+            }
+        }
+        if paths.is_empty() {
+            return Ok(());
         }
-    }
 
-    pub fn toggle_commit_focus(&mut self) {
-        self.commit_focus_description = !self.commit_focus_description;
+        let mut checkout = build::CheckoutBuilder::new();
+        checkout.force().remove_untracked(true);
+        for path in &paths {
+            checkout.path(path);
+        }
+        repo.checkout_tree(target_tree.as_object(), Some(&mut checkout))?;
+
+        for path in &paths {
+            let path = Path::new(path);
+            let _ = index.conflict_remove(path);
+            match target_tree.get_path(path) {
+                Ok(entry) => {
+                    let blob = repo.find_blob(entry.id())?;
+                    index.add_frombuffer(
+                        &IndexEntry {
+                            ctime: IndexTime::new(0, 0),
+                            mtime: IndexTime::new(0, 0),
+                            dev: 0,
+                            ino: 0,
+                            mode: entry.filemode() as u32,
+                            uid: 0,
+                            gid: 0,
+                            file_size: blob.size() as u32,
+                            id: entry.id(),
+                            flags: 0,
+                            flags_extended: 0,
+                            path: path.to_string_lossy().as_bytes().to_vec(),
+                        },
+                        blob.content(),
+                    )?;
+                }
+                Err(_) => {
+                    let _ = index.remove_path(path);
+                }
+            }
+        }
+        index.write()
     }
 
-    pub fn commit(&mut self) -> Result<Oid, Error> {
-        let repo = Repository::open(&self.cur_dir)?;
-        let mut index = repo.index()?;
-
-        let tree_oid = index.write_tree()?;
-        let tree = repo.find_tree(tree_oid)?;
-
-        let signature = repo.signature()?;
+    fn after_operation_step(&mut self) {
+        if self
+            .operation
+            .as_ref()
+            .is_some_and(|op| op.kind == OperationKind::Rebase)
+            && let Err(e) = Repository::open(&self.cur_dir)
+                .and_then(|repo| rebase::save_stop(&repo, self.rebase_stop))
+        {
+            self.operation_error = Some(e.message().to_string());
+        }
+        self.scan_git();
+        self.refresh_repository_view();
+    }
 
-        let message = if self.commit_description.is_empty() {
-            self.commit_summary.clone()
-        } else {
-            format!("{}\n\n{}", self.commit_summary, self.commit_description)
+    /// Pick up an operation left in progress by another tool, and forget
+    /// ours when it was finished outside fastgit.
+    fn refresh_operation(&mut self) {
+        let Ok(repo) = Repository::open(&self.cur_dir) else {
+            return;
         };
-
-        let mut parents = Vec::new();
-        if let Ok(head) = repo.head() {
-            if let Ok(commit) = head.peel_to_commit() {
-                parents.push(commit);
+        match self.operation.as_mut() {
+            None => {
+                self.operation = Operation::from_repo(&repo);
+                if self
+                    .operation
+                    .as_ref()
+                    .is_some_and(|op| op.kind == OperationKind::Rebase && op.resumable)
+                {
+                    self.rebase_actions = rebase::load_actions(&repo).unwrap_or_default();
+                    self.rebase_stop = rebase::load_stop(&repo);
+                }
             }
+            Some(op) if op.current.is_some() => {
+                if repo.state() == RepositoryState::Clean {
+                    self.operation = None;
+                } else if let Ok(index) = repo.index() {
+                    op.conflicts = conflicted_paths(&index);
+                }
+            }
+            Some(_) => {}
         }
+    }
 
-        let parent_refs: Vec<&Commit> = parents.iter().collect();
-
-        let oid = repo.commit(
-            Some("HEAD"),
-            &signature,
-            &signature,
-            &message,
-            &tree,
-            &parent_refs,
-        )?;
-
-        self.staged_count = 0;
-
-        Ok(oid)
+    /// Point HEAD, or the branch it refers to, at `oid`.
+    fn update_head(repo: &Repository, oid: Oid, reflog: &str) -> Result<(), Error> {
+        let head = repo.find_reference("HEAD")?;
+        match head.symbolic_target() {
+            Some(name) => {
+                repo.reference(name, oid, true, reflog)?;
+            }
+            None => repo.set_head_detached(oid)?,
+        }
+        Ok(())
     }
 
     pub fn start_push(&mut self) {
@@ -784,21 +3840,32 @@ impl App {
         let (tx, rx) = mpsc::channel();
         self.push_result_rx = Some(rx);
 
+        let pre_push = Repository::open(&cur_dir)
+            .ok()
+            .map(|repo| Hooks::for_repo(&repo))
+            .filter(|hooks| hooks.find("pre-push").is_some())
+            .map(|hooks| (hooks, self.begin_hooks(HookStage::Push)));
+
         std::thread::spawn(move || {
-            let result = Self::push_repo_sync(&cur_dir, preferred_remote, current_branch);
+            let result = Self::push_repo_sync(&cur_dir, preferred_remote, current_branch, pre_push);
             let _ = tx.send(result.map_err(|e| e.to_string()));
         });
     }
 
     pub fn check_push_result(&mut self) {
-        if let Some(rx) = &self.push_result_rx {
-            if let Ok(result) = rx.try_recv() {
-                self.push_in_progress = false;
-                self.push_result_rx = None;
-                match result {
-                    Ok(_) => self.push_success_open = true,
-                    Err(err) => self.push_error = Some(err),
-                }
+        // The pre-push result is sent before the push result, so draining
+        // it first tells whether a failure was the hook's.
+        self.check_hook_result();
+
+        if let Some(rx) = &self.push_result_rx
+            && let Ok(result) = rx.try_recv()
+        {
+            self.push_in_progress = false;
+            self.push_result_rx = None;
+            match result {
+                Ok(_) => self.push_success_open = true,
+                Err(_) if self.hook_error.is_some() => {}
+                Err(err) => self.push_error = Some(err),
             }
         }
     }
@@ -821,16 +3888,59 @@ impl App {
     }
 
     pub fn check_pull_result(&mut self) {
-        if let Some(rx) = &self.pull_result_rx {
-            if let Ok(result) = rx.try_recv() {
-                self.pull_in_progress = false;
-                self.pull_result_rx = None;
-
-                if result.is_ok() {
-                    self.scan_git();
-                    self.refresh_repository_view();
+        if let Some(rx) = &self.pull_result_rx
+            && let Ok(result) = rx.try_recv()
+        {
+            self.pull_in_progress = false;
+            self.pull_result_rx = None;
+
+            if result.is_ok() {
+                self.scan_git();
+                self.refresh_repository_view();
+            }
+        }
+    }
+
+    /// Queue `git verify-commit` for `oid` on the signature worker, starting
+    /// it on first use. The worker skips requests that were superseded
+    /// while it was busy, so scrolling through the graph stays cheap.
+    fn request_signature(&mut self, oid: String, kind: String) {
+        if self.signature_tx.is_none() {
+            let (request_tx, request_rx) = mpsc::channel::<(String, String, String)>();
+            let (result_tx, result_rx) = mpsc::channel();
+            self.signature_tx = Some(request_tx);
+            self.signature_rx = Some(result_rx);
+
+            std::thread::spawn(move || {
+                while let Ok(mut request) = request_rx.recv() {
+                    while let Ok(newer) = request_rx.try_recv() {
+                        request = newer;
+                    }
+                    let (dir, oid, kind) = request;
+                    let status = verify_commit(Path::new(&dir), &oid, &kind);
+                    if result_tx.send((oid, status)).is_err() {
+                        break;
+                    }
                 }
+            });
+        }
+
+        if let Some(tx) = &self.signature_tx {
+            let _ = tx.send((self.cur_dir.clone(), oid, kind));
+        }
+    }
+
+    pub fn check_signature_result(&mut self) {
+        let Some(rx) = &self.signature_rx else {
+            return;
+        };
+        while let Ok((oid, status)) = rx.try_recv() {
+            if let Some(detail) = self.commit_detail.as_mut()
+                && detail.oid == oid
+            {
+                detail.signature = status.clone();
             }
+            self.signature_cache.insert(oid, status);
         }
     }
 
@@ -838,6 +3948,7 @@ impl App {
         cur_dir: &str,
         preferred_remote: Option<String>,
         current_branch: Option<String>,
+        pre_push: Option<(Hooks, mpsc::Sender<HookEvent>)>,
     ) -> Result<(), Error> {
         let default_remote = Self::resolve_default_remote(cur_dir, current_branch.as_deref());
         let remote_to_use = preferred_remote.or(default_remote);
@@ -845,6 +3956,30 @@ impl App {
         let mut command = std::process::Command::new("git");
         command.arg("push");
 
+        // pre-push runs here so its output reaches the hook dialog; `git push`
+        // is then told not to run it a second time. Without a remote to name
+        // the hook is left to git.
+        if let Some((hooks, tx)) = pre_push {
+            let result = match &remote_to_use {
+                Some(remote) => {
+                    let outcome =
+                        Self::run_pre_push(cur_dir, &hooks, remote, current_branch.as_deref(), &tx);
+                    if outcome.is_ok() {
+                        command.arg("--no-verify");
+                    }
+                    outcome
+                }
+                None => Ok(()),
+            };
+            let _ = tx.send(HookEvent::Finished(
+                result
+                    .as_ref()
+                    .map(|_| None)
+                    .map_err(|e| e.message().to_string()),
+            ));
+            result?;
+        }
+
         if let Some(remote) = &remote_to_use {
             command.arg(remote);
 
@@ -866,6 +4001,39 @@ impl App {
         Ok(())
     }
 
+    /// Run pre-push with git's arguments (remote name and URL) and one
+    /// `<local ref> <local sha> <remote ref> <remote sha>` line for the branch.
+    fn run_pre_push(
+        cur_dir: &str,
+        hooks: &Hooks,
+        remote: &str,
+        branch: Option<&str>,
+        tx: &mpsc::Sender<HookEvent>,
+    ) -> Result<(), Error> {
+        let repo = Repository::open(cur_dir)?;
+        let url = repo
+            .find_remote(remote)
+            .ok()
+            .and_then(|r| r.pushurl().or(r.url()).map(|u| u.to_string()))
+            .unwrap_or_else(|| remote.to_string());
+
+        let mut stdin = String::new();
+        if let Some(branch) = branch {
+            let local_ref = format!("refs/heads/{}", branch);
+            if let Ok(local) = repo.refname_to_id(&local_ref) {
+                let remote_sha = repo
+                    .refname_to_id(&format!("refs/remotes/{}/{}", remote, branch))
+                    .unwrap_or_else(|_| Oid::zero());
+                stdin = format!("{} {} {} {}\n", local_ref, local, local_ref, remote_sha);
+            }
+        }
+
+        hooks.run_announced("pre-push", &[remote, &url], Some(&stdin), &mut |event| {
+            let _ = tx.send(event);
+        })?;
+        Ok(())
+    }
+
     fn resolve_default_remote(cur_dir: &str, current_branch: Option<&str>) -> Option<String> {
         if let Some(branch) = current_branch {
             let config_key = format!("branch.{}.remote", branch);
@@ -949,13 +4117,13 @@ impl App {
         if !self.has_git {
             return;
         }
-        if let Ok(repo) = Repository::open(&self.cur_dir) {
-            if let Ok(rmts) = repo.remotes() {
-                for name in rmts.iter().flatten() {
-                    if let Ok(remote) = repo.find_remote(name) {
-                        let url = remote.url().unwrap_or("").to_string();
-                        self.remotes.push((name.to_string(), url));
-                    }
+        if let Ok(repo) = Repository::open(&self.cur_dir)
+            && let Ok(rmts) = repo.remotes()
+        {
+            for name in rmts.iter().flatten() {
+                if let Ok(remote) = repo.find_remote(name) {
+                    let url = remote.url().unwrap_or("").to_string();
+                    self.remotes.push((name.to_string(), url));
                 }
             }
         }
@@ -1030,18 +4198,18 @@ impl App {
     }
 
     pub fn remove_selected_remote(&mut self) -> Result<(), Error> {
-        if let Some(idx) = self.remote_state.selected() {
-            if let Some((name, _)) = self.remotes.get(idx) {
-                let name = name.clone();
-                let repo = Repository::open(&self.cur_dir)?;
-                repo.remote_delete(&name)?;
-                self.load_remotes();
-                if self.remotes.is_empty() {
-                    self.remote_state.select(None);
-                } else {
-                    let new_idx = idx.min(self.remotes.len() - 1);
-                    self.remote_state.select(Some(new_idx));
-                }
+        if let Some(idx) = self.remote_state.selected()
+            && let Some((name, _)) = self.remotes.get(idx)
+        {
+            let name = name.clone();
+            let repo = Repository::open(&self.cur_dir)?;
+            repo.remote_delete(&name)?;
+            self.load_remotes();
+            if self.remotes.is_empty() {
+                self.remote_state.select(None);
+            } else {
+                let new_idx = idx.min(self.remotes.len() - 1);
+                self.remote_state.select(Some(new_idx));
             }
         }
         Ok(())
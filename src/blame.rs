@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Fitrian Musya
+
+use git2::{BlameOptions, Error, Oid, Repository};
+
+/// One line of a blamed file and the commit that last changed it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlameLine {
+    pub oid: Oid,
+    pub author: String,
+    pub time: i64,
+    /// The file's path in that commit, which differs after a rename.
+    pub orig_path: String,
+    pub content: String,
+}
+
+impl BlameLine {
+    pub fn short_id(&self) -> String {
+        self.oid.to_string()[..7].to_string()
+    }
+}
+
+/// A file blamed as of one revision.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blame {
+    pub path: String,
+    /// The revision blamed; `None` is HEAD.
+    pub rev: Option<Oid>,
+    pub lines: Vec<BlameLine>,
+}
+
+impl Blame {
+    /// Blame `path` as it is in `rev`, or in HEAD.
+    pub fn load(repo: &Repository, path: &str, rev: Option<Oid>) -> Result<Self, Error> {
+        let commit = match rev {
+            Some(oid) => repo.find_commit(oid)?,
+            None => repo.head()?.peel_to_commit()?,
+        };
+        let entry = commit.tree()?.get_path(std::path::Path::new(path))?;
+        let blob = repo.find_blob(entry.id())?;
+        if blob.is_binary() {
+            return Err(Error::from_str(&format!("{} is a binary file", path)));
+        }
+
+        let mut options = BlameOptions::new();
+        options.newest_commit(commit.id());
+        let blame = repo.blame_file(std::path::Path::new(path), Some(&mut options))?;
+
+        let text = String::from_utf8_lossy(blob.content());
+        let mut lines = Vec::new();
+        for (i, content) in text.lines().enumerate() {
+            let Some(hunk) = blame.get_line(i + 1) else {
+                continue;
+            };
+            let sig = hunk.final_signature();
+            lines.push(BlameLine {
+                oid: hunk.final_commit_id(),
+                author: sig.name().unwrap_or("").to_string(),
+                time: sig.when().seconds(),
+                orig_path: hunk
+                    .path()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|| path.to_string()),
+                content: content.to_string(),
+            });
+        }
+
+        Ok(Self {
+            path: path.to_string(),
+            rev,
+            lines,
+        })
+    }
+}
@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Fitrian Musya
+
+use std::path::Path;
+
+use git2::{Commit, Diff, Repository};
+
+/// Who made a commit, and when.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Person {
+    pub name: String,
+    pub email: String,
+    pub time: i64,
+    pub offset_minutes: i32,
+}
+
+impl Person {
+    fn from_signature(sig: &git2::Signature) -> Self {
+        Self {
+            name: sig.name().unwrap_or("").to_string(),
+            email: sig.email().unwrap_or("").to_string(),
+            time: sig.when().seconds(),
+            offset_minutes: sig.when().offset_minutes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignatureStatus {
+    Unsigned,
+    /// Signed with the given kind; `git verify-commit` has not answered yet.
+    Checking {
+        kind: String,
+    },
+    /// Signed, with the signature kind (GPG, SSH, X.509) and whether
+    /// `git verify-commit` accepted it, plus its first line of output.
+    Signed {
+        kind: String,
+        verified: bool,
+        detail: String,
+    },
+}
+
+/// Everything the header above a commit's diff shows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitDetail {
+    pub oid: String,
+    pub author: Person,
+    pub committer: Person,
+    pub parents: Vec<String>,
+    /// The message without its trailer block.
+    pub message: String,
+    pub trailers: Vec<(String, String)>,
+    pub signature: SignatureStatus,
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+impl CommitDetail {
+    /// Collect the header for `commit`, whose patch against its first
+    /// parent is `diff`.
+    pub fn load(repo: &Repository, commit: &Commit, diff: &Diff) -> Self {
+        let message = commit.message().unwrap_or("").trim_end().to_string();
+        let trailers: Vec<(String, String)> = git2::message_trailers_strs(&message)
+            .map(|t| {
+                t.iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // git only reads trailers from the last paragraph, so that is the
+        // part to drop from the body.
+        let message = match message.rsplit_once("\n\n") {
+            Some((body, _)) if !trailers.is_empty() => body.trim_end().to_string(),
+            _ => message,
+        };
+
+        let (files_changed, insertions, deletions) = diff
+            .stats()
+            .map(|s| (s.files_changed(), s.insertions(), s.deletions()))
+            .unwrap_or((0, 0, 0));
+
+        Self {
+            oid: commit.id().to_string(),
+            author: Person::from_signature(&commit.author()),
+            committer: Person::from_signature(&commit.committer()),
+            parents: commit.parent_ids().map(|p| p.to_string()).collect(),
+            message,
+            trailers,
+            signature: signature_kind(repo, commit).map_or(SignatureStatus::Unsigned, |kind| {
+                SignatureStatus::Checking { kind }
+            }),
+            files_changed,
+            insertions,
+            deletions,
+        }
+    }
+}
+
+/// The kind of the commit's signature, or `None` when it is unsigned.
+fn signature_kind(repo: &Repository, commit: &Commit) -> Option<String> {
+    let (signature, _) = repo.extract_signature(&commit.id(), None).ok()?;
+    let signature = signature.as_str().unwrap_or("");
+
+    let kind = if signature.contains("BEGIN SSH SIGNATURE") {
+        "SSH"
+    } else if signature.contains("BEGIN SIGNED MESSAGE") {
+        "X.509"
+    } else {
+        "GPG"
+    };
+    Some(kind.to_string())
+}
+
+/// Let `git verify-commit` judge a signature of `kind`, so the user's gpg
+/// keyring and `gpg.ssh.allowedSignersFile` are honored. This runs gpg or
+/// ssh-keygen, so it is called off the UI thread.
+pub fn verify_commit(workdir: &Path, oid: &str, kind: &str) -> SignatureStatus {
+    let (verified, detail) = match std::process::Command::new("git")
+        .arg("verify-commit")
+        .arg(oid)
+        .current_dir(workdir)
+        .output()
+    {
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let detail = stderr
+                .lines()
+                .find(|l| l.contains("Good") || l.contains("BAD") || l.contains("error"))
+                .or_else(|| stderr.lines().next())
+                .unwrap_or("")
+                .trim()
+                .to_string();
+            (output.status.success(), detail)
+        }
+        Err(e) => (false, e.to_string()),
+    };
+
+    SignatureStatus::Signed {
+        kind: kind.to_string(),
+        verified,
+        detail,
+    }
+}
@@ -71,6 +71,7 @@ pub struct FileTree {
     pub root: FileNode,
     pub state: ListState,
     pub items: Vec<(PathBuf, usize, bool)>,
+    pub marked: HashSet<PathBuf>,
 }
 
 impl FileTree {
@@ -82,6 +83,7 @@ impl FileTree {
             root,
             state: ListState::default(),
             items: Vec::new(),
+            marked: HashSet::new(),
         }
     }
 
@@ -111,6 +113,10 @@ impl FileTree {
         Self::apply_expanded_dirs(&mut self.root, &expanded_dirs);
         self.update_items();
 
+        let root = &self.root;
+        self.marked
+            .retain(|path| Self::find_node(root, path).is_some());
+
         if self.items.is_empty() {
             self.state.select(None);
             return;
@@ -235,6 +241,34 @@ impl FileTree {
         }
     }
 
+    /// Mark or unmark the selected row for a batch operation.
+    pub fn toggle_mark(&mut self) {
+        if let Some(i) = self.state.selected()
+            && let Some((path, _, _)) = self.items.get(i)
+            && !self.marked.remove(path)
+        {
+            self.marked.insert(path.clone());
+        }
+    }
+
+    pub fn is_marked(&self, path: &Path) -> bool {
+        self.marked.contains(path)
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+    }
+
+    fn find_node<'a>(node: &'a FileNode, path: &Path) -> Option<&'a FileNode> {
+        if node.path == path {
+            return Some(node);
+        }
+        node.children
+            .iter()
+            .filter(|child| path.starts_with(&child.path))
+            .find_map(|child| Self::find_node(child, path))
+    }
+
     fn find_node_recursive<'a>(node: &'a mut FileNode, path: &Path) -> Option<&'a mut FileNode> {
         if node.path == path {
             return Some(node);
@@ -0,0 +1,365 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Fitrian Musya
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use git2::{Commit, Oid, Repository};
+
+/// One character of the graph column, with the color slot of the lane it
+/// belongs to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GraphCell {
+    pub ch: char,
+    pub color: usize,
+}
+
+/// Incremental lane assignment for a topologically sorted history.
+///
+/// Every lane waits for the commit it will reach next. Feeding commits in
+/// order through `push` yields one row of cells per commit: `●` for the
+/// commit, `│` for lanes passing by, and box-drawing joins where lanes fork
+/// off into a new parent or come back together at a shared one.
+#[derive(Debug, Clone, Default)]
+pub struct GraphLayout {
+    lanes: Vec<Option<Oid>>,
+    colors: Vec<usize>,
+    next_color: usize,
+}
+
+impl GraphLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn allocate(&mut self, skip: usize) -> usize {
+        let free = self
+            .lanes
+            .iter()
+            .enumerate()
+            .position(|(i, lane)| i != skip && lane.is_none());
+
+        let lane = match free {
+            Some(i) => i,
+            None => {
+                self.lanes.push(None);
+                self.colors.push(0);
+                self.lanes.len() - 1
+            }
+        };
+        self.colors[lane] = self.next_color;
+        self.next_color += 1;
+        lane
+    }
+
+    /// Lay out `oid` and return its row.
+    pub fn push(&mut self, oid: Oid, parents: &[Oid]) -> Vec<GraphCell> {
+        let before: Vec<bool> = self.lanes.iter().map(Option::is_some).collect();
+
+        let mut joined: Vec<usize> = self
+            .lanes
+            .iter()
+            .enumerate()
+            .filter(|(_, lane)| **lane == Some(oid))
+            .map(|(i, _)| i)
+            .collect();
+
+        let mine = match joined.first() {
+            Some(&lane) => lane,
+            None => self.allocate(usize::MAX),
+        };
+        joined.retain(|&lane| lane != mine);
+
+        // Other lanes that were waiting for this commit end here.
+        for &lane in &joined {
+            self.lanes[lane] = None;
+        }
+
+        let mut links = joined.clone();
+        self.lanes[mine] = parents.first().copied();
+
+        for parent in parents.iter().skip(1) {
+            let lane = match self.lanes.iter().position(|l| *l == Some(*parent)) {
+                Some(lane) => lane,
+                None => {
+                    let lane = self.allocate(mine);
+                    self.lanes[lane] = Some(*parent);
+                    lane
+                }
+            };
+            if !links.contains(&lane) {
+                links.push(lane);
+            }
+        }
+
+        let after: Vec<bool> = self.lanes.iter().map(Option::is_some).collect();
+        let row = self.render(mine, &links, &before, &after);
+
+        while self.lanes.last() == Some(&None) {
+            self.lanes.pop();
+            self.colors.pop();
+        }
+
+        row
+    }
+
+    fn render(
+        &self,
+        mine: usize,
+        links: &[usize],
+        before: &[bool],
+        after: &[bool],
+    ) -> Vec<GraphCell> {
+        let width = before.len().max(after.len()).max(mine + 1);
+        let up = |i: usize| before.get(i).copied().unwrap_or(false);
+        let down = |i: usize| after.get(i).copied().unwrap_or(false);
+
+        let left = links.iter().copied().filter(|&l| l < mine).min();
+        let right = links.iter().copied().filter(|&l| l > mine).max();
+        let color_of = |i: usize| self.colors.get(i).copied().unwrap_or(0);
+
+        // The lane a horizontal segment at `i` is heading for.
+        let target = |i: usize| -> Option<usize> {
+            if let Some(l) = left
+                && i >= l
+                && i < mine
+            {
+                return links.iter().copied().filter(|&x| x <= i).max();
+            }
+            if let Some(r) = right
+                && i > mine
+                && i <= r
+            {
+                return links.iter().copied().filter(|&x| x >= i).min();
+            }
+            None
+        };
+
+        let mut row = Vec::with_capacity(width * 2);
+        for i in 0..width {
+            let ch = if i == mine {
+                '●'
+            } else if links.contains(&i) {
+                // A link with more links beyond it has the line running
+                // through it instead of ending there.
+                let through = Some(i) != left && Some(i) != right;
+                match (through, i > mine, up(i), down(i)) {
+                    (true, _, true, true) => '┼',
+                    (true, _, true, false) => '┴',
+                    (true, _, false, _) => '┬',
+                    (false, true, true, true) => '┤',
+                    (false, true, true, false) => '╯',
+                    (false, true, false, _) => '╮',
+                    (false, false, true, true) => '├',
+                    (false, false, true, false) => '╰',
+                    (false, false, false, _) => '╭',
+                }
+            } else if target(i).is_some() {
+                if up(i) && down(i) { '┼' } else { '─' }
+            } else if up(i) || down(i) {
+                '│'
+            } else {
+                ' '
+            };
+
+            let color = if i != mine && !links.contains(&i) && target(i).is_some() && !up(i) {
+                target(i).map(color_of).unwrap_or(0)
+            } else {
+                color_of(i)
+            };
+            row.push(GraphCell { ch, color });
+
+            // The gap after the cell carries the horizontal line while it is
+            // inside the span between the commit and its outermost links.
+            let span_lo = left.unwrap_or(mine);
+            let span_hi = right.unwrap_or(mine);
+            if i >= span_lo && i < span_hi {
+                let heading = if i >= mine { target(i + 1) } else { target(i) };
+                row.push(GraphCell {
+                    ch: '─',
+                    color: heading.map(color_of).unwrap_or(0),
+                });
+            } else {
+                row.push(GraphCell { ch: ' ', color: 0 });
+            }
+        }
+
+        while row.last().is_some_and(|c| c.ch == ' ') {
+            row.pop();
+        }
+        row
+    }
+}
+
+/// A resumable newest-first history walk.
+///
+/// Unlike a libgit2 revwalk it owns no repository handle, so it can live on
+/// `App` and hand out one page at a time. Commits with the same timestamp
+/// come out in the order they were queued, which keeps children ahead of
+/// their parents when a whole branch was committed within one second.
+///
+/// A commit is also held back while any child the walk has already found
+/// is still queued, so a parent whose clock ran ahead of its child's still
+/// comes after it.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryWalk {
+    queue: BinaryHeap<(i64, Reverse<u64>, Oid)>,
+    seen: HashSet<Oid>,
+    /// Found children not yet handed out, by parent.
+    pending: HashMap<Oid, usize>,
+    /// Popped too early, with their timestamps, until `pending` drops to 0.
+    held: HashMap<Oid, i64>,
+    seq: u64,
+}
+
+impl HistoryWalk {
+    pub fn new(repo: &Repository, tips: &[Oid]) -> Self {
+        let mut walk = Self::default();
+        for tip in tips {
+            walk.enqueue(repo, *tip);
+        }
+        walk
+    }
+
+    fn enqueue(&mut self, repo: &Repository, oid: Oid) {
+        if !self.seen.insert(oid) {
+            return;
+        }
+        if let Ok(commit) = repo.find_commit(oid) {
+            for parent in commit.parent_ids() {
+                *self.pending.entry(parent).or_default() += 1;
+            }
+            self.push(commit.time().seconds(), oid);
+        }
+    }
+
+    fn push(&mut self, time: i64, oid: Oid) {
+        self.queue.push((time, Reverse(self.seq), oid));
+        self.seq += 1;
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// The next commit, newest first; its parents join the queue.
+    pub fn next_commit<'r>(&mut self, repo: &'r Repository) -> Option<Commit<'r>> {
+        while let Some((time, _, oid)) = self.queue.pop() {
+            if self.pending.get(&oid).is_some_and(|&n| n > 0) {
+                self.held.insert(oid, time);
+                continue;
+            }
+            let Ok(commit) = repo.find_commit(oid) else {
+                continue;
+            };
+            for parent in commit.parent_ids() {
+                if let Some(n) = self.pending.get_mut(&parent) {
+                    *n = n.saturating_sub(1);
+                    if *n == 0
+                        && let Some(time) = self.held.remove(&parent)
+                    {
+                        self.push(time, parent);
+                    }
+                }
+                self.enqueue(repo, parent);
+            }
+            return Some(commit);
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RefKind {
+    Head,
+    Local,
+    Remote,
+    Tag,
+}
+
+/// A ref name decorating a commit row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefLabel {
+    pub name: String,
+    pub kind: RefKind,
+}
+
+/// The ref namespaces the graph shows: branches, remote branches and tags.
+/// Others, such as `refs/stash` and `refs/notes/*`, are not history.
+pub const GRAPH_REF_GLOBS: [&str; 3] = ["refs/heads/*", "refs/remotes/*", "refs/tags/*"];
+
+/// Branch, remote-branch and tag names by the commit they point at.
+///
+/// The branch HEAD is on becomes a single `HEAD -> name` label; a detached
+/// HEAD gets a bare `HEAD`. Labels are ordered HEAD, local, remote, tag.
+pub fn ref_labels(repo: &Repository) -> HashMap<Oid, Vec<RefLabel>> {
+    let mut labels: HashMap<Oid, Vec<RefLabel>> = HashMap::new();
+
+    let head = repo.head().ok();
+    let head_branch = head
+        .as_ref()
+        .filter(|h| h.is_branch())
+        .and_then(|h| h.name())
+        .map(|n| n.to_string());
+    if let Some(head) = &head
+        && !head.is_branch()
+        && let Ok(commit) = head.peel_to_commit()
+    {
+        labels.entry(commit.id()).or_default().push(RefLabel {
+            name: "HEAD".to_string(),
+            kind: RefKind::Head,
+        });
+    }
+
+    for glob in GRAPH_REF_GLOBS {
+        let Ok(references) = repo.references_glob(glob) else {
+            continue;
+        };
+        for reference in references.flatten() {
+            // Symbolic refs such as origin/HEAD only repeat another label.
+            if reference.target().is_none() {
+                continue;
+            }
+            let (Some(name), Some(short)) = (reference.name(), reference.shorthand()) else {
+                continue;
+            };
+
+            let kind = if Some(name) == head_branch.as_deref() {
+                RefKind::Head
+            } else if reference.is_branch() {
+                RefKind::Local
+            } else if reference.is_remote() {
+                RefKind::Remote
+            } else if reference.is_tag() {
+                RefKind::Tag
+            } else {
+                continue;
+            };
+
+            let Ok(commit) = reference.peel_to_commit() else {
+                continue;
+            };
+            let name = match kind {
+                RefKind::Head => format!("HEAD -> {}", short),
+                RefKind::Tag => format!("tag: {}", short),
+                _ => short.to_string(),
+            };
+            labels
+                .entry(commit.id())
+                .or_default()
+                .push(RefLabel { name, kind });
+        }
+    }
+
+    let rank = |kind: &RefKind| match kind {
+        RefKind::Head => 0,
+        RefKind::Local => 1,
+        RefKind::Remote => 2,
+        RefKind::Tag => 3,
+    };
+    for list in labels.values_mut() {
+        list.sort_by_key(|label| rank(&label.kind));
+    }
+
+    labels
+}
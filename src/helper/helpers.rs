@@ -60,10 +60,9 @@ impl Helper {
             || st.contains(Status::INDEX_DELETED)
             || st.contains(Status::INDEX_RENAMED)
             || st.contains(Status::INDEX_TYPECHANGE)
+            || st.contains(Status::WT_NEW)
         {
             Color::Green
-        } else if st.contains(Status::WT_NEW) {
-            Color::Green
         } else if st.contains(Status::WT_MODIFIED) {
             Color::Yellow
         } else if st.contains(Status::WT_DELETED) {
@@ -75,6 +74,54 @@ impl Helper {
         }
     }
 
+    /// Format a duration in seconds as a short age such as `5m ago`.
+    pub fn format_age(&self, seconds: i64) -> String {
+        const MINUTE: i64 = 60;
+        const HOUR: i64 = 60 * MINUTE;
+        const DAY: i64 = 24 * HOUR;
+
+        match seconds.max(0) {
+            s if s < MINUTE => "just now".to_string(),
+            s if s < HOUR => format!("{}m ago", s / MINUTE),
+            s if s < DAY => format!("{}h ago", s / HOUR),
+            s if s < 30 * DAY => format!("{}d ago", s / DAY),
+            s if s < 365 * DAY => format!("{}mo ago", s / (30 * DAY)),
+            s => format!("{}y ago", s / (365 * DAY)),
+        }
+    }
+
+    /// Format a git timestamp in its own timezone, e.g. `2026-10-17 14:03 +0200`.
+    pub fn format_date(&self, seconds: i64, offset_minutes: i32) -> String {
+        let local = seconds + offset_minutes as i64 * 60;
+        let days = local.div_euclid(86_400);
+        let secs = local.rem_euclid(86_400);
+
+        // Days since 1970-01-01 to a proleptic Gregorian date.
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        let sign = if offset_minutes < 0 { '-' } else { '+' };
+        let offset = offset_minutes.unsigned_abs();
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02} {}{:02}{:02}",
+            year,
+            month,
+            day,
+            secs / 3600,
+            secs % 3600 / 60,
+            sign,
+            offset / 60,
+            offset % 60
+        )
+    }
+
     /// Draw a centered dialog box with customizable type, title, and content
     ///
     /// # Arguments
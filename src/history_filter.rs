@@ -0,0 +1,349 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Fitrian Musya
+
+use std::collections::HashMap;
+
+use git2::{Commit, Delta, DiffFile, Error, Oid, Repository};
+use regex::Regex;
+
+/// How the commit message is matched.
+#[derive(Debug, Clone)]
+pub enum MessageMatch {
+    /// Case-insensitive substring.
+    Text(String),
+    Regex(Regex),
+}
+
+/// A pickaxe search over each commit's patch against its parent.
+#[derive(Debug, Clone)]
+pub enum Pickaxe {
+    /// `git log -S`: the number of occurrences of the string changed.
+    Count(String),
+    /// `git log -G`: an added or removed line matches the regex.
+    Lines(Regex),
+}
+
+/// Predicates narrowing the commit graph, parsed from the filter prompt.
+///
+/// The query is a list of `key:value` terms; values with spaces can be
+/// quoted. Words without a key are matched against the message.
+///
+/// ```text
+/// author:"Jane Doe" since:2026-01-01 until:2026-03-31 path:src/app.rs fix crash
+/// re:^feat(\(ui\))?: path:README.md
+/// S:parse_date G:"fn \w+_filter"
+/// follow:src/app.rs
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    pub query: String,
+    pub author: Option<String>,
+    pub message: Option<MessageMatch>,
+    /// Earliest committer time, in seconds since the epoch.
+    pub since: Option<i64>,
+    /// Latest committer time; `until:` includes the whole day.
+    pub until: Option<i64>,
+    pub path: Option<String>,
+    pub pickaxe: Option<Pickaxe>,
+    /// `follow:PATH`, like `git log --follow`: the file's history across
+    /// renames. The path is tracked as the walk goes back in time.
+    pub follow: Option<String>,
+    /// The followed file's path at each commit that matched.
+    pub follow_paths: HashMap<Oid, String>,
+    following: Option<String>,
+}
+
+impl HistoryFilter {
+    /// Parse a query; a blank one means no filter.
+    pub fn parse(query: &str) -> Result<Option<Self>, Error> {
+        let mut filter = Self {
+            query: query.trim().to_string(),
+            ..Self::default()
+        };
+        let mut words: Vec<String> = Vec::new();
+
+        for term in split_terms(query) {
+            let Some((key, value)) = term.split_once(':') else {
+                words.push(term);
+                continue;
+            };
+            match key {
+                "author" => filter.author = Some(value.to_lowercase()),
+                "since" => filter.since = Some(parse_date(value)?),
+                "until" => filter.until = Some(parse_date(value)? + 86_399),
+                "path" => filter.path = Some(value.trim_matches('/').to_string()),
+                "follow" if !value.is_empty() => {
+                    filter.follow = Some(value.trim_matches('/').to_string());
+                }
+                "re" => {
+                    let re = Regex::new(value).map_err(|e| Error::from_str(&e.to_string()))?;
+                    filter.message = Some(MessageMatch::Regex(re));
+                }
+                "msg" => filter.message = Some(MessageMatch::Text(value.to_lowercase())),
+                "S" if !value.is_empty() => {
+                    filter.pickaxe = Some(Pickaxe::Count(value.to_string()));
+                }
+                "G" => {
+                    let re = Regex::new(value).map_err(|e| Error::from_str(&e.to_string()))?;
+                    filter.pickaxe = Some(Pickaxe::Lines(re));
+                }
+                _ => words.push(term),
+            }
+        }
+
+        if !words.is_empty() {
+            if filter.message.is_some() {
+                return Err(Error::from_str(
+                    "use either re:/msg: or plain words for the message",
+                ));
+            }
+            filter.message = Some(MessageMatch::Text(words.join(" ").to_lowercase()));
+        }
+
+        if filter.author.is_none()
+            && filter.message.is_none()
+            && filter.since.is_none()
+            && filter.until.is_none()
+            && filter.path.is_none()
+            && filter.pickaxe.is_none()
+            && filter.follow.is_none()
+        {
+            return Ok(None);
+        }
+        filter.restart();
+        Ok(Some(filter))
+    }
+
+    /// Whether matching diffs each commit's tree, which makes scanning
+    /// far slower than for the other predicates.
+    pub fn needs_diff(&self) -> bool {
+        self.path.is_some() || self.pickaxe.is_some() || self.follow.is_some()
+    }
+
+    /// Forget what an earlier walk learned about renames.
+    pub fn restart(&mut self) {
+        self.following = self.follow.clone();
+        self.follow_paths.clear();
+    }
+
+    /// Whether `commit` passes the filter. Commits must be fed newest
+    /// first, so a followed file can be traced back through its renames.
+    pub fn matches(&mut self, repo: &Repository, commit: &Commit) -> bool {
+        // Rename tracking has to see every commit, even ones the other
+        // predicates reject.
+        if let Some(path) = self.following.as_mut() {
+            let at = path.clone();
+            if !follow_step(repo, commit, path) {
+                return false;
+            }
+            self.follow_paths.insert(commit.id(), at);
+        }
+
+        if let Some(author) = &self.author {
+            let who = commit.author();
+            let ident = format!(
+                "{} <{}>",
+                who.name().unwrap_or(""),
+                who.email().unwrap_or("")
+            );
+            if !ident.to_lowercase().contains(author) {
+                return false;
+            }
+        }
+
+        let time = commit.time().seconds();
+        if self.since.is_some_and(|since| time < since) || self.until.is_some_and(|u| time > u) {
+            return false;
+        }
+
+        if let Some(message) = &self.message {
+            let text = commit.message().unwrap_or("");
+            let found = match message {
+                MessageMatch::Text(needle) => text.to_lowercase().contains(needle),
+                MessageMatch::Regex(re) => re.is_match(text),
+            };
+            if !found {
+                return false;
+            }
+        }
+
+        if let Some(path) = &self.path
+            && !touches_path(repo, commit, path)
+        {
+            return false;
+        }
+
+        match &self.pickaxe {
+            Some(pickaxe) => pickaxe_matches(repo, commit, pickaxe),
+            None => true,
+        }
+    }
+
+    /// Whether a diff line is one the pickaxe search was looking for.
+    pub fn pickaxe_hit(&self, line: &str) -> bool {
+        match &self.pickaxe {
+            Some(Pickaxe::Count(needle)) => line.contains(needle.as_str()),
+            Some(Pickaxe::Lines(re)) => re.is_match(line),
+            None => false,
+        }
+    }
+}
+
+/// Run the pickaxe over the commit's patch against its parent. Merges are
+/// skipped, as `git log -S` does without `-m`.
+fn pickaxe_matches(repo: &Repository, commit: &Commit, pickaxe: &Pickaxe) -> bool {
+    if commit.parent_count() > 1 {
+        return false;
+    }
+    let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+    let Ok(tree) = commit.tree() else {
+        return false;
+    };
+    let Ok(diff) = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None) else {
+        return false;
+    };
+
+    match pickaxe {
+        Pickaxe::Count(needle) => {
+            let count = |file: DiffFile| {
+                if file.id().is_zero() {
+                    return 0;
+                }
+                repo.find_blob(file.id())
+                    .ok()
+                    .filter(|blob| !blob.is_binary())
+                    .map(|blob| {
+                        String::from_utf8_lossy(blob.content())
+                            .matches(needle.as_str())
+                            .count()
+                    })
+                    .unwrap_or(0)
+            };
+            diff.deltas()
+                .any(|delta| count(delta.old_file()) != count(delta.new_file()))
+        }
+        Pickaxe::Lines(re) => {
+            let mut hit = false;
+            // Returning false from the callback stops the walk at the
+            // first hit; the resulting "user cancelled" error is expected.
+            let _ = diff.foreach(
+                &mut |_, _| true,
+                None,
+                None,
+                Some(&mut |_, _, line| {
+                    if matches!(line.origin(), '+' | '-')
+                        && re.is_match(
+                            String::from_utf8_lossy(line.content()).trim_end_matches('\n'),
+                        )
+                    {
+                        hit = true;
+                    }
+                    !hit
+                }),
+            );
+            hit
+        }
+    }
+}
+
+/// Whether `commit` touched the followed file at `path`. When the commit
+/// created it by renaming another file, `path` becomes the old name.
+fn follow_step(repo: &Repository, commit: &Commit, path: &mut String) -> bool {
+    if !touches_path(repo, commit, path) {
+        return false;
+    }
+    // Renames are only looked for against the first parent of ordinary
+    // commits; a merge that changed the file keeps its name.
+    if commit.parent_count() != 1 {
+        return true;
+    }
+
+    let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+    let Ok(tree) = commit.tree() else {
+        return true;
+    };
+    let Ok(mut diff) = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None) else {
+        return true;
+    };
+    let _ = diff.find_similar(None);
+
+    let renamed_from = diff
+        .deltas()
+        .find(|d| d.new_file().path() == Some(std::path::Path::new(path.as_str())))
+        .filter(|d| d.status() == Delta::Renamed)
+        .and_then(|d| d.old_file().path().map(|p| p.display().to_string()));
+    if let Some(old) = renamed_from {
+        *path = old;
+    }
+    true
+}
+
+/// Whether `commit` changed `path` (a file or a directory). Like
+/// `git log -- path`, a merge only counts when it differs from every
+/// parent.
+fn touches_path(repo: &Repository, commit: &Commit, path: &str) -> bool {
+    let entry_at = |commit: &Commit| {
+        commit
+            .tree()
+            .ok()
+            .and_then(|tree| tree.get_path(std::path::Path::new(path)).ok())
+            .map(|entry| entry.id())
+    };
+    let mine = entry_at(commit);
+
+    if commit.parent_count() == 0 {
+        return mine.is_some();
+    }
+    commit.parent_ids().all(|parent| {
+        repo.find_commit(parent)
+            .map(|p| entry_at(&p) != mine)
+            .unwrap_or(true)
+    })
+}
+
+/// Split on whitespace, keeping `"quoted values"` together.
+fn split_terms(query: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    terms.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        terms.push(current);
+    }
+    terms
+}
+
+/// Midnight UTC of a `YYYY-MM-DD` date, in seconds since the epoch.
+fn parse_date(value: &str) -> Result<i64, Error> {
+    let invalid = || Error::from_str(&format!("invalid date '{}', expected YYYY-MM-DD", value));
+    let parts: Vec<&str> = value.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return Err(invalid());
+    };
+    let year: i64 = year.parse().map_err(|_| invalid())?;
+    let month: i64 = month.parse().map_err(|_| invalid())?;
+    let day: i64 = day.parse().map_err(|_| invalid())?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+
+    // Proleptic Gregorian date to days since 1970-01-01.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Ok((era * 146_097 + doe - 719_468) * 86_400)
+}
@@ -0,0 +1,211 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Fitrian Musya
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use git2::{Error, Repository};
+
+/// Progress of a hook run, sent from the worker thread to the UI.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookEvent {
+    Started(String),
+    Output(String),
+    /// The run is over. On success the commit hooks hand back the final
+    /// commit message, which they are allowed to rewrite.
+    Finished(Result<Option<String>, String>),
+}
+
+/// The hooks directory of a repository.
+#[derive(Debug, Clone)]
+pub struct Hooks {
+    pub dir: PathBuf,
+    pub workdir: PathBuf,
+    pub git_dir: PathBuf,
+}
+
+impl Hooks {
+    /// Hooks live in `core.hooksPath` when it is set (relative paths are
+    /// taken from the work tree, like git does), otherwise in `.git/hooks`.
+    pub fn for_repo(repo: &Repository) -> Self {
+        let git_dir = repo.path().to_path_buf();
+        let workdir = repo
+            .workdir()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| git_dir.clone());
+
+        let dir = repo
+            .config()
+            .and_then(|cfg| cfg.get_path("core.hooksPath"))
+            .map(|path| {
+                if path.is_absolute() {
+                    path
+                } else {
+                    workdir.join(path)
+                }
+            })
+            .unwrap_or_else(|_| git_dir.join("hooks"));
+
+        Self {
+            dir,
+            workdir,
+            git_dir,
+        }
+    }
+
+    /// The hook called `name`, if it exists and can be executed.
+    pub fn find(&self, name: &str) -> Option<PathBuf> {
+        let path = self.dir.join(name);
+        let meta = std::fs::metadata(&path).ok()?;
+        if !meta.is_file() {
+            return None;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if meta.permissions().mode() & 0o111 == 0 {
+                return None;
+            }
+        }
+
+        Some(path)
+    }
+
+    pub fn any(&self, names: &[&str]) -> bool {
+        names.iter().any(|name| self.find(name).is_some())
+    }
+
+    /// Run hook `name` from the work tree, passing each line of its combined
+    /// stdout/stderr to `on_line` as it is printed.
+    ///
+    /// Returns `Ok(false)` when the hook does not exist and an error when it
+    /// could not be started or exited with a non-zero status.
+    pub fn run(
+        &self,
+        name: &str,
+        args: &[&str],
+        stdin: Option<&str>,
+        on_line: &mut dyn FnMut(String),
+    ) -> Result<bool, Error> {
+        let Some(path) = self.find(name) else {
+            return Ok(false);
+        };
+
+        let io_err = |e: std::io::Error| Error::from_str(&format!("{} hook: {}", name, e));
+        let (reader, writer) = std::io::pipe().map_err(io_err)?;
+
+        let mut child = {
+            let mut command = Command::new(&path);
+            command
+                .args(args)
+                .current_dir(&self.workdir)
+                .env("GIT_DIR", &self.git_dir)
+                .stdin(if stdin.is_some() {
+                    Stdio::piped()
+                } else {
+                    Stdio::null()
+                })
+                .stdout(writer.try_clone().map_err(io_err)?)
+                .stderr(writer);
+            // The command holds the write end of the pipe until it is
+            // dropped, and the reader below only sees EOF after that.
+            command.spawn().map_err(io_err)?
+        };
+
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            let _ = pipe.write_all(input.as_bytes());
+        }
+
+        for line in BufReader::new(reader).lines() {
+            match line {
+                Ok(line) => on_line(line),
+                Err(_) => break,
+            }
+        }
+
+        let status = child.wait().map_err(io_err)?;
+        if !status.success() {
+            let code = status
+                .code()
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "a signal".to_string());
+            return Err(Error::from_str(&format!(
+                "{} hook exited with {}",
+                name, code
+            )));
+        }
+
+        Ok(true)
+    }
+
+    /// Run pre-commit, prepare-commit-msg and commit-msg for `message`.
+    ///
+    /// The message goes through `.git/COMMIT_EDITMSG` so the hooks can edit
+    /// it; the cleaned-up result is returned. When `amend` is set,
+    /// prepare-commit-msg is told the message comes from `commit HEAD`, as
+    /// `git commit --amend` does.
+    pub fn run_commit_hooks(
+        &self,
+        message: &str,
+        amend: bool,
+        on_event: &mut dyn FnMut(HookEvent),
+    ) -> Result<String, Error> {
+        self.run_announced("pre-commit", &[], None, on_event)?;
+
+        let msg_file = self.git_dir.join("COMMIT_EDITMSG");
+        std::fs::write(&msg_file, format!("{}\n", message))
+            .map_err(|e| Error::from_str(&e.to_string()))?;
+        let msg_path = msg_file.display().to_string();
+
+        let source: &[&str] = if amend {
+            &[&msg_path, "commit", "HEAD"]
+        } else {
+            &[&msg_path, "message"]
+        };
+        self.run_announced("prepare-commit-msg", source, None, on_event)?;
+        self.run_announced("commit-msg", &[&msg_path], None, on_event)?;
+
+        let edited =
+            std::fs::read_to_string(&msg_file).map_err(|e| Error::from_str(&e.to_string()))?;
+        let cleaned = cleanup_message(&edited);
+        if cleaned.is_empty() {
+            return Err(Error::from_str(
+                "Aborting commit due to empty commit message",
+            ));
+        }
+
+        Ok(cleaned)
+    }
+
+    /// `run`, reporting the start of the hook and its output as events.
+    pub fn run_announced(
+        &self,
+        name: &str,
+        args: &[&str],
+        stdin: Option<&str>,
+        on_event: &mut dyn FnMut(HookEvent),
+    ) -> Result<bool, Error> {
+        if self.find(name).is_none() {
+            return Ok(false);
+        }
+        on_event(HookEvent::Started(name.to_string()));
+        self.run(name, args, stdin, &mut |line| {
+            on_event(HookEvent::Output(line))
+        })
+    }
+}
+
+/// Strip `#` comment lines and surrounding blank lines, as git's default
+/// `strip` cleanup mode does.
+pub fn cleanup_message(message: &str) -> String {
+    message
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
@@ -2,6 +2,15 @@
 // Copyright (c) 2026 Fitrian Musya
 
 pub mod app;
+pub mod blame;
+pub mod commit_detail;
 pub mod file_tree;
+pub mod graph;
 pub mod helper;
+pub mod history_filter;
+pub mod hooks;
+pub mod operation;
+pub mod patch;
+pub mod rebase;
+pub mod signing;
 pub mod ui;
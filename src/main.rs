@@ -5,16 +5,30 @@ use std::io;
 use std::time::{Duration, Instant};
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
+use git2::ResetType;
 use ratatui::{Terminal, backend::CrosstermBackend};
 
-use crate::{app::App, ui::draw_ui};
+use crate::{
+    app::{App, GraphTab},
+    rebase::RebaseAction,
+    ui::draw_ui,
+};
 mod app;
+mod blame;
+mod commit_detail;
 mod file_tree;
+mod graph;
 mod helper;
+mod history_filter;
+mod hooks;
+mod operation;
+mod patch;
+mod rebase;
+mod signing;
 mod ui;
 
 const AUTO_REFRESH_INTERVAL: Duration = Duration::from_secs(3);
@@ -46,7 +60,9 @@ async fn main() -> io::Result<()> {
 
         terminal.draw(|f| draw_ui(f, &mut app))?;
 
+        app.check_hook_result();
         app.check_push_result();
+        app.check_signature_result();
         app.check_pull_result();
 
         if !event::poll(Duration::from_millis(100))? {
@@ -59,21 +75,13 @@ async fn main() -> io::Result<()> {
                     match key.code {
                         KeyCode::Esc => app.close_commit_dialog(),
                         KeyCode::Tab => app.toggle_commit_focus(),
-                        KeyCode::Enter => {
-                            if !app.commit_summary.is_empty() {
-                                match app.commit() {
-                                    Ok(_oid) => {
-                                        app.scan_git();
-                                        app.refresh_repository_view();
-
-                                        app.commit_success_open = true;
-                                    }
-                                    Err(_e) => {
-                                        // TODO: Show error dialog
-                                    }
-                                }
-                            }
-                            app.close_commit_dialog();
+                        KeyCode::BackTab => app.toggle_commit_amend(),
+                        KeyCode::Enter => app.submit_commit(),
+                        KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.commit_skip_hooks = !app.commit_skip_hooks;
+                        }
+                        KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.toggle_commit_fixup();
                         }
                         KeyCode::Char(c) => app.commit_message_insert(c),
                         KeyCode::Backspace => app.commit_message_backspace(),
@@ -84,6 +92,10 @@ async fn main() -> io::Result<()> {
                         KeyCode::End => app.commit_cursor_end(),
                         _ => {}
                     }
+                } else if app.show_hook_dialog {
+                    app.dismiss_hook_dialog();
+                } else if app.commit_sign_error.is_some() || app.commit_error.is_some() {
+                    app.dismiss_commit_error();
                 } else if app.commit_success_open {
                     app.commit_success_open = false;
                 } else if app.push_success_open {
@@ -94,12 +106,113 @@ async fn main() -> io::Result<()> {
                     if let KeyCode::Char('q') = key.code {
                         app.commit_warning_open = false;
                     }
+                } else if app.show_stash_dialog {
+                    match key.code {
+                        KeyCode::Esc => app.close_stash_dialog(),
+                        KeyCode::Enter => app.confirm_stash_save(),
+                        KeyCode::Tab => app.stash_include_untracked = !app.stash_include_untracked,
+                        KeyCode::BackTab => app.stash_keep_index = !app.stash_keep_index,
+                        KeyCode::Char(c) => app.stash_message.push(c),
+                        KeyCode::Backspace => {
+                            app.stash_message.pop();
+                        }
+                        _ => {}
+                    }
+                } else if app.stash_drop_confirm {
+                    match key.code {
+                        KeyCode::Char('y') => app.drop_selected_stash(),
+                        _ => app.stash_drop_confirm = false,
+                    }
+                } else if app.stash_error.is_some() {
+                    app.stash_error = None;
+                } else if app.ignore_confirm.is_some() {
+                    match key.code {
+                        KeyCode::Char('y') => {
+                            if let Err(err) = app.confirm_ignore() {
+                                eprintln!("{}", err);
+                            }
+                        }
+                        _ => app.cancel_ignore(),
+                    }
+                } else if app.discard_confirm.is_some() {
+                    match key.code {
+                        KeyCode::Char('y') => app.confirm_discard(),
+                        _ => app.cancel_discard(),
+                    }
+                } else if app.discard_error.is_some() {
+                    app.discard_error = None;
                 } else if app.checkout_success.is_some() {
                     app.checkout_success = None;
                 } else if app.checkout_error.is_some() {
                     app.checkout_error = None;
+                } else if app.blame_error.is_some() {
+                    app.blame_error = None;
+                } else if app.operation_error.is_some() {
+                    app.operation_error = None;
+                } else if app.reset_error.is_some() {
+                    app.reset_error = None;
+                } else if app.reset_done.is_some() {
+                    match key.code {
+                        KeyCode::Char('u') => app.undo_reset(),
+                        _ => app.reset_done = None,
+                    }
+                } else if app.reset_hard_confirm.is_some() {
+                    match key.code {
+                        KeyCode::Char('y') => app.confirm_hard_reset(),
+                        _ => app.cancel_reset(),
+                    }
+                } else if app.reset_target.is_some() {
+                    match key.code {
+                        KeyCode::Char('s') => app.choose_reset(ResetType::Soft),
+                        KeyCode::Char('m') => app.choose_reset(ResetType::Mixed),
+                        KeyCode::Char('h') => app.choose_reset(ResetType::Hard),
+                        KeyCode::Esc => app.cancel_reset(),
+                        _ => {}
+                    }
+                } else if app.reword_confirm.is_some() {
+                    match key.code {
+                        KeyCode::Char('y') => app.confirm_reword(),
+                        _ => app.reword_confirm = None,
+                    }
+                } else if app.rebase_plan.is_some() {
+                    match key.code {
+                        KeyCode::Up => app.rebase_plan_previous(),
+                        KeyCode::Down => app.rebase_plan_next(),
+                        KeyCode::Char('K') => app.rebase_plan_move(true),
+                        KeyCode::Char('J') => app.rebase_plan_move(false),
+                        KeyCode::Char(c) => {
+                            if let Some(action) = RebaseAction::from_key(c) {
+                                app.rebase_plan_set(action);
+                            }
+                        }
+                        KeyCode::Enter => app.start_rebase(),
+                        KeyCode::Esc => app.close_rebase_planner(),
+                        _ => {}
+                    }
+                } else if app.revert_mainline.is_some() {
+                    match key.code {
+                        KeyCode::Char(c) if c.is_ascii_digit() => {
+                            app.choose_revert_mainline(c as usize - '0' as usize);
+                        }
+                        KeyCode::Esc => app.cancel_revert_mainline(),
+                        _ => {}
+                    }
                 } else if app.show_help {
                     app.show_help = false;
+                } else if app.show_graph_filter_dialog {
+                    match key.code {
+                        KeyCode::Esc => app.close_graph_filter_dialog(),
+                        KeyCode::Enter => app.apply_graph_filter(),
+                        KeyCode::Char(c) => {
+                            app.graph_filter_input.push(c);
+                            app.graph_filter_error = None;
+                        }
+                        KeyCode::Backspace => {
+                            app.graph_filter_input.pop();
+                            app.graph_filter_error = None;
+                        }
+                        _ => {}
+                    }
                 } else if app.show_new_branch_dialog {
                     match key.code {
                         KeyCode::Esc => app.close_new_branch_dialog(),
@@ -145,20 +258,14 @@ async fn main() -> io::Result<()> {
                                 app.set_push_remote_from_selection();
                             }
                         }
-                        KeyCode::Char('n') => {
-                            if app.branch_tab == crate::app::BranchTab::Local {
-                                app.open_new_branch_dialog();
-                            }
+                        KeyCode::Char('n') if app.branch_tab == crate::app::BranchTab::Local => {
+                            app.open_new_branch_dialog();
                         }
-                        KeyCode::Char('a') => {
-                            if app.branch_tab == crate::app::BranchTab::Remote {
-                                app.open_add_remote_dialog();
-                            }
+                        KeyCode::Char('a') if app.branch_tab == crate::app::BranchTab::Remote => {
+                            app.open_add_remote_dialog();
                         }
-                        KeyCode::Char('d') => {
-                            if app.branch_tab == crate::app::BranchTab::Remote {
-                                let _ = app.remove_selected_remote();
-                            }
+                        KeyCode::Char('d') if app.branch_tab == crate::app::BranchTab::Remote => {
+                            let _ = app.remove_selected_remote();
                         }
                         KeyCode::Tab => {
                             app.branch_focused = false;
@@ -168,6 +275,39 @@ async fn main() -> io::Result<()> {
                         KeyCode::Char('q') => break,
                         _ => {}
                     }
+                } else if app.window_index == 3 && app.blame.is_some() {
+                    match key.code {
+                        KeyCode::Up => app.blame_previous(),
+                        KeyCode::Down => app.blame_next(),
+                        KeyCode::Enter => app.blame_show_commit(),
+                        KeyCode::Char('p') => app.blame_parent(),
+                        KeyCode::Backspace => app.blame_back(),
+                        KeyCode::Esc => app.close_blame(),
+                        KeyCode::Tab => app.increase_window(),
+                        KeyCode::Char('?') => app.show_help = true,
+                        KeyCode::Char('q') => break,
+                        _ => {}
+                    }
+                } else if app.window_index == 1 && app.graph_tab == GraphTab::Stash {
+                    match key.code {
+                        KeyCode::Up => app.stash_previous(),
+                        KeyCode::Down => app.stash_next(),
+                        KeyCode::Left | KeyCode::Right => app.graph_tab_toggle(),
+                        KeyCode::Char(' ') => app.apply_selected_stash(false),
+                        KeyCode::Char('p') => app.apply_selected_stash(true),
+                        KeyCode::Char('d') if app.stash_state.selected().is_some() => {
+                            app.stash_drop_confirm = true;
+                        }
+                        KeyCode::Char('z') => app.open_stash_dialog(),
+                        KeyCode::Enter => {
+                            app.window_index = 3;
+                            app.focused = true;
+                        }
+                        KeyCode::Tab => app.increase_window(),
+                        KeyCode::Char('?') => app.show_help = true,
+                        KeyCode::Char('q') => break,
+                        _ => {}
+                    }
                 } else {
                     // INFO: Main Terminal Key logic
                     match key.code {
@@ -183,9 +323,33 @@ async fn main() -> io::Result<()> {
                                 app.open_commit_dialog()
                             }
                         }
+                        KeyCode::Char('C') => app.open_amend_dialog(),
                         KeyCode::Char('P') => {
                             app.start_push();
                         }
+                        KeyCode::Char('d') => app.open_discard_dialog(),
+                        KeyCode::Char('z') => app.open_stash_dialog(),
+                        KeyCode::Left | KeyCode::Right if app.window_index == 1 => {
+                            app.graph_tab_toggle();
+                        }
+                        KeyCode::Char('b') if app.window_index == 1 => {
+                            app.toggle_graph_all_refs();
+                        }
+                        KeyCode::Char('/') if app.window_index == 1 => {
+                            app.open_graph_filter_dialog();
+                        }
+                        KeyCode::Char('m') if app.window_index == 1 => app.toggle_graph_mark(),
+                        KeyCode::Char('p') if app.window_index == 1 => app.cherry_pick_selected(),
+                        KeyCode::Char('r') if app.window_index == 1 => app.revert_selected(),
+                        KeyCode::Char('R') if app.window_index == 1 => app.open_reset_dialog(),
+                        KeyCode::Char('i') if app.window_index == 1 => app.open_rebase_planner(),
+                        KeyCode::Char('F') if app.window_index == 1 => app.autosquash(),
+                        KeyCode::Char('w') if app.window_index == 1 => app.open_reword_dialog(),
+                        KeyCode::Char('o') if app.operation.is_some() => app.continue_operation(),
+                        KeyCode::Char('O') if app.operation.is_some() => app.abort_operation(),
+                        KeyCode::Char('S') if app.operation.is_some() => app.skip_operation(),
+                        KeyCode::Char(']') => app.commit_file_next(),
+                        KeyCode::Char('[') => app.commit_file_previous(),
                         KeyCode::Enter => {
                             if app.window_index == 1 {
                                 app.window_index = 3;
@@ -199,7 +363,7 @@ async fn main() -> io::Result<()> {
                             if app.window_index == 1 {
                                 app.commit_graph_previous();
                             } else if app.focused {
-                                app.diff_scroll_up();
+                                app.diff_cursor_up();
                             } else {
                                 app.tree.previous();
                                 app.select_file();
@@ -209,24 +373,63 @@ async fn main() -> io::Result<()> {
                             if app.window_index == 1 {
                                 app.commit_graph_next();
                             } else if app.focused {
-                                app.diff_scroll_down();
+                                app.diff_cursor_down();
                             } else {
                                 app.tree.next();
                                 app.select_file();
                             }
                         }
-                        KeyCode::Esc => {
-                            if app.focused {
-                                app.focused = false;
-                            }
+                        KeyCode::Esc if app.diff_select_anchor.is_some() => {
+                            app.diff_select_anchor = None;
+                        }
+                        KeyCode::Esc if app.focused => {
+                            app.focused = false;
+                        }
+                        KeyCode::Esc if !app.tree.marked.is_empty() => app.tree.clear_marks(),
+                        KeyCode::Char('m') if !app.focused => app.tree.toggle_mark(),
+                        KeyCode::Char('h') if app.window_index == 0 && !app.focused => {
+                            app.open_file_history();
+                        }
+                        KeyCode::Char('B') if app.window_index != 1 => app.open_blame(),
+                        KeyCode::Char('i') if app.window_index == 0 && !app.focused => {
+                            app.open_ignore_dialog();
+                        }
+                        KeyCode::Char('v') if app.focused && app.commit_diff_label.is_none() => {
+                            app.toggle_diff_selection();
                         }
                         KeyCode::Char(' ') => {
-                            if let Some(path) = &app.selected_file
+                            if app.diff_select_anchor.is_some() {
+                                if let Err(err) = app.toggle_stage_lines() {
+                                    eprintln!("{}", err);
+                                }
+                            } else if app.focused && app.commit_diff_label.is_none() {
+                                if let Err(err) = app.toggle_stage_hunk() {
+                                    eprintln!("{}", err);
+                                }
+                            } else if !app.tree.marked.is_empty() {
+                                if let Err(err) = app.toggle_stage_marked() {
+                                    eprintln!("{}", err);
+                                }
+                            } else if let Some(dir) = app.selected_tree_dir() {
+                                if let Err(err) = app.toggle_stage_dir(&dir) {
+                                    eprintln!("{}", err);
+                                }
+                            } else if let Some(path) = &app.selected_file
                                 && let Err(err) = app.toggle_stage(&path.clone())
                             {
                                 eprintln!("{}", err);
                             };
                         }
+                        KeyCode::Char('a') => {
+                            if let Err(err) = app.stage_all() {
+                                eprintln!("{}", err);
+                            }
+                        }
+                        KeyCode::Char('A') => {
+                            if let Err(err) = app.unstage_all() {
+                                eprintln!("{}", err);
+                            }
+                        }
                         KeyCode::Left => app.tree.collapse_or_parent(),
                         KeyCode::Right => {
                             let selected_is_file = app
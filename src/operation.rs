@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Fitrian Musya
+
+use std::collections::VecDeque;
+
+use git2::{Index, Oid, Repository, RepositoryState};
+
+/// A multi-step history operation that can stop on conflicts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OperationKind {
+    CherryPick,
+    Revert,
+    Rebase,
+}
+
+impl OperationKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OperationKind::CherryPick => "cherry-pick",
+            OperationKind::Revert => "revert",
+            OperationKind::Rebase => "rebase",
+        }
+    }
+}
+
+/// An operation started from fastgit, or found half-done in the
+/// repository.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    pub kind: OperationKind,
+    /// Commits still to be applied, in order.
+    pub queue: VecDeque<Oid>,
+    /// The commit being applied, which stopped on conflicts.
+    pub current: Option<Oid>,
+    /// Where HEAD was before the operation, for abort.
+    pub orig_head: Option<Oid>,
+    /// Paths with unresolved conflicts.
+    pub conflicts: Vec<String>,
+    /// False for a rebase in git's own format, which only git can finish.
+    pub resumable: bool,
+}
+
+impl Operation {
+    pub fn new(kind: OperationKind, commits: Vec<Oid>, orig_head: Oid) -> Self {
+        Self {
+            kind,
+            queue: commits.into(),
+            current: None,
+            orig_head: Some(orig_head),
+            conflicts: Vec::new(),
+            resumable: true,
+        }
+    }
+
+    /// The operation a repository was left in by another tool, such as a
+    /// `git cherry-pick` or `git revert` that stopped on conflicts. Only
+    /// rebases in libgit2's own format can be resumed; `git rebase -i`
+    /// keeps a todo list fastgit does not read.
+    pub fn from_repo(repo: &Repository) -> Option<Self> {
+        let (kind, head_file) = match repo.state() {
+            RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+                (OperationKind::CherryPick, "CHERRY_PICK_HEAD")
+            }
+            RepositoryState::Revert | RepositoryState::RevertSequence => {
+                (OperationKind::Revert, "REVERT_HEAD")
+            }
+            RepositoryState::RebaseMerge => (OperationKind::Rebase, "rebase-merge/current"),
+            _ => return None,
+        };
+
+        let current = std::fs::read_to_string(repo.path().join(head_file))
+            .ok()
+            .and_then(|s| Oid::from_str(s.trim()).ok());
+
+        Some(Self {
+            kind,
+            queue: VecDeque::new(),
+            current,
+            orig_head: None,
+            conflicts: repo
+                .index()
+                .map(|i| conflicted_paths(&i))
+                .unwrap_or_default(),
+            resumable: kind != OperationKind::Rebase || is_libgit2_rebase(repo),
+        })
+    }
+}
+
+/// Whether `rebase-merge` holds libgit2's `end` and `cmt.1`..`cmt.N`
+/// files rather than the `git-rebase-todo` of `git rebase -i`.
+fn is_libgit2_rebase(repo: &Repository) -> bool {
+    let dir = repo.path().join("rebase-merge");
+    let end = std::fs::read_to_string(dir.join("end"))
+        .ok()
+        .and_then(|s| s.trim().parse::<usize>().ok());
+    match end {
+        Some(end) => (1..=end).all(|i| dir.join(format!("cmt.{}", i)).is_file()),
+        None => false,
+    }
+}
+
+/// Paths of the conflicted entries in `index`.
+pub fn conflicted_paths(index: &Index) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    if let Ok(conflicts) = index.conflicts() {
+        for conflict in conflicts.flatten() {
+            let entry = conflict.our.or(conflict.their).or(conflict.ancestor);
+            if let Some(entry) = entry {
+                let path = String::from_utf8_lossy(&entry.path).to_string();
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+    }
+    paths
+}
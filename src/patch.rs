@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Fitrian Musya
+
+/// One hunk of a file diff, with a per-line selection mask.
+///
+/// `lines` holds `(origin, content)` pairs as git2 reports them, so the
+/// content keeps its trailing newline and end-of-file markers use the
+/// `=`, `>` and `<` origins.
+#[derive(Debug, Clone)]
+pub struct HunkLines {
+    pub old_start: u32,
+    pub new_start: u32,
+    pub lines: Vec<(char, String)>,
+    pub selected: Vec<bool>,
+}
+
+use git2::FileMode;
+
+/// Build a unified patch for `path` that only carries the selected
+/// `+`/`-` lines of `hunks`.
+///
+/// Unselected additions are dropped and unselected deletions become
+/// context, so applying the patch leaves them where they are. With
+/// `reverse` the hunks are taken from the new side, which is how the
+/// index -> HEAD direction is built when unstaging. `new_file` carries the
+/// mode of a file the index does not have yet. Returns `None` when no
+/// changed line is selected.
+pub fn partial_patch(
+    path: &str,
+    new_file: Option<FileMode>,
+    hunks: &[HunkLines],
+    reverse: bool,
+) -> Option<String> {
+    let mut body = String::new();
+    let mut offset: i64 = 0;
+
+    for hunk in hunks {
+        let mut out = String::new();
+        let mut old_count = 0u32;
+        let mut new_count = 0u32;
+        let mut last_emitted = false;
+
+        for (i, (origin, content)) in hunk.lines.iter().enumerate() {
+            let selected = hunk.selected.get(i).copied().unwrap_or(false);
+            let origin = match (*origin, reverse) {
+                ('+', true) => '-',
+                ('-', true) => '+',
+                (o, _) => o,
+            };
+
+            let emit = match origin {
+                ' ' => Some(' '),
+                '+' if selected => Some('+'),
+                '+' => None,
+                '-' if selected => Some('-'),
+                '-' => Some(' '),
+                '=' | '>' | '<' => {
+                    if last_emitted {
+                        out.push_str("\\ No newline at end of file\n");
+                    }
+                    continue;
+                }
+                _ => None,
+            };
+
+            last_emitted = emit.is_some();
+            let Some(prefix) = emit else {
+                continue;
+            };
+
+            match prefix {
+                ' ' => {
+                    old_count += 1;
+                    new_count += 1;
+                }
+                '+' => new_count += 1,
+                _ => old_count += 1,
+            }
+
+            out.push(prefix);
+            out.push_str(content);
+            if !content.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+
+        if !out
+            .lines()
+            .any(|l| l.starts_with('+') || l.starts_with('-'))
+        {
+            continue;
+        }
+
+        let old_start = if reverse {
+            hunk.new_start
+        } else {
+            hunk.old_start
+        };
+        let mut new_start = old_start as i64 + offset;
+        if old_count == 0 {
+            new_start += 1;
+        }
+        if new_count == 0 {
+            new_start -= 1;
+        }
+        offset += new_count as i64 - old_count as i64;
+
+        body.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start,
+            old_count,
+            new_start.max(0),
+            new_count
+        ));
+        body.push_str(&out);
+    }
+
+    if body.is_empty() {
+        return None;
+    }
+
+    let mut patch = format!("diff --git a/{0} b/{0}\n", path);
+    if let Some(mode) = new_file {
+        patch.push_str(&format!("new file mode {:o}\n", u32::from(mode)));
+        patch.push_str("--- /dev/null\n");
+    } else {
+        patch.push_str(&format!("--- a/{}\n", path));
+    }
+    patch.push_str(&format!("+++ b/{}\n", path));
+    patch.push_str(&body);
+
+    Some(patch)
+}
@@ -0,0 +1,417 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Fitrian Musya
+
+use std::collections::HashMap;
+
+use git2::{Error, Oid, RebaseOptions, Repository, Sort};
+
+/// Files fastgit keeps beside libgit2's own state in `rebase-merge/`, so a
+/// rebase can be continued after a restart: libgit2 only records which
+/// commits to pick, not what to do with them or why the rebase stopped.
+const ACTIONS_FILE: &str = "fastgit-actions";
+const STOP_FILE: &str = "fastgit-stop";
+
+/// What to do with one commit of an interactive rebase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RebaseAction {
+    Pick,
+    /// Pick, then stop to edit the message.
+    Reword,
+    /// Pick, then stop so the commit can be amended.
+    Edit,
+    /// Meld into the previous commit and edit the combined message.
+    Squash,
+    /// Meld into the previous commit, keeping its message.
+    Fixup,
+    Drop,
+}
+
+impl RebaseAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RebaseAction::Pick => "pick",
+            RebaseAction::Reword => "reword",
+            RebaseAction::Edit => "edit",
+            RebaseAction::Squash => "squash",
+            RebaseAction::Fixup => "fixup",
+            RebaseAction::Drop => "drop",
+        }
+    }
+
+    /// The action bound to a key in the planner, which is its initial.
+    pub fn from_key(c: char) -> Option<Self> {
+        match c {
+            'p' => Some(RebaseAction::Pick),
+            'r' => Some(RebaseAction::Reword),
+            'e' => Some(RebaseAction::Edit),
+            's' => Some(RebaseAction::Squash),
+            'f' => Some(RebaseAction::Fixup),
+            'd' => Some(RebaseAction::Drop),
+            _ => None,
+        }
+    }
+}
+
+/// The action and target subject of a `fixup!` or `squash!` commit, from
+/// its summary. Repeated prefixes are stripped, and the first one decides,
+/// as with `git rebase --autosquash`.
+pub fn fixup_target(summary: &str) -> Option<(RebaseAction, &str)> {
+    let mut action = None;
+    let mut rest = summary;
+    loop {
+        if let Some(subject) = rest.strip_prefix("fixup! ") {
+            action.get_or_insert(RebaseAction::Fixup);
+            rest = subject;
+        } else if let Some(subject) = rest.strip_prefix("squash! ") {
+            action.get_or_insert(RebaseAction::Squash);
+            rest = subject;
+        } else {
+            break;
+        }
+    }
+    action.map(|action| (action, rest))
+}
+
+/// How a commit matches the subject of a `fixup!`, in the order
+/// `git rebase --autosquash` tries them: the whole summary, an abbreviated
+/// id, then a summary the subject is the start of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum FixupMatch {
+    Summary,
+    Id,
+    Prefix,
+}
+
+fn fixup_match(oid: Oid, summary: &str, subject: &str) -> Option<FixupMatch> {
+    if summary == subject {
+        Some(FixupMatch::Summary)
+    } else if subject.len() >= 4 && oid.to_string().starts_with(subject) {
+        Some(FixupMatch::Id)
+    } else if summary.starts_with(subject) {
+        Some(FixupMatch::Prefix)
+    } else {
+        None
+    }
+}
+
+/// The oldest commit targeted by the `fixup!` and `squash!` commits
+/// above it on HEAD's first-parent line, which is where an autosquash
+/// has to start. The walk stops at the first merge.
+pub fn autosquash_base(repo: &Repository) -> Result<Option<Oid>, Error> {
+    let mut commit = repo.head()?.peel_to_commit()?;
+    // Subjects not matched exactly yet, and whether a summary they start
+    // with was seen; an exact match further back still wins over it.
+    let mut pending: Vec<(String, bool)> = Vec::new();
+    let mut base = None;
+
+    loop {
+        let summary = commit.summary().unwrap_or("");
+        match fixup_target(summary) {
+            Some((_, subject)) => pending.push((subject.to_string(), false)),
+            None => {
+                let before = pending.len();
+                pending.retain_mut(|(subject, prefixed)| {
+                    match fixup_match(commit.id(), summary, subject) {
+                        Some(FixupMatch::Prefix) if !*prefixed => {
+                            *prefixed = true;
+                            base = Some(commit.id());
+                            true
+                        }
+                        Some(FixupMatch::Summary | FixupMatch::Id) => false,
+                        _ => true,
+                    }
+                });
+                if pending.len() != before {
+                    base = Some(commit.id());
+                    if pending.is_empty() {
+                        break;
+                    }
+                }
+            }
+        }
+        if commit.parent_count() != 1 {
+            break;
+        }
+        commit = commit.parent(0)?;
+    }
+    Ok(base)
+}
+
+/// Why a running rebase handed control back to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RebaseStop {
+    Conflicts,
+    /// A reword or squash waits for its message in the commit dialog.
+    Message,
+    /// An edit step was committed and can now be amended.
+    Edit,
+}
+
+impl RebaseStop {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RebaseStop::Conflicts => "conflicts",
+            RebaseStop::Message => "message",
+            RebaseStop::Edit => "edit",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [RebaseStop::Conflicts, RebaseStop::Message, RebaseStop::Edit]
+            .into_iter()
+            .find(|stop| stop.name() == name)
+    }
+}
+
+/// The actions planned for the rebase in progress, or `None` when fastgit
+/// did not start it, in which case every commit is picked.
+pub fn load_actions(repo: &Repository) -> Option<HashMap<Oid, RebaseAction>> {
+    let text = std::fs::read_to_string(repo.path().join("rebase-merge").join(ACTIONS_FILE)).ok()?;
+    text.lines()
+        .map(|line| {
+            let (action, oid) = line.split_once(' ')?;
+            let action =
+                RebaseAction::from_key(action.chars().next()?).filter(|a| a.name() == action)?;
+            Some((Oid::from_str(oid).ok()?, action))
+        })
+        .collect()
+}
+
+/// Why the rebase in progress last stopped, as saved by `save_stop`.
+pub fn load_stop(repo: &Repository) -> Option<RebaseStop> {
+    let text = std::fs::read_to_string(repo.path().join("rebase-merge").join(STOP_FILE)).ok()?;
+    RebaseStop::from_name(text.trim())
+}
+
+/// Record why the rebase in progress stopped. Does nothing once the rebase
+/// is over and its state is gone.
+pub fn save_stop(repo: &Repository, stop: Option<RebaseStop>) -> Result<(), Error> {
+    let dir = repo.path().join("rebase-merge");
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let result = match stop {
+        Some(stop) => std::fs::write(dir.join(STOP_FILE), format!("{}\n", stop.name())),
+        None => match std::fs::remove_file(dir.join(STOP_FILE)) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        },
+    };
+    result.map_err(|e| Error::from_str(&e.to_string()))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RebaseStep {
+    pub action: RebaseAction,
+    pub oid: Oid,
+    pub summary: String,
+}
+
+/// The todo list of an interactive rebase, oldest commit first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RebasePlan {
+    /// The commit the plan's commits come after.
+    pub upstream: Oid,
+    /// Where they are replayed; the upstream when `None`.
+    pub onto: Option<Oid>,
+    pub steps: Vec<RebaseStep>,
+}
+
+impl RebasePlan {
+    /// Plan a rebase of `from` and every commit after it up to HEAD, all
+    /// picked.
+    pub fn load(repo: &Repository, from: Oid) -> Result<Self, Error> {
+        let commit = repo.find_commit(from)?;
+        let upstream = commit
+            .parent_id(0)
+            .map_err(|_| Error::from_str("the root commit cannot be rebased"))?;
+
+        Self::ensure_on_head(repo, from)?;
+        Ok(Self {
+            upstream,
+            onto: None,
+            steps: Self::steps_after(repo, upstream)?,
+        })
+    }
+
+    /// Replay the commits after `upstream` up to HEAD, all picked, onto
+    /// `onto`.
+    pub fn replay(repo: &Repository, upstream: Oid, onto: Oid) -> Result<Self, Error> {
+        Self::ensure_on_head(repo, upstream)?;
+        Ok(Self {
+            upstream,
+            onto: Some(onto),
+            steps: Self::steps_after(repo, upstream)?,
+        })
+    }
+
+    fn ensure_on_head(repo: &Repository, oid: Oid) -> Result<(), Error> {
+        let head = repo.head()?.peel_to_commit()?.id();
+        if head != oid && !repo.graph_descendant_of(head, oid)? {
+            return Err(Error::from_str(&format!(
+                "{} is not an ancestor of HEAD",
+                &oid.to_string()[..7]
+            )));
+        }
+        Ok(())
+    }
+
+    fn steps_after(repo: &Repository, upstream: Oid) -> Result<Vec<RebaseStep>, Error> {
+        let head = repo.head()?.peel_to_commit()?.id();
+        let mut walk = repo.revwalk()?;
+        walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        walk.push(head)?;
+        walk.hide(upstream)?;
+
+        let mut steps = Vec::new();
+        for oid in walk {
+            let commit = repo.find_commit(oid?)?;
+            if commit.parent_count() > 1 {
+                return Err(Error::from_str(&format!(
+                    "{} is a merge; rebasing across merges is not supported",
+                    &commit.id().to_string()[..7]
+                )));
+            }
+            steps.push(RebaseStep {
+                action: RebaseAction::Pick,
+                oid: commit.id(),
+                summary: commit.summary().unwrap_or("").to_string(),
+            });
+        }
+        Ok(steps)
+    }
+
+    /// Swap step `i` with the one above (`up`) or below it, returning
+    /// where it ended up.
+    pub fn move_step(&mut self, i: usize, up: bool) -> usize {
+        let j = if up {
+            i.checked_sub(1)
+        } else {
+            Some(i + 1).filter(|&j| j < self.steps.len())
+        };
+        match j {
+            Some(j) if i < self.steps.len() => {
+                self.steps.swap(i, j);
+                j
+            }
+            _ => i,
+        }
+    }
+
+    /// Move each `fixup!` and `squash!` commit right after the commit it
+    /// targets, behind earlier fixups of the same commit, and mark it to
+    /// be melded in. Returns how many were moved.
+    pub fn autosquash(&mut self) -> usize {
+        let mut arranged: Vec<(RebaseStep, Option<Oid>)> = Vec::new();
+        let mut moved = 0;
+
+        for mut step in std::mem::take(&mut self.steps) {
+            let target = fixup_target(&step.summary).and_then(|(action, subject)| {
+                arranged
+                    .iter()
+                    .filter(|(_, target)| target.is_none())
+                    .filter_map(|(s, _)| {
+                        fixup_match(s.oid, &s.summary, subject).map(|m| (m, s.oid))
+                    })
+                    .min_by_key(|(m, _)| *m)
+                    .map(|(_, oid)| (action, oid))
+            });
+            let Some((action, target)) = target else {
+                arranged.push((step, None));
+                continue;
+            };
+
+            let mut at = arranged
+                .iter()
+                .position(|(s, _)| s.oid == target)
+                .map_or(arranged.len(), |i| i + 1);
+            while arranged.get(at).is_some_and(|(_, t)| *t == Some(target)) {
+                at += 1;
+            }
+            step.action = action;
+            arranged.insert(at, (step, Some(target)));
+            moved += 1;
+        }
+
+        self.steps = arranged.into_iter().map(|(step, _)| step).collect();
+        moved
+    }
+
+    /// Check that every squash and fixup has a commit to meld into.
+    pub fn validate(&self) -> Result<(), Error> {
+        let first = self
+            .steps
+            .iter()
+            .find(|step| step.action != RebaseAction::Drop);
+        match first {
+            Some(step) if matches!(step.action, RebaseAction::Squash | RebaseAction::Fixup) => {
+                Err(Error::from_str(&format!(
+                    "cannot {} {}: there is no earlier commit to meld into",
+                    step.action.name(),
+                    &step.oid.to_string()[..7]
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The action chosen for each commit that is not dropped.
+    pub fn actions(&self) -> HashMap<Oid, RebaseAction> {
+        self.steps
+            .iter()
+            .filter(|step| step.action != RebaseAction::Drop)
+            .map(|step| (step.oid, step.action))
+            .collect()
+    }
+
+    /// Start the rebase in the repository, checking out where the commits
+    /// are replayed.
+    ///
+    /// libgit2 only replays commits in their original order, so its todo
+    /// list (`rebase-merge/cmt.N` and `end`) is rewritten to the plan's
+    /// order, without the dropped commits, before it is reopened. That
+    /// format is private to libgit2, which is why `git2` is pinned.
+    pub fn start(&self, repo: &Repository) -> Result<(), Error> {
+        self.validate()?;
+        let head = repo.reference_to_annotated_commit(&repo.head()?)?;
+        let upstream = repo.find_annotated_commit(self.upstream)?;
+        let onto = match self.onto {
+            Some(oid) => Some(repo.find_annotated_commit(oid)?),
+            None => None,
+        };
+        let rebase = repo.rebase(
+            Some(&head),
+            Some(&upstream),
+            onto.as_ref(),
+            Some(&mut RebaseOptions::new()),
+        )?;
+        let planned = rebase.len();
+        drop(rebase);
+
+        let dir = repo.path().join("rebase-merge");
+        let write = |name: String, contents: String| {
+            std::fs::write(dir.join(name), contents).map_err(|e| Error::from_str(&e.to_string()))
+        };
+        let kept: Vec<Oid> = self
+            .steps
+            .iter()
+            .filter(|step| step.action != RebaseAction::Drop)
+            .map(|step| step.oid)
+            .collect();
+        for (i, oid) in kept.iter().enumerate() {
+            write(format!("cmt.{}", i + 1), format!("{}\n", oid))?;
+        }
+        for i in kept.len()..planned {
+            let _ = std::fs::remove_file(dir.join(format!("cmt.{}", i + 1)));
+        }
+        write("end".to_string(), format!("{}\n", kept.len()))?;
+
+        let actions: String = self
+            .steps
+            .iter()
+            .filter(|step| step.action != RebaseAction::Drop)
+            .map(|step| format!("{} {}\n", step.action.name(), step.oid))
+            .collect();
+        write(ACTIONS_FILE.to_string(), actions)
+    }
+}
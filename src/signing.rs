@@ -0,0 +1,205 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2026 Fitrian Musya
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use git2::{Config, Error};
+
+/// Signature format, as selected by `gpg.format`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignFormat {
+    OpenPgp,
+    X509,
+    Ssh,
+}
+
+/// Signing a commit failed, kept apart from libgit2's errors so it can be
+/// reported in its own dialog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignError(pub String);
+
+impl fmt::Display for SignError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SignError {}
+
+/// How commits should be signed, read from git config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignConfig {
+    pub format: SignFormat,
+    pub program: String,
+    pub key: Option<String>,
+}
+
+impl SignConfig {
+    /// The signing setup from `config`, or `None` when `commit.gpgsign` is
+    /// off. Unknown `gpg.format` values are reported as an error rather than
+    /// silently producing an unsigned commit.
+    pub fn from_config(config: &Config) -> Result<Option<Self>, Error> {
+        if !config.get_bool("commit.gpgsign").unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let format = match config.get_string("gpg.format").ok().as_deref() {
+            None | Some("openpgp") => SignFormat::OpenPgp,
+            Some("x509") => SignFormat::X509,
+            Some("ssh") => SignFormat::Ssh,
+            Some(other) => {
+                return Err(Error::from_str(&format!(
+                    "unsupported gpg.format '{}'",
+                    other
+                )));
+            }
+        };
+
+        let program = match format {
+            SignFormat::OpenPgp => config
+                .get_string("gpg.openpgp.program")
+                .or_else(|_| config.get_string("gpg.program"))
+                .unwrap_or_else(|_| "gpg".to_string()),
+            SignFormat::X509 => config
+                .get_string("gpg.x509.program")
+                .unwrap_or_else(|_| "gpgsm".to_string()),
+            SignFormat::Ssh => config
+                .get_string("gpg.ssh.program")
+                .unwrap_or_else(|_| "ssh-keygen".to_string()),
+        };
+
+        let key = config
+            .get_string("user.signingkey")
+            .ok()
+            .filter(|k| !k.is_empty());
+
+        Ok(Some(Self {
+            format,
+            program,
+            key,
+        }))
+    }
+
+    /// Sign a commit buffer and return the armored signature.
+    ///
+    /// `committer` is the `Name <email>` identity gpg falls back to when no
+    /// `user.signingkey` is configured. SSH signing needs a key.
+    pub fn sign(&self, buffer: &str, committer: &str) -> Result<String, SignError> {
+        match self.format {
+            SignFormat::OpenPgp | SignFormat::X509 => {
+                let key = self.key.as_deref().unwrap_or(committer);
+                self.run(&["--status-fd=2", "-bsau", key], buffer)
+            }
+            SignFormat::Ssh => {
+                let Some(key) = self.key.as_deref() else {
+                    return Err(SignError(
+                        "gpg.format is ssh but user.signingkey is not set".to_string(),
+                    ));
+                };
+                self.sign_ssh(key, buffer)
+            }
+        }
+    }
+
+    fn sign_ssh(&self, key: &str, buffer: &str) -> Result<String, SignError> {
+        let literal = key.strip_prefix("key::").or_else(|| {
+            (key.starts_with("ssh-") || key.starts_with("ecdsa-") || key.starts_with("sk-"))
+                .then_some(key)
+        });
+
+        let Some(literal) = literal else {
+            return self.run(&["-Y", "sign", "-n", "git", "-f", key], buffer);
+        };
+
+        // A literal public key is handed to ssh-keygen as a file, and the
+        // private half is looked up in the agent.
+        let path = write_temp_key(&format!("{}\n", literal))
+            .map_err(|e| SignError(format!("could not write the signing key: {}", e)))?;
+        let key_path = path.display().to_string();
+        let result = self.run(&["-Y", "sign", "-n", "git", "-U", "-f", &key_path], buffer);
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    fn run(&self, args: &[&str], input: &str) -> Result<String, SignError> {
+        let mut child = Command::new(&self.program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| SignError(format!("could not run {}: {}", self.program, e)))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(input.as_bytes())
+                .map_err(|e| SignError(e.to_string()))?;
+        }
+
+        let output = child
+            .wait_with_output()
+            .map_err(|e| SignError(e.to_string()))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let detail = stderr
+                .lines()
+                .filter(|l| !l.starts_with("[GNUPG:]"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            return Err(SignError(format!(
+                "{} failed to sign the commit: {}",
+                self.program,
+                detail.trim()
+            )));
+        }
+
+        let signature = String::from_utf8_lossy(&output.stdout).to_string();
+        if signature.trim().is_empty() {
+            return Err(SignError(format!(
+                "{} returned an empty signature",
+                self.program
+            )));
+        }
+
+        Ok(signature)
+    }
+}
+
+/// Write `contents` to a new file in the temp directory, under a name that
+/// cannot be guessed ahead of time. The file is created exclusively, so an
+/// existing file or symlink planted there is never written through.
+fn write_temp_key(contents: &str) -> io::Result<PathBuf> {
+    for _ in 0..16 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(std::process::id());
+        hasher.write_u128(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos()),
+        );
+        let path =
+            std::env::temp_dir().join(format!("fastgit-signingkey-{:016x}.pub", hasher.finish()));
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        };
+        if let Err(e) = file.write_all(contents.as_bytes()) {
+            let _ = std::fs::remove_file(&path);
+            return Err(e);
+        }
+        return Ok(path);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "no free temporary file name",
+    ))
+}
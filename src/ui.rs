@@ -1,17 +1,24 @@
 // SPDX-License-Identifier: MIT
 // Copyright (c) 2026 Fitrian Musya
 
+use std::collections::HashMap;
+
+use git2::ResetType;
 use ratatui::{
     Frame,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style, Stylize},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, BorderType, Borders, Clear, List, ListItem, Paragraph, Wrap},
 };
 
 use crate::{
-    app::{App, BranchTab, DiffLineKind, Tab},
+    app::{App, BranchTab, DiffLineKind, DiscardTarget, GraphTab, Tab},
+    commit_detail::{CommitDetail, Person, SignatureStatus},
+    graph::RefKind,
     helper::helpers::{Dialog, DialogType, Helper},
+    operation::OperationKind,
+    rebase::{RebaseAction, RebaseStop},
 };
 
 const BORDER_STYLE: Style = Style::new().yellow().bold();
@@ -69,12 +76,18 @@ fn input_line_with_cursor(
 pub fn draw_ui(f: &mut Frame, app: &mut App) {
     app.refresh_current_branch();
 
+    let banner = if app.operation.is_some() { 1 } else { 0 };
     let vertical_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(0), Constraint::Length(3)])
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(banner),
+            Constraint::Length(3),
+        ])
         .split(f.area());
 
     draw_content(f, vertical_chunks[0], app);
+    draw_operation_banner(f, vertical_chunks[1], app);
 
     if app.show_commit_dialog {
         draw_commit_dialog(f, app);
@@ -95,6 +108,45 @@ pub fn draw_ui(f: &mut Frame, app: &mut App) {
             },
         );
     }
+    if let Some(err) = app.commit_sign_error.clone() {
+        let h = Helper;
+        let mut content: Vec<Line> = err.lines().map(|l| Line::from(l.to_string())).collect();
+        content.push(Line::from(""));
+        content.push(Line::from(
+            "Check commit.gpgsign, gpg.format and user.signingkey",
+        ));
+        content.push(Line::from("Press any key to return to the commit message"));
+        h.draw_dialog(
+            f,
+            Dialog {
+                dialog_type: DialogType::Error,
+                title: "Signing Failed".to_string(),
+                height: content.len() as u16 + 4,
+                content,
+                width: 70,
+            },
+        );
+    }
+    if let Some((class, message)) = app.commit_error.clone() {
+        let h = Helper;
+        let mut content = vec![
+            Line::from(format!("Error class: {:?}", class)),
+            Line::from(""),
+        ];
+        content.extend(message.lines().map(|l| Line::from(l.to_string())));
+        content.push(Line::from(""));
+        content.push(Line::from("Press any key to return to the commit message"));
+        h.draw_dialog(
+            f,
+            Dialog {
+                dialog_type: DialogType::Error,
+                title: "Commit Failed".to_string(),
+                height: content.len() as u16 + 4,
+                content,
+                width: 70,
+            },
+        );
+    }
     if app.commit_success_open {
         let h = Helper;
         h.draw_dialog(
@@ -160,7 +212,7 @@ pub fn draw_ui(f: &mut Frame, app: &mut App) {
         );
     }
     app.branch_focused = app.window_index == 2;
-    draw_footer(vertical_chunks[1], app, f);
+    draw_footer(vertical_chunks[2], app, f);
 
     if app.show_add_remote_dialog {
         draw_add_remote_dialog(f, app);
@@ -168,6 +220,9 @@ pub fn draw_ui(f: &mut Frame, app: &mut App) {
     if app.show_new_branch_dialog {
         draw_new_branch_dialog(f, app);
     }
+    if app.show_graph_filter_dialog {
+        draw_graph_filter_dialog(f, app);
+    }
     if app.show_help {
         draw_help_dialog(f);
     }
@@ -188,6 +243,162 @@ pub fn draw_ui(f: &mut Frame, app: &mut App) {
             },
         );
     }
+    if let Some(patterns) = &app.ignore_confirm {
+        draw_ignore_dialog(f, patterns);
+    }
+    if let Some(target) = app.discard_confirm.clone() {
+        draw_discard_dialog(f, &target, app.discard_delete_confirm);
+    }
+    if app.show_stash_dialog {
+        draw_stash_dialog(f, app);
+    }
+    if app.stash_drop_confirm {
+        let h = Helper;
+        let name = app
+            .stash_state
+            .selected()
+            .and_then(|i| app.stashes.get(i))
+            .map(|s| format!("stash@{{{}}}: {}", s.index, s.message))
+            .unwrap_or_default();
+        h.draw_dialog(
+            f,
+            Dialog {
+                dialog_type: DialogType::Warning,
+                title: "Drop Stash".to_string(),
+                content: vec![
+                    Line::from(format!("Drop {}?", name)),
+                    Line::from("This cannot be undone."),
+                    Line::from(""),
+                    Line::from("Press y to drop, any other key to cancel"),
+                ],
+                width: 70,
+                height: 9,
+            },
+        );
+    }
+    if let Some(err) = app.stash_error.clone() {
+        let h = Helper;
+        h.draw_dialog(
+            f,
+            Dialog {
+                dialog_type: DialogType::Error,
+                title: "Stash Failed".to_string(),
+                content: vec![
+                    Line::from(err),
+                    Line::from(""),
+                    Line::from("Press any key to continue"),
+                ],
+                width: 70,
+                height: 8,
+            },
+        );
+    }
+    if let Some(err) = app.discard_error.clone() {
+        let h = Helper;
+        h.draw_dialog(
+            f,
+            Dialog {
+                dialog_type: DialogType::Error,
+                title: "Discard Failed".to_string(),
+                content: vec![
+                    Line::from(err),
+                    Line::from(""),
+                    Line::from("Press any key to continue"),
+                ],
+                width: 70,
+                height: 8,
+            },
+        );
+    }
+    draw_reset_dialog(f, app);
+    if let Some((oid, remote)) = app.reword_confirm.clone() {
+        let h = Helper;
+        h.draw_dialog(
+            f,
+            Dialog {
+                dialog_type: DialogType::Warning,
+                title: "Reword Published Commit".to_string(),
+                content: vec![
+                    Line::from(format!(
+                        "{} is already on {}.",
+                        &oid.to_string()[..7],
+                        remote
+                    )),
+                    Line::from("Rewording rewrites it and every commit after it;"),
+                    Line::from("pushing the result will need --force."),
+                    Line::from(""),
+                    Line::from("Press y to reword anyway, any other key to cancel"),
+                ],
+                width: 70,
+                height: 10,
+            },
+        );
+    }
+    if app.rebase_plan.is_some() {
+        draw_rebase_planner(f, app);
+    }
+    if let Some((oid, parents)) = app.revert_mainline.clone() {
+        let h = Helper;
+        let mut content = vec![
+            Line::from(format!(
+                "{} is a merge. Revert the changes it brought in relative to:",
+                &oid.to_string()[..7]
+            )),
+            Line::from(""),
+        ];
+        for (i, parent) in parents.iter().enumerate() {
+            content.push(Line::from(format!("  {}  {}", i + 1, parent)));
+        }
+        content.push(Line::from(""));
+        content.push(Line::from(
+            "Press a number to choose the mainline, Esc to cancel",
+        ));
+        let height = content.len() as u16 + 5;
+        h.draw_dialog(
+            f,
+            Dialog {
+                dialog_type: DialogType::Info,
+                title: "Revert Merge".to_string(),
+                content,
+                width: 70,
+                height,
+            },
+        );
+    }
+    if let Some(err) = app.operation_error.clone() {
+        let h = Helper;
+        h.draw_dialog(
+            f,
+            Dialog {
+                dialog_type: DialogType::Error,
+                title: "Operation Failed".to_string(),
+                content: vec![
+                    Line::from(err),
+                    Line::from(""),
+                    Line::from("Press any key to continue"),
+                ],
+                width: 70,
+                height: 8,
+            },
+        );
+    }
+    if let Some(err) = app.blame_error.clone() {
+        let h = Helper;
+        h.draw_dialog(
+            f,
+            Dialog {
+                dialog_type: DialogType::Warning,
+                title: "Blame Failed".to_string(),
+                content: vec![
+                    Line::from(err),
+                    Line::from(""),
+                    Line::from("Press any key to continue"),
+                ],
+                width: 70,
+                height: 8,
+            },
+        );
+    }
     if let Some(err) = app.checkout_error.clone() {
         let h = Helper;
         h.draw_dialog(
@@ -205,6 +416,53 @@ pub fn draw_ui(f: &mut Frame, app: &mut App) {
             },
         );
     }
+    if app.show_hook_dialog {
+        draw_hook_dialog(f, app);
+    }
+}
+
+/// Output of the running hooks, tailed so the latest lines stay visible.
+fn draw_hook_dialog(f: &mut Frame, app: &App) {
+    let h = Helper;
+    let height = 20u16;
+    let visible = height.saturating_sub(5) as usize;
+
+    let mut content: Vec<Line> = Vec::new();
+    let (dialog_type, title) = match &app.hook_error {
+        Some(err) => {
+            content.push(Line::from(Span::styled(
+                err.clone(),
+                Style::default().fg(Color::Red),
+            )));
+            (DialogType::Error, "Hook Failed".to_string())
+        }
+        None => (
+            DialogType::Info,
+            format!(
+                "Running {}...",
+                app.hook_title.as_deref().unwrap_or("hooks")
+            ),
+        ),
+    };
+
+    let room = visible.saturating_sub(content.len());
+    let start = app.hook_output.len().saturating_sub(room);
+    let mut lines: Vec<Line> = app.hook_output[start..]
+        .iter()
+        .map(|l| Line::from(l.clone()))
+        .collect();
+    lines.append(&mut content);
+
+    h.draw_dialog(
+        f,
+        Dialog {
+            dialog_type,
+            title,
+            content: lines,
+            width: 80,
+            height,
+        },
+    );
 }
 
 fn draw_content(f: &mut Frame, area: ratatui::layout::Rect, app: &mut App) {
@@ -238,9 +496,10 @@ fn draw_content(f: &mut Frame, area: ratatui::layout::Rect, app: &mut App) {
                 .map(|(path, depth, is_dir)| {
                     let indent = "  ".repeat(*depth);
                     let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    let mark = if app.tree.is_marked(path) { "● " } else { "" };
 
                     if *is_dir {
-                        let text = format!("{}{}/", indent, name);
+                        let text = format!("{}{}{}/", indent, mark, name);
                         ListItem::new(text).style(
                             Style::default()
                                 .fg(Color::White)
@@ -254,7 +513,7 @@ fn draw_content(f: &mut Frame, area: ratatui::layout::Rect, app: &mut App) {
                         } else {
                             ("??", Color::White)
                         };
-                        let text = format!("{}{} {}", indent, icon, name);
+                        let text = format!("{}{}{} {}", indent, mark, icon, name);
                         ListItem::new(text).style(Style::default().fg(color))
                     }
                 })
@@ -281,7 +540,11 @@ fn draw_content(f: &mut Frame, area: ratatui::layout::Rect, app: &mut App) {
                         Block::default()
                             .borders(Borders::ALL)
                             .border_type(BorderType::Rounded)
-                            .title("Tree"),
+                            .title(if app.tree.marked.is_empty() {
+                                "Tree".to_string()
+                            } else {
+                                format!("Tree — {} marked", app.tree.marked.len())
+                            }),
                     )
                     .highlight_style(
                         Style::default()
@@ -404,10 +667,62 @@ fn draw_content(f: &mut Frame, area: ratatui::layout::Rect, app: &mut App) {
                 }
             }
 
+            if app.blame.is_some() {
+                return draw_blame_panel(f, bottom_chunks[0], app);
+            }
+
+            let diff_area = match &app.commit_detail {
+                Some(detail) => {
+                    let header = commit_detail_lines(detail);
+                    // Borders take two rows; the header never takes more
+                    // than 40% of the panel so the diff stays visible.
+                    let cap = bottom_chunks[0].height * 2 / 5;
+                    let height = (header.len() as u16 + 2).min(cap);
+                    let parts = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Length(height), Constraint::Min(0)])
+                        .split(bottom_chunks[0]);
+
+                    let block = Paragraph::new(header)
+                        .block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .border_type(BorderType::Rounded)
+                                .title(" Commit "),
+                        )
+                        .style(BORDER_DEFAULT_STYLE)
+                        .wrap(Wrap { trim: false });
+                    f.render_widget(block, parts[0]);
+                    parts[1]
+                }
+                None => bottom_chunks[0],
+            };
+
+            let diff_area = if app.commit_files.is_empty() {
+                diff_area
+            } else {
+                let parts = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+                    .split(diff_area);
+                draw_commit_files(f, parts[0], app);
+                parts[1]
+            };
+
             let diff_title = if let Some(label) = &app.commit_diff_label {
-                format!("Diff — {}", label)
+                match app
+                    .commit_file_state
+                    .selected()
+                    .and_then(|i| app.commit_files.get(i))
+                {
+                    Some(file) => format!("Diff — {} — {}", label, file.path),
+                    None => format!("Diff — {}", label),
+                }
             } else {
                 match &app.selected_file {
+                    Some(p) if app.diff_select_anchor.is_some() => {
+                        format!("Diff — {} [select lines]", p.display())
+                    }
                     Some(p) => format!("Diff — {}", p.display()),
                     None => "Diff — No file selected".to_string(),
                 }
@@ -433,13 +748,33 @@ fn draw_content(f: &mut Frame, area: ratatui::layout::Rect, app: &mut App) {
                     } else {
                         BORDER_DEFAULT_STYLE
                     });
-                f.render_widget(empty, bottom_chunks[0]);
+                f.render_widget(empty, diff_area);
             } else {
+                app.diff_view_height = diff_area.height.saturating_sub(2) as usize;
+                if app.diff_view_height > 0
+                    && app.diff_cursor >= app.diff_scroll + app.diff_view_height
+                {
+                    app.diff_scroll = app.diff_cursor + 1 - app.diff_view_height;
+                }
+
+                let active_hunk = if app.focused {
+                    app.hunk_at_cursor().cloned()
+                } else {
+                    None
+                };
+
+                // Lines a pickaxe search matched stand out in its results.
+                let pickaxe = app
+                    .graph_filter
+                    .as_ref()
+                    .filter(|f| f.pickaxe.is_some() && app.commit_detail.is_some());
+
                 let visible_lines: Vec<ListItem> = app
                     .diff_content
                     .iter()
+                    .enumerate()
                     .skip(app.diff_scroll)
-                    .map(|dl| {
+                    .map(|(row, dl)| {
                         let color = match dl.kind {
                             DiffLineKind::Add => Color::Green,
                             DiffLineKind::Delete => Color::Red,
@@ -452,10 +787,27 @@ fn draw_content(f: &mut Frame, area: ratatui::layout::Rect, app: &mut App) {
                             DiffLineKind::Header => "",
                             DiffLineKind::Context => "  ",
                         };
-                        ListItem::new(Line::from(Span::styled(
-                            format!("{}{}", prefix, dl.content),
-                            Style::default().fg(color),
-                        )))
+                        let gutter = match &active_hunk {
+                            Some(h) if h.contains(row) => "▌",
+                            _ => " ",
+                        };
+                        let mut style = Style::default().fg(color);
+                        if pickaxe.is_some_and(|p| {
+                            matches!(dl.kind, DiffLineKind::Add | DiffLineKind::Delete)
+                                && p.pickaxe_hit(&dl.content)
+                        }) {
+                            style = style.bg(Color::Magenta).add_modifier(Modifier::BOLD);
+                        }
+                        if app.is_diff_row_selected(row) {
+                            style = style.bg(Color::Blue);
+                        }
+                        if app.focused && row == app.diff_cursor {
+                            style = style.bg(Color::DarkGray).add_modifier(Modifier::BOLD);
+                        }
+                        ListItem::new(Line::from(vec![
+                            Span::styled(gutter, Style::default().fg(Color::Cyan)),
+                            Span::styled(format!("{}{}", prefix, dl.content), style),
+                        ]))
                     })
                     .collect();
 
@@ -472,119 +824,172 @@ fn draw_content(f: &mut Frame, area: ratatui::layout::Rect, app: &mut App) {
                         BORDER_DEFAULT_STYLE
                     });
 
-                f.render_widget(diff_list, bottom_chunks[0]);
+                f.render_widget(diff_list, diff_area);
             }
         }
     }
 }
 
-fn draw_help_dialog(f: &mut Frame) {
-    let area = f.area();
-    let dialog_width = 52u16;
-    let dialog_height = 24u16;
-
-    let x = (area.width.saturating_sub(dialog_width)) / 2;
-    let y = (area.height.saturating_sub(dialog_height)) / 2;
-
-    let dialog_area = ratatui::layout::Rect {
-        x,
-        y,
-        width: dialog_width,
-        height: dialog_height,
-    };
-
-    f.render_widget(Clear, dialog_area);
-
-    let row = |key: &'static str, desc: &'static str| -> Line<'static> {
+/// Header lines for the commit whose diff is open.
+fn commit_detail_lines(detail: &CommitDetail) -> Vec<Line<'static>> {
+    let h = Helper;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let key = Style::default().fg(Color::Cyan);
+    let text = Style::default().fg(Color::White);
+
+    let person = |label: &'static str, p: &Person| {
         Line::from(vec![
+            Span::styled(label, key),
+            Span::styled(format!("{} <{}>  ", p.name, p.email), text),
             Span::styled(
-                format!("  {:>14}  ", key),
-                Style::default()
-                    .fg(Color::Yellow)
-                    .add_modifier(Modifier::BOLD),
+                format!(
+                    "{} ({})",
+                    h.format_date(p.time, p.offset_minutes),
+                    h.format_age(now - p.time)
+                ),
+                Style::default().fg(Color::DarkGray),
             ),
-            Span::styled(desc, Style::default().fg(Color::White)),
         ])
     };
 
-    let content = Paragraph::new(vec![
-        Line::from(Span::styled(
-            "  Keybindings",
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )),
-        Line::from(""),
-        row("Tab", "Switch window"),
-        row("Enter", "Select / Checkout branch"),
-        row("Esc", "Deselect / Close dialog"),
-        row("Up / Down", "Navigate list"),
-        row("Left / Right", "Switch branch tab"),
-        row("Space", "Stage / unstage file"),
-        Line::from(""),
-        row("c", "Commit staged changes"),
-        row("P", "Push to remote"),
-        row("Enter (Remote)", "Set selected remote for push"),
-        row("n", "New branch (Local tab)"),
-        row("a", "Add remote (Remote tab)"),
-        row("d", "Delete remote (Remote tab)"),
-        row("s", "Rescan git status"),
-        row("q", "Quit"),
-        Line::from(""),
-        row("Left / Right", "Move cursor in input"),
-        row("Home / End", "Jump to start / end"),
-        row("Delete", "Delete char at cursor"),
-        Line::from(""),
-        Line::from(Span::styled(
-            "  Press any key to close",
-            Style::default()
-                .fg(Color::DarkGray)
-                .add_modifier(Modifier::ITALIC),
-        )),
-    ])
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_type(BorderType::Rounded)
-            .title(" ? Help ")
-            .border_style(
-                Style::default()
-                    .fg(Color::Yellow)
-                    .add_modifier(Modifier::BOLD),
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("commit ", key),
+            Span::styled(detail.oid.clone(), Style::default().fg(Color::Yellow)),
+        ]),
+        person("Author:    ", &detail.author),
+        person("Committer: ", &detail.committer),
+    ];
+
+    let parents = if detail.parents.is_empty() {
+        "(root commit)".to_string()
+    } else {
+        detail
+            .parents
+            .iter()
+            .map(|p| p[..7.min(p.len())].to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    lines.push(Line::from(vec![
+        Span::styled("Parents:   ", key),
+        Span::styled(parents, Style::default().fg(Color::Yellow)),
+    ]));
+
+    let signature = match &detail.signature {
+        SignatureStatus::Unsigned => Span::styled("unsigned", Style::default().fg(Color::DarkGray)),
+        SignatureStatus::Checking { kind } => Span::styled(
+            format!("{} checking…", kind),
+            Style::default().fg(Color::DarkGray),
+        ),
+        SignatureStatus::Signed {
+            kind,
+            verified: true,
+            detail,
+        } => Span::styled(
+            format!("{} ✓ {}", kind, detail),
+            Style::default().fg(Color::Green),
+        ),
+        SignatureStatus::Signed { kind, detail, .. } => Span::styled(
+            format!("{} ✗ {}", kind, detail),
+            Style::default().fg(Color::Red),
+        ),
+    };
+    lines.push(Line::from(vec![
+        Span::styled("Signature: ", key),
+        signature,
+    ]));
+
+    lines.push(Line::from(vec![
+        Span::styled(
+            format!(
+                "{} file{} changed, ",
+                detail.files_changed,
+                if detail.files_changed == 1 { "" } else { "s" }
             ),
-    );
+            text,
+        ),
+        Span::styled(
+            format!("+{}", detail.insertions),
+            Style::default().fg(Color::Green),
+        ),
+        Span::styled(" ", text),
+        Span::styled(
+            format!("-{}", detail.deletions),
+            Style::default().fg(Color::Red),
+        ),
+    ]));
 
-    f.render_widget(content, dialog_area);
+    lines.push(Line::default());
+    for line in detail.message.lines() {
+        lines.push(Line::from(Span::styled(format!("    {}", line), text)));
+    }
+    if !detail.trailers.is_empty() {
+        lines.push(Line::default());
+        for (k, v) in &detail.trailers {
+            lines.push(Line::from(vec![
+                Span::styled(format!("    {}: ", k), Style::default().fg(Color::Magenta)),
+                Span::styled(v.clone(), text),
+            ]));
+        }
+    }
+
+    lines
 }
 
-fn draw_commit_graph_panel(f: &mut Frame, area: ratatui::layout::Rect, app: &mut App) {
-    let total = app.commit_graph.len();
-    let selected = app.commit_graph_state.selected().unwrap_or(0);
-    let current = if total == 0 { 0 } else { selected + 1 };
+/// The files of the open commit, beside their diff.
+fn draw_commit_files(f: &mut Frame, area: Rect, app: &mut App) {
+    let items: Vec<ListItem> = app
+        .commit_files
+        .iter()
+        .map(|file| {
+            let color = match file.status {
+                'A' => Color::Green,
+                'D' => Color::Red,
+                'R' | 'C' => Color::Cyan,
+                _ => Color::Yellow,
+            };
+            let path = match &file.old_path {
+                Some(old) => format!("{} → {}", old, file.path),
+                None => file.path.clone(),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{} ", file.status), Style::default().fg(color)),
+                Span::styled(
+                    format!("+{}", file.additions),
+                    Style::default().fg(Color::Green),
+                ),
+                Span::raw(" "),
+                Span::styled(
+                    format!("-{}", file.deletions),
+                    Style::default().fg(Color::Red),
+                ),
+                Span::styled(format!(" {}", path), Style::default().fg(Color::White)),
+            ]))
+        })
+        .collect();
 
     let counter = Line::from(Span::styled(
-        format!(" {} of {} ", current, total),
+        format!(
+            " {} of {} ",
+            app.commit_file_state.selected().map(|i| i + 1).unwrap_or(0),
+            app.commit_files.len()
+        ),
         Style::default().fg(Color::DarkGray),
     ))
     .right_aligned();
 
-    let items: Vec<ListItem> = if app.commit_graph.is_empty() {
-        vec![ListItem::new("No commits found").style(Style::default().fg(Color::DarkGray))]
-    } else {
-        app.commit_graph
-            .iter()
-            .map(|line| ListItem::new(line.as_str()).style(Style::default().fg(Color::White)))
-            .collect()
-    };
-
     let list = List::new(items)
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
-                .title("Commit Graph")
+                .title(" Files [ ] ")
                 .title_bottom(counter)
-                .border_style(if app.window_index == 1 {
+                .border_style(if app.window_index == 3 {
                     BORDER_STYLE
                 } else {
                     BORDER_DEFAULT_STYLE
@@ -592,12 +997,614 @@ fn draw_commit_graph_panel(f: &mut Frame, area: ratatui::layout::Rect, app: &mut
         )
         .highlight_style(
             Style::default()
-                .fg(Color::Black)
-                .bg(Color::White)
+                .bg(Color::DarkGray)
                 .add_modifier(Modifier::BOLD),
-        )
-        .highlight_symbol("▶ ");
-
+        );
+    f.render_stateful_widget(list, area, &mut app.commit_file_state);
+}
+
+/// Each line of the blamed file behind a gutter with the commit, author
+/// and age of its last change. Runs of lines from one commit share a color.
+fn draw_blame_panel(f: &mut Frame, area: Rect, app: &mut App) {
+    let Some(blame) = &app.blame else {
+        return;
+    };
+    let h = Helper;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    let mut slots: HashMap<git2::Oid, usize> = HashMap::new();
+    let mut previous = None;
+    let items: Vec<ListItem> = blame
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let next_slot = slots.len();
+            let slot = *slots.entry(line.oid).or_insert(next_slot);
+            let color = graph_color(slot);
+
+            // Only the first line of a run repeats the commit details.
+            let gutter = if previous == Some(line.oid) {
+                format!("{:<7} {:<12} {:>8}", "", "", "")
+            } else {
+                let author: String = line.author.chars().take(12).collect();
+                format!(
+                    "{} {:<12} {:>8}",
+                    line.short_id(),
+                    author,
+                    h.format_age(now - line.time)
+                )
+            };
+            previous = Some(line.oid);
+
+            ListItem::new(Line::from(vec![
+                Span::styled("▌", Style::default().fg(color)),
+                Span::styled(gutter, Style::default().fg(color)),
+                Span::styled(
+                    format!(" {:>4} ", i + 1),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(line.content.clone(), Style::default().fg(Color::White)),
+            ]))
+        })
+        .collect();
+
+    let rev = match blame.rev {
+        Some(oid) => oid.to_string()[..7].to_string(),
+        None => "HEAD".to_string(),
+    };
+    let hints = Line::from(Span::styled(
+        " Enter: show commit  p: blame parent  Backspace: back  Esc: close ",
+        Style::default().fg(Color::DarkGray),
+    ))
+    .right_aligned();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(format!(" Blame — {} @ {} ", blame.path, rev))
+                .title_bottom(hints)
+                .border_style(if app.window_index == 3 {
+                    BORDER_STYLE
+                } else {
+                    BORDER_DEFAULT_STYLE
+                }),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        );
+    f.render_stateful_widget(list, area, &mut app.blame_state);
+}
+
+fn draw_ignore_dialog(f: &mut Frame, patterns: &[String]) {
+    let h = Helper;
+    let shown = 5;
+    let mut content = vec![Line::from("Add to .gitignore?"), Line::from("")];
+    content.extend(patterns.iter().take(shown).map(|p| Line::from(p.clone())));
+    if patterns.len() > shown {
+        content.push(Line::from(format!("… and {} more", patterns.len() - shown)));
+    }
+    content.push(Line::from(""));
+    content.push(Line::from("Press y to add, any other key to cancel"));
+
+    let height = content.len() as u16 + 5;
+    h.draw_dialog(
+        f,
+        Dialog {
+            dialog_type: DialogType::Warning,
+            title: "Ignore Files".to_string(),
+            content,
+            width: 70,
+            height,
+        },
+    );
+}
+
+fn draw_discard_dialog(f: &mut Frame, target: &DiscardTarget, delete_confirm: bool) {
+    let h = Helper;
+    let subject = match target {
+        DiscardTarget::File(p) => format!("all changes to {}", p.display()),
+        DiscardTarget::Marked(paths) => format!("all changes to {} marked files", paths.len()),
+        DiscardTarget::Hunk(p) => format!("the hunk under the cursor in {}", p.display()),
+        DiscardTarget::Lines(p) => format!("the selected lines in {}", p.display()),
+    };
+
+    let content = if delete_confirm {
+        let (who, gone) = match target {
+            DiscardTarget::Marked(_) => (
+                "Some marked files have".to_string(),
+                "They will be deleted from disk permanently.",
+            ),
+            DiscardTarget::File(p) | DiscardTarget::Hunk(p) | DiscardTarget::Lines(p) => (
+                format!("{} has", p.display()),
+                "It will be deleted from disk permanently.",
+            ),
+        };
+        vec![
+            Line::from(format!("{} no committed or staged copy.", who)),
+            Line::from(gone),
+            Line::from(""),
+            Line::from("Press y again to delete, any other key to cancel"),
+        ]
+    } else {
+        vec![
+            Line::from(format!("Discard {}?", subject)),
+            Line::from("This cannot be undone."),
+            Line::from(""),
+            Line::from("Press y to discard, any other key to cancel"),
+        ]
+    };
+
+    h.draw_dialog(
+        f,
+        Dialog {
+            dialog_type: DialogType::Warning,
+            title: if delete_confirm {
+                "Delete File".to_string()
+            } else {
+                "Discard Changes".to_string()
+            },
+            content,
+            width: 70,
+            height: 9,
+        },
+    );
+}
+
+fn reset_mode_name(mode: ResetType) -> &'static str {
+    match mode {
+        ResetType::Soft => "soft",
+        ResetType::Mixed => "mixed",
+        ResetType::Hard => "hard",
+    }
+}
+
+fn draw_reset_dialog(f: &mut Frame, app: &App) {
+    let h = Helper;
+    let short = |oid: &git2::Oid| oid.to_string()[..7].to_string();
+
+    if let Some(err) = app.reset_error.clone() {
+        h.draw_dialog(
+            f,
+            Dialog {
+                dialog_type: DialogType::Error,
+                title: "Reset Failed".to_string(),
+                content: vec![
+                    Line::from(err),
+                    Line::from(""),
+                    Line::from("Press any key to continue"),
+                ],
+                width: 70,
+                height: 8,
+            },
+        );
+    } else if let Some(done) = &app.reset_done {
+        h.draw_dialog(
+            f,
+            Dialog {
+                dialog_type: DialogType::Success,
+                title: "Reset".to_string(),
+                content: vec![
+                    Line::from(format!(
+                        "Reset ({}) to {}.",
+                        reset_mode_name(done.mode),
+                        short(&done.target)
+                    )),
+                    Line::from(format!(
+                        "HEAD was {} {}",
+                        short(&done.previous),
+                        done.previous_summary
+                    )),
+                    Line::from(""),
+                    Line::from("Press u to undo, any other key to continue"),
+                ],
+                width: 70,
+                height: 9,
+            },
+        );
+    } else if let Some(lost) = &app.reset_hard_confirm {
+        const SHOWN: usize = 10;
+        let target = app.reset_target.as_ref().map(short).unwrap_or_default();
+        let mut content = vec![Line::from(format!("Hard reset to {}?", target))];
+        if lost.is_empty() {
+            content.push(Line::from("There are no uncommitted changes to lose."));
+        } else {
+            content.push(Line::from(
+                "These uncommitted changes will be lost; untracked files are kept:",
+            ));
+            for change in lost.iter().take(SHOWN) {
+                content.push(Line::from(format!("  {}", change)));
+            }
+            if lost.len() > SHOWN {
+                content.push(Line::from(format!("  … and {} more", lost.len() - SHOWN)));
+            }
+        }
+        content.push(Line::from(""));
+        content.push(Line::from("Press y to reset, any other key to cancel"));
+        let height = content.len() as u16 + 5;
+        h.draw_dialog(
+            f,
+            Dialog {
+                dialog_type: DialogType::Warning,
+                title: "Hard Reset".to_string(),
+                content,
+                width: 74,
+                height,
+            },
+        );
+    } else if let Some(target) = &app.reset_target {
+        let line = app
+            .commit_graph_oids
+            .iter()
+            .position(|o| *o == target.to_string())
+            .and_then(|i| app.commit_graph.get(i))
+            .cloned()
+            .unwrap_or_else(|| short(target));
+        h.draw_dialog(
+            f,
+            Dialog {
+                dialog_type: DialogType::Info,
+                title: "Reset Branch".to_string(),
+                content: vec![
+                    Line::from(format!("Reset the current branch to {}", line)),
+                    Line::from(""),
+                    Line::from("  s  soft   keep the index and the work tree"),
+                    Line::from("  m  mixed  keep the work tree, reset the index"),
+                    Line::from("  h  hard   discard the index and the work tree"),
+                    Line::from(""),
+                    Line::from("Esc to cancel"),
+                ],
+                width: 70,
+                height: 12,
+            },
+        );
+    }
+}
+
+fn draw_help_dialog(f: &mut Frame) {
+    let area = f.area();
+    let dialog_width = 52u16;
+    let dialog_height = 47u16;
+
+    let x = (area.width.saturating_sub(dialog_width)) / 2;
+    let y = (area.height.saturating_sub(dialog_height)) / 2;
+
+    let dialog_area = ratatui::layout::Rect {
+        x,
+        y,
+        width: dialog_width,
+        height: dialog_height,
+    };
+
+    f.render_widget(Clear, dialog_area);
+
+    let row = |key: &'static str, desc: &'static str| -> Line<'static> {
+        Line::from(vec![
+            Span::styled(
+                format!("  {:>14}  ", key),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(desc, Style::default().fg(Color::White)),
+        ])
+    };
+
+    let content = Paragraph::new(vec![
+        Line::from(Span::styled(
+            "  Keybindings",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        row("Tab", "Switch window"),
+        row("Enter", "Select / Checkout branch"),
+        row("Esc", "Deselect / Close dialog"),
+        row("Up / Down", "Navigate list"),
+        row("Left / Right", "Switch branch tab"),
+        row("Space", "Stage / unstage file or dir"),
+        row("a / A", "Stage all / unstage all"),
+        row("m", "Mark file for batch action"),
+        row("i", "Add to .gitignore"),
+        row("z", "Stash changes"),
+        row("Left / Right", "Commits / Stash (Graph)"),
+        row("Space / p / d", "Apply / pop / drop stash"),
+        row("Space (Diff)", "Stage / unstage hunk"),
+        row("v (Diff)", "Select lines to stage"),
+        row("d", "Discard file / hunk / lines"),
+        Line::from(""),
+        row("c", "Commit staged changes"),
+        row("C", "Amend the last commit"),
+        row("b", "Graph: HEAD only / all refs"),
+        row("[ / ]", "Previous / next file in commit"),
+        row("/", "Filter the commit graph"),
+        row("h", "History of the selected file"),
+        row("B", "Blame the selected file"),
+        row("m / p (Graph)", "Mark commits / cherry-pick"),
+        row("r (Graph)", "Revert the selected commit"),
+        row("R (Graph)", "Reset branch to selected commit"),
+        row("i (Graph)", "Interactive rebase from commit"),
+        row("F (Graph)", "Autosquash fixup! commits"),
+        row("w (Graph)", "Reword the selected commit"),
+        row("Ctrl+F (Commit)", "fixup! / squash! graph commit"),
+        row("o / S / O", "Continue / skip / abort operation"),
+        row("P", "Push to remote"),
+        row("Enter (Remote)", "Set selected remote for push"),
+        row("n", "New branch (Local tab)"),
+        row("a", "Add remote (Remote tab)"),
+        row("d", "Delete remote (Remote tab)"),
+        row("s", "Rescan git status"),
+        row("q", "Quit"),
+        Line::from(""),
+        row("Left / Right", "Move cursor in input"),
+        row("Home / End", "Jump to start / end"),
+        row("Delete", "Delete char at cursor"),
+        Line::from(""),
+        Line::from(Span::styled(
+            "  Press any key to close",
+            Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::ITALIC),
+        )),
+    ])
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(" ? Help ")
+            .border_style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+    );
+
+    f.render_widget(content, dialog_area);
+}
+
+fn graph_tab_title(app: &App) -> Line<'static> {
+    let tab_style = |active: bool| {
+        if active {
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        }
+    };
+
+    Line::from(vec![
+        Span::styled(
+            if app.graph_all_refs {
+                " Commit Graph (all refs) "
+            } else {
+                " Commit Graph "
+            },
+            tab_style(app.graph_tab == GraphTab::Commits),
+        ),
+        Span::raw(" "),
+        Span::styled(
+            format!(" Stash ({}) ", app.stashes.len()),
+            tab_style(app.graph_tab == GraphTab::Stash),
+        ),
+    ])
+}
+
+fn draw_stash_panel(f: &mut Frame, area: ratatui::layout::Rect, app: &mut App) {
+    let h = Helper;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    let items: Vec<ListItem> = if app.stashes.is_empty() {
+        vec![
+            ListItem::new("No stashes  |z| Stash changes")
+                .style(Style::default().fg(Color::DarkGray)),
+        ]
+    } else {
+        app.stashes
+            .iter()
+            .map(|stash| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("stash@{{{}}} ", stash.index),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::styled(
+                        format!("{:>8} ", h.format_age(now - stash.time)),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(stash.message.clone(), Style::default().fg(Color::White)),
+                ]))
+            })
+            .collect()
+    };
+
+    let hints = Line::from(Span::styled(
+        " |Space| apply  |p| pop  |d| drop  |z| save ",
+        Style::default().fg(Color::DarkGray),
+    ))
+    .right_aligned();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(graph_tab_title(app))
+                .title_bottom(hints)
+                .border_style(if app.window_index == 1 {
+                    BORDER_STYLE
+                } else {
+                    BORDER_DEFAULT_STYLE
+                }),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("▶ ");
+
+    f.render_stateful_widget(list, area, &mut app.stash_state);
+}
+
+const GRAPH_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Red,
+];
+
+fn graph_color(slot: usize) -> Color {
+    GRAPH_COLORS[slot % GRAPH_COLORS.len()]
+}
+
+fn ref_label_style(kind: &RefKind) -> Style {
+    match kind {
+        RefKind::Head => Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD | Modifier::REVERSED),
+        RefKind::Local => Style::default()
+            .fg(Color::Green)
+            .add_modifier(Modifier::BOLD),
+        RefKind::Remote => Style::default().fg(Color::Red),
+        RefKind::Tag => Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+    }
+}
+
+fn draw_commit_graph_panel(f: &mut Frame, area: ratatui::layout::Rect, app: &mut App) {
+    if app.graph_tab == GraphTab::Stash {
+        return draw_stash_panel(f, area, app);
+    }
+
+    let total = app.commit_graph.len();
+    let selected = app.commit_graph_state.selected().unwrap_or(0);
+    let current = if total == 0 { 0 } else { selected + 1 };
+
+    let more = if app.graph_has_more() { "+" } else { "" };
+    let mut counter_spans = Vec::new();
+    if let Some(filter) = &app.graph_filter {
+        counter_spans.push(Span::styled(
+            format!(" / {} ", filter.query),
+            Style::default().fg(Color::Magenta),
+        ));
+        if app.graph_has_more() {
+            counter_spans.push(Span::styled(
+                format!(" searched {} commits… ", app.graph_scanned),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+    }
+    counter_spans.push(Span::styled(
+        format!(" {} of {}{} ", current, total, more),
+        Style::default().fg(Color::DarkGray),
+    ));
+    let counter = Line::from(counter_spans).right_aligned();
+
+    let items: Vec<ListItem> = if app.commit_graph.is_empty() {
+        let msg = if app.graph_filter.is_some() && app.graph_has_more() {
+            format!(
+                "No matches in {} commits yet; ↓ to search further",
+                app.graph_scanned
+            )
+        } else if app.graph_filter.is_some() {
+            "No commits match the filter".to_string()
+        } else {
+            "No commits found".to_string()
+        };
+        vec![ListItem::new(msg).style(Style::default().fg(Color::DarkGray))]
+    } else {
+        app.commit_graph
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let mut spans: Vec<Span> = app
+                    .commit_graph_rows
+                    .get(i)
+                    .map(|row| {
+                        row.iter()
+                            .map(|cell| {
+                                Span::styled(
+                                    cell.ch.to_string(),
+                                    Style::default().fg(graph_color(cell.color)),
+                                )
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                spans.push(Span::raw(" "));
+
+                let (sha, summary) = line.split_once(' ').unwrap_or((line.as_str(), ""));
+                let oid = app.commit_graph_oids.get(i);
+                if oid.is_some_and(|oid| app.graph_marked.contains(oid)) {
+                    spans.push(Span::styled("✔ ", Style::default().fg(Color::Green)));
+                }
+                spans.push(Span::styled(
+                    sha.to_string(),
+                    Style::default().fg(Color::Yellow),
+                ));
+                spans.push(Span::raw(" "));
+
+                if let Some(labels) = oid.and_then(|oid| app.commit_graph_labels.get(oid)) {
+                    for label in labels {
+                        spans.push(Span::styled(
+                            format!("({})", label.name),
+                            ref_label_style(&label.kind),
+                        ));
+                        spans.push(Span::raw(" "));
+                    }
+                }
+
+                let is_head = oid.is_some() && oid == app.commit_graph_head.as_ref();
+                let summary_style = if is_head {
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                spans.push(Span::styled(summary.to_string(), summary_style));
+                ListItem::new(Line::from(spans))
+            })
+            .collect()
+    };
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(graph_tab_title(app))
+                .title_bottom(counter)
+                .border_style(if app.window_index == 1 {
+                    BORDER_STYLE
+                } else {
+                    BORDER_DEFAULT_STYLE
+                }),
+        )
+        .highlight_style(
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("▶ ");
+
     f.render_stateful_widget(list, area, &mut app.commit_graph_state);
 }
 
@@ -641,7 +1648,13 @@ fn draw_commit_dialog(f: &mut Frame, app: &mut App) {
         Block::default()
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
-            .title(" Commit Summary ")
+            .title(if app.commit_amend {
+                " Amend Summary "
+            } else if app.commit_reword.is_some() {
+                " Reword Summary "
+            } else {
+                " Commit Summary "
+            })
             .border_style(summary_border_style),
     );
 
@@ -667,12 +1680,111 @@ fn draw_commit_dialog(f: &mut Frame, app: &mut App) {
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
             .title(" Commit Description ")
+            .title_bottom(commit_options_line(app))
             .border_style(description_border_style),
     );
 
     f.render_widget(description, chunks[1]);
 }
 
+fn commit_options_line(app: &App) -> Line<'static> {
+    let mut spans = vec![];
+    if app.commit_amend_allowed() {
+        let mark = if app.commit_amend { "x" } else { " " };
+        spans.push(Span::styled(
+            format!(" [{}] amend HEAD (Shift+Tab) ", mark),
+            Style::default().fg(Color::Gray),
+        ));
+    }
+
+    let mark = if app.commit_skip_hooks { "x" } else { " " };
+    spans.push(Span::styled(
+        format!(" [{}] skip hooks (Ctrl+N) ", mark),
+        Style::default().fg(Color::Gray),
+    ));
+
+    let fixup = match app.commit_fixup {
+        Some((action, oid)) => format!("{}! {}", action.name(), &oid.to_string()[..7]),
+        None => "fixup!".to_string(),
+    };
+    let mark = if app.commit_fixup.is_some() { "x" } else { " " };
+    spans.push(Span::styled(
+        format!(" [{}] {} (Ctrl+F) ", mark, fixup),
+        Style::default().fg(Color::Gray),
+    ));
+
+    if let Some(upstream) = &app.commit_amend_pushed {
+        let commit = app
+            .commit_reword
+            .map(|oid| oid.to_string()[..7].to_string())
+            .unwrap_or_else(|| "HEAD".to_string());
+        spans.push(Span::styled(
+            format!(" ⚠ {} is already on {} ", commit, upstream),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    Line::from(spans)
+}
+
+/// A one-line reminder that a cherry-pick or similar is waiting for the
+/// user, with what to do about it.
+fn draw_operation_banner(f: &mut Frame, area: Rect, app: &App) {
+    let Some(op) = &app.operation else {
+        return;
+    };
+    let current = op
+        .current
+        .map(|oid| format!(" at {}", &oid.to_string()[..7]))
+        .unwrap_or_default();
+    let status = if !op.resumable {
+        "started by git; finish it with git rebase --continue or --abort".to_string()
+    } else if !op.conflicts.is_empty() {
+        format!(
+            "{} conflicted file{}: {}",
+            op.conflicts.len(),
+            if op.conflicts.len() == 1 { "" } else { "s" },
+            op.conflicts.join(", ")
+        )
+    } else if app.rebase_stop == Some(RebaseStop::Message) {
+        "waiting for the commit message".to_string()
+    } else if app.rebase_stop == Some(RebaseStop::Edit) {
+        "stopped to edit; amend with C".to_string()
+    } else {
+        "conflicts resolved".to_string()
+    };
+    let remaining = if op.queue.is_empty() {
+        String::new()
+    } else {
+        format!(", {} more to go", op.queue.len())
+    };
+
+    let line = Line::from(vec![
+        Span::styled(
+            format!(" {} in progress{} ", op.kind.name().to_uppercase(), current),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format!(" {}{} ", status, remaining),
+            Style::default().fg(Color::Yellow),
+        ),
+        Span::styled(
+            if !op.resumable {
+                ""
+            } else if op.kind == OperationKind::Revert {
+                " o: continue  O: abort "
+            } else {
+                " o: continue  S: skip  O: abort "
+            },
+            Style::default().fg(Color::DarkGray),
+        ),
+    ]);
+    f.render_widget(Paragraph::new(line), area);
+}
+
 fn draw_footer(area: Rect, app: &App, f: &mut Frame) {
     let focused_panel = if app.focused {
         "Focused"
@@ -785,6 +1897,73 @@ fn draw_add_remote_dialog(f: &mut Frame, app: &App) {
     f.render_widget(url_input, chunks[1]);
 }
 
+fn draw_stash_dialog(f: &mut Frame, app: &App) {
+    let area = f.area();
+    let dialog_width = 60u16;
+    let dialog_height = 9u16;
+
+    let x = (area.width.saturating_sub(dialog_width)) / 2;
+    let y = (area.height.saturating_sub(dialog_height)) / 2;
+
+    let dialog_area = ratatui::layout::Rect {
+        x,
+        y,
+        width: dialog_width,
+        height: dialog_height,
+    };
+
+    f.render_widget(Clear, dialog_area);
+
+    let outer_block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(" Stash Changes ")
+        .border_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let inner = outer_block.inner(dialog_area);
+    f.render_widget(outer_block, dialog_area);
+
+    let checkbox = |on: bool| if on { "[x]" } else { "[ ]" };
+
+    let hint = Paragraph::new(vec![
+        Line::from(vec![
+            Span::styled("  Message: ", Style::default().fg(Color::DarkGray)),
+            Span::styled(
+                app.stash_message.as_str(),
+                Style::default().fg(Color::White),
+            ),
+            Span::styled("█", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!(
+                "  {} Include untracked files   [Tab]",
+                checkbox(app.stash_include_untracked)
+            ),
+            Style::default().fg(Color::White),
+        )),
+        Line::from(Span::styled(
+            format!(
+                "  {} Keep staged changes       [Shift+Tab]",
+                checkbox(app.stash_keep_index)
+            ),
+            Style::default().fg(Color::White),
+        )),
+        Line::from(""),
+        Line::from(Span::styled(
+            "  [Enter] Stash   [Esc] Cancel",
+            Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::ITALIC),
+        )),
+    ]);
+    f.render_widget(hint, inner);
+}
+
 fn draw_new_branch_dialog(f: &mut Frame, app: &App) {
     let area = f.area();
     let dialog_width = 60u16;
@@ -834,3 +2013,174 @@ fn draw_new_branch_dialog(f: &mut Frame, app: &App) {
     ]);
     f.render_widget(hint, inner);
 }
+
+fn draw_rebase_planner(f: &mut Frame, app: &mut App) {
+    let Some(plan) = app.rebase_plan.clone() else {
+        return;
+    };
+    let area = f.area();
+    let dialog_width = 76u16.min(area.width);
+    let dialog_height = (plan.steps.len() as u16 + 8).clamp(12, area.height.max(12));
+
+    let x = (area.width.saturating_sub(dialog_width)) / 2;
+    let y = (area.height.saturating_sub(dialog_height)) / 2;
+
+    let dialog_area = ratatui::layout::Rect {
+        x,
+        y,
+        width: dialog_width,
+        height: dialog_height.min(area.height),
+    };
+
+    f.render_widget(Clear, dialog_area);
+
+    let outer_block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(format!(
+            " Interactive Rebase onto {} ",
+            &plan.upstream.to_string()[..7]
+        ))
+        .border_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let inner = outer_block.inner(dialog_area);
+    f.render_widget(outer_block, dialog_area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(4)])
+        .split(inner);
+
+    let items: Vec<ListItem> = plan
+        .steps
+        .iter()
+        .map(|step| {
+            let color = match step.action {
+                RebaseAction::Pick => Color::Green,
+                RebaseAction::Reword | RebaseAction::Edit => Color::Cyan,
+                RebaseAction::Squash | RebaseAction::Fixup => Color::Magenta,
+                RebaseAction::Drop => Color::Red,
+            };
+            let mut summary = Style::default().fg(Color::White);
+            if step.action == RebaseAction::Drop {
+                summary = summary
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::CROSSED_OUT);
+            }
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!(" {:<7}", step.action.name()),
+                    Style::default().fg(color).add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    format!("{} ", &step.oid.to_string()[..7]),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::styled(step.summary.clone(), summary),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_style(Style::default().bg(Color::DarkGray))
+        .highlight_symbol("▶ ");
+    f.render_stateful_widget(list, chunks[0], &mut app.rebase_plan_state);
+
+    let status = match &app.rebase_plan_error {
+        Some(err) => Line::from(Span::styled(
+            format!("  {}", err),
+            Style::default().fg(Color::Red),
+        )),
+        None => Line::from(""),
+    };
+    let hint = Paragraph::new(vec![
+        status,
+        Line::from(Span::styled(
+            "  p pick  r reword  e edit  s squash  f fixup  d drop",
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(Span::styled(
+            "  K / J move up / down   Enter start   Esc cancel",
+            Style::default().fg(Color::DarkGray),
+        )),
+    ]);
+    f.render_widget(hint, chunks[1]);
+}
+
+fn draw_graph_filter_dialog(f: &mut Frame, app: &App) {
+    let area = f.area();
+    let dialog_width = 72u16;
+    let dialog_height = 11u16;
+
+    let x = (area.width.saturating_sub(dialog_width)) / 2;
+    let y = (area.height.saturating_sub(dialog_height)) / 2;
+
+    let dialog_area = ratatui::layout::Rect {
+        x,
+        y,
+        width: dialog_width,
+        height: dialog_height,
+    };
+
+    f.render_widget(Clear, dialog_area);
+
+    let outer_block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(" Filter History ")
+        .border_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let inner = outer_block.inner(dialog_area);
+    f.render_widget(outer_block, dialog_area);
+
+    let status = match &app.graph_filter_error {
+        Some(err) => Line::from(Span::styled(
+            format!("  {}", err),
+            Style::default().fg(Color::Red),
+        )),
+        None => Line::from(""),
+    };
+
+    let hint = Paragraph::new(vec![
+        Line::from(vec![
+            Span::styled("  Filter: ", Style::default().fg(Color::DarkGray)),
+            Span::styled(
+                app.graph_filter_input.as_str(),
+                Style::default().fg(Color::White),
+            ),
+            Span::styled("█", Style::default().fg(Color::Yellow)),
+        ]),
+        status,
+        Line::from(Span::styled(
+            "  author:NAME  since:YYYY-MM-DD  until:YYYY-MM-DD  path:PATH",
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(Span::styled(
+            "  re:REGEX  or plain words to match the message",
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(Span::styled(
+            "  S:TEXT  G:REGEX  search what each commit added or removed",
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(Span::styled(
+            "  follow:FILE  history of a file across renames",
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(""),
+        Line::from(Span::styled(
+            "  [Enter] Apply (empty clears)   [Esc] Cancel",
+            Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::ITALIC),
+        )),
+    ]);
+    f.render_widget(hint, inner);
+}
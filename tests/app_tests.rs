@@ -1,4 +1,14 @@
-use fastgit::app::{App, BranchTab, DiffLine, DiffLineKind, Tab};
+use fastgit::app::{
+    App, BranchTab, DiffLine, DiffLineKind, DiscardTarget, GRAPH_DIFF_SCAN_BUDGET, GRAPH_PAGE_SIZE,
+    GRAPH_SCAN_BUDGET, GraphTab, Tab,
+};
+use fastgit::commit_detail::SignatureStatus;
+use fastgit::graph::RefKind;
+use fastgit::operation::OperationKind;
+use fastgit::rebase::{RebaseAction, RebaseStop};
+use git2::{Repository, Status};
+use std::fs;
+use std::path::{Path, PathBuf};
 
 #[test]
 fn tab_enum_equality() {
@@ -401,3 +411,2734 @@ fn remote_next_empty_selects_none() {
     app.remote_next();
     assert_eq!(app.remote_state.selected(), None);
 }
+
+fn scratch_repo(name: &str) -> (PathBuf, Repository) {
+    let dir = std::env::temp_dir().join(format!("fastgit-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let repo = Repository::init(&dir).unwrap();
+    {
+        let mut cfg = repo.config().unwrap();
+        cfg.set_str("user.name", "fastgit").unwrap();
+        cfg.set_str("user.email", "fastgit@example.com").unwrap();
+    }
+    (dir, repo)
+}
+
+fn commit_all(repo: &Repository, message: &str) {
+    let mut index = repo.index().unwrap();
+    index
+        .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+        .unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = repo.signature().unwrap();
+    let parents: Vec<git2::Commit> = repo
+        .head()
+        .ok()
+        .and_then(|h| h.peel_to_commit().ok())
+        .into_iter()
+        .collect();
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+    repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parent_refs)
+        .unwrap();
+}
+
+fn app_for(dir: &Path, file: &str) -> App {
+    let mut app = App::new();
+    app.cur_dir = dir.display().to_string();
+    app.scan_git();
+    app.refresh_repository_view();
+    app.selected_file = Some(PathBuf::from(file));
+    app.load_diff();
+    app
+}
+
+fn numbered_lines(n: usize) -> String {
+    (1..=n).map(|i| format!("line {}\n", i)).collect()
+}
+
+#[test]
+fn load_diff_keeps_hunk_boundaries() {
+    let (dir, repo) = scratch_repo("hunks");
+    fs::write(dir.join("a.txt"), numbered_lines(30)).unwrap();
+    commit_all(&repo, "init");
+    let edited = numbered_lines(30)
+        .replace("line 2\n", "line two\n")
+        .replace("line 28\n", "line twenty-eight\n");
+    fs::write(dir.join("a.txt"), edited).unwrap();
+
+    let app = app_for(&dir, "a.txt");
+    assert_eq!(app.diff_hunks.len(), 2);
+    assert!(app.diff_hunks.iter().all(|h| !h.staged));
+    for hunk in &app.diff_hunks {
+        assert!(app.diff_content[hunk.start].content.starts_with("@@"));
+        assert!(hunk.end > hunk.start + 1);
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn toggle_stage_hunk_stages_only_that_hunk() {
+    let (dir, repo) = scratch_repo("stage-hunk");
+    fs::write(dir.join("a.txt"), numbered_lines(30)).unwrap();
+    commit_all(&repo, "init");
+    let edited = numbered_lines(30)
+        .replace("line 2\n", "line two\n")
+        .replace("line 28\n", "line twenty-eight\n");
+    fs::write(dir.join("a.txt"), edited).unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.diff_cursor = app.diff_hunks[0].start + 1;
+    app.toggle_stage_hunk().unwrap();
+
+    assert_eq!(app.staged_count, 1);
+    let status = app.file_statuses[&PathBuf::from("a.txt")];
+    assert!(status.contains(Status::INDEX_MODIFIED));
+    assert!(status.contains(Status::WT_MODIFIED));
+    assert_eq!(app.diff_hunks.iter().filter(|h| h.staged).count(), 1);
+    assert_eq!(app.diff_hunks.iter().filter(|h| !h.staged).count(), 1);
+
+    let staged = app.diff_hunks.iter().find(|h| h.staged).unwrap().clone();
+    assert_eq!(staged.new_start, 1);
+
+    app.diff_cursor = staged.start;
+    app.toggle_stage_hunk().unwrap();
+    assert_eq!(app.staged_count, 0);
+    assert!(app.diff_hunks.iter().all(|h| !h.staged));
+    assert_eq!(app.diff_hunks.len(), 2);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn toggle_stage_hunk_adds_untracked_file() {
+    let (dir, repo) = scratch_repo("stage-new");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "init");
+    fs::write(dir.join("b.txt"), "one\ntwo\n").unwrap();
+
+    let mut app = app_for(&dir, "b.txt");
+    assert_eq!(app.diff_hunks.len(), 1);
+    app.diff_cursor = app.diff_hunks[0].start;
+    app.toggle_stage_hunk().unwrap();
+
+    let status = app.file_statuses[&PathBuf::from("b.txt")];
+    assert_eq!(status, Status::INDEX_NEW);
+    assert_eq!(app.staged_count, 1);
+
+    app.diff_cursor = app.diff_hunks[0].start;
+    app.toggle_stage_hunk().unwrap();
+    let status = app.file_statuses[&PathBuf::from("b.txt")];
+    assert_eq!(status, Status::WT_NEW);
+    assert_eq!(app.staged_count, 0);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn diff_cursor_moves_and_drags_scroll() {
+    let mut app = App::new();
+    app.diff_content = (0..5)
+        .map(|i| DiffLine {
+            kind: DiffLineKind::Context,
+            content: i.to_string(),
+        })
+        .collect();
+    app.diff_cursor = 0;
+    app.diff_scroll = 0;
+    app.diff_view_height = 2;
+    app.diff_cursor_down();
+    assert_eq!((app.diff_cursor, app.diff_scroll), (1, 0));
+    app.diff_cursor_down();
+    assert_eq!((app.diff_cursor, app.diff_scroll), (2, 1));
+    app.diff_cursor_down();
+    app.diff_cursor_down();
+    app.diff_cursor_down();
+    assert_eq!(app.diff_cursor, 4);
+    app.diff_cursor_up();
+    app.diff_cursor_up();
+    app.diff_cursor_up();
+    assert_eq!((app.diff_cursor, app.diff_scroll), (1, 1));
+    app.diff_cursor_up();
+    assert_eq!((app.diff_cursor, app.diff_scroll), (0, 0));
+}
+
+#[test]
+fn toggle_stage_lines_stages_only_selected_lines() {
+    let (dir, repo) = scratch_repo("stage-lines");
+    fs::write(dir.join("a.txt"), "one\ntwo\nthree\n").unwrap();
+    commit_all(&repo, "init");
+    fs::write(dir.join("a.txt"), "one\nadded 1\nadded 2\ntwo\nthree\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    let add_row = app
+        .diff_content
+        .iter()
+        .position(|l| l.kind == DiffLineKind::Add && l.content == "added 2")
+        .unwrap();
+    app.diff_cursor = add_row;
+    app.toggle_diff_selection();
+    assert!(app.is_diff_row_selected(add_row));
+    app.toggle_stage_lines().unwrap();
+
+    let mut index = repo.index().unwrap();
+    index.read(true).unwrap();
+    let entry = index.get_path(Path::new("a.txt"), 0).unwrap();
+    let blob = repo.find_blob(entry.id).unwrap();
+    assert_eq!(blob.content(), b"one\nadded 2\ntwo\nthree\n");
+    assert_eq!(
+        fs::read_to_string(dir.join("a.txt")).unwrap(),
+        "one\nadded 1\nadded 2\ntwo\nthree\n"
+    );
+    assert!(app.diff_select_anchor.is_none());
+    assert_eq!(app.staged_count, 1);
+
+    let staged_row = app
+        .diff_content
+        .iter()
+        .enumerate()
+        .position(|(row, l)| {
+            l.content == "added 2" && app.diff_hunks.iter().any(|h| h.staged && h.contains(row))
+        })
+        .unwrap();
+    app.diff_cursor = staged_row;
+    app.toggle_diff_selection();
+    app.toggle_stage_lines().unwrap();
+    assert_eq!(app.staged_count, 0);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn toggle_stage_lines_on_untracked_file() {
+    let (dir, repo) = scratch_repo("stage-lines-new");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "init");
+    fs::write(dir.join("b.txt"), "keep\nskip\n").unwrap();
+
+    let mut app = app_for(&dir, "b.txt");
+    let row = app
+        .diff_content
+        .iter()
+        .position(|l| l.content == "keep")
+        .unwrap();
+    app.diff_cursor = row;
+    app.toggle_diff_selection();
+    app.toggle_stage_lines().unwrap();
+
+    let mut index = repo.index().unwrap();
+    index.read(true).unwrap();
+    let entry = index.get_path(Path::new("b.txt"), 0).unwrap();
+    assert_eq!(repo.find_blob(entry.id).unwrap().content(), b"keep\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn toggle_stage_lines_keeps_the_exec_bit_of_an_untracked_file() {
+    use std::os::unix::fs::PermissionsExt;
+    let (dir, repo) = scratch_repo("stage-lines-exec");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "init");
+    fs::write(dir.join("run.sh"), "#!/bin/sh\necho hi\n").unwrap();
+    fs::set_permissions(dir.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut app = app_for(&dir, "run.sh");
+    app.diff_cursor = app
+        .diff_content
+        .iter()
+        .position(|l| l.content == "#!/bin/sh")
+        .unwrap();
+    app.toggle_diff_selection();
+    app.toggle_stage_lines().unwrap();
+
+    let mut index = repo.index().unwrap();
+    index.read(true).unwrap();
+    let entry = index.get_path(Path::new("run.sh"), 0).unwrap();
+    assert_eq!(entry.mode, 0o100755);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn discard_hunk_reverts_only_that_hunk() {
+    let (dir, repo) = scratch_repo("discard-hunk");
+    fs::write(dir.join("a.txt"), numbered_lines(30)).unwrap();
+    commit_all(&repo, "init");
+    let edited = numbered_lines(30)
+        .replace("line 2\n", "line two\n")
+        .replace("line 28\n", "line twenty-eight\n");
+    fs::write(dir.join("a.txt"), edited).unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.focused = true;
+    app.diff_cursor = app.diff_hunks[0].start + 1;
+    app.open_discard_dialog();
+    assert_eq!(
+        app.discard_confirm,
+        Some(DiscardTarget::Hunk(PathBuf::from("a.txt")))
+    );
+    app.confirm_discard();
+    assert!(app.discard_confirm.is_none());
+    assert!(app.discard_error.is_none());
+
+    let content = fs::read_to_string(dir.join("a.txt")).unwrap();
+    assert!(content.contains("line 2\n"));
+    assert!(content.contains("line twenty-eight\n"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn discard_lines_reverts_only_selected_lines() {
+    let (dir, repo) = scratch_repo("discard-lines");
+    fs::write(dir.join("a.txt"), "one\ntwo\n").unwrap();
+    commit_all(&repo, "init");
+    fs::write(dir.join("a.txt"), "one\nkeep\ndrop\ntwo\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.focused = true;
+    app.diff_cursor = app
+        .diff_content
+        .iter()
+        .position(|l| l.content == "drop")
+        .unwrap();
+    app.toggle_diff_selection();
+    app.open_discard_dialog();
+    assert_eq!(
+        app.discard_confirm,
+        Some(DiscardTarget::Lines(PathBuf::from("a.txt")))
+    );
+    app.confirm_discard();
+
+    assert_eq!(
+        fs::read_to_string(dir.join("a.txt")).unwrap(),
+        "one\nkeep\ntwo\n"
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn discard_file_keeps_staged_changes() {
+    let (dir, repo) = scratch_repo("discard-file");
+    fs::write(dir.join("a.txt"), "one\n").unwrap();
+    commit_all(&repo, "init");
+    fs::write(dir.join("a.txt"), "staged\n").unwrap();
+    let mut app = app_for(&dir, "a.txt");
+    app.toggle_stage(Path::new("a.txt")).unwrap();
+    fs::write(dir.join("a.txt"), "unstaged\n").unwrap();
+    app.refresh_repository_view();
+    app.tree.state.select(Some(0));
+
+    app.open_discard_dialog();
+    app.confirm_discard();
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "staged\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn discard_untracked_file_needs_second_confirmation() {
+    let (dir, repo) = scratch_repo("discard-new");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "init");
+    fs::write(dir.join("new.txt"), "scratch\n").unwrap();
+
+    let mut app = app_for(&dir, "new.txt");
+    app.tree.state.select(Some(0));
+    app.open_discard_dialog();
+    app.confirm_discard();
+    assert!(app.discard_delete_confirm);
+    assert!(dir.join("new.txt").exists());
+
+    app.confirm_discard();
+    assert!(app.discard_confirm.is_none());
+    assert!(!dir.join("new.txt").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn cancel_discard_clears_pending_state() {
+    let mut app = App::new();
+    app.discard_confirm = Some(DiscardTarget::File(PathBuf::from("x")));
+    app.discard_delete_confirm = true;
+    app.cancel_discard();
+    assert!(app.discard_confirm.is_none());
+    assert!(!app.discard_delete_confirm);
+}
+
+#[test]
+fn toggle_stage_dir_stages_then_unstages_everything_under_it() {
+    let (dir, repo) = scratch_repo("stage-dir");
+    fs::create_dir_all(dir.join("src/nested")).unwrap();
+    fs::write(dir.join("src/a.rs"), "a\n").unwrap();
+    fs::write(dir.join("top.txt"), "top\n").unwrap();
+    commit_all(&repo, "init");
+    fs::write(dir.join("src/a.rs"), "a2\n").unwrap();
+    fs::write(dir.join("src/nested/b.rs"), "b\n").unwrap();
+    fs::write(dir.join("top.txt"), "top2\n").unwrap();
+
+    let mut app = app_for(&dir, "top.txt");
+    app.toggle_stage_dir(Path::new("src")).unwrap();
+    assert_eq!(app.staged_count, 2);
+    assert_eq!(
+        app.file_statuses[&PathBuf::from("src/nested/b.rs")],
+        Status::INDEX_NEW
+    );
+    assert_eq!(
+        app.file_statuses[&PathBuf::from("top.txt")],
+        Status::WT_MODIFIED
+    );
+
+    app.toggle_stage_dir(Path::new("src")).unwrap();
+    assert_eq!(app.staged_count, 0);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn stage_all_and_unstage_all() {
+    let (dir, repo) = scratch_repo("stage-all");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    fs::write(dir.join("gone.txt"), "gone\n").unwrap();
+    commit_all(&repo, "init");
+    fs::write(dir.join("a.txt"), "a2\n").unwrap();
+    fs::write(dir.join("b.txt"), "b\n").unwrap();
+    fs::remove_file(dir.join("gone.txt")).unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.stage_all().unwrap();
+    assert_eq!(app.staged_count, 3);
+    assert_eq!(
+        app.file_statuses[&PathBuf::from("gone.txt")],
+        Status::INDEX_DELETED
+    );
+
+    app.unstage_all().unwrap();
+    assert_eq!(app.staged_count, 0);
+    assert_eq!(
+        app.file_statuses[&PathBuf::from("b.txt")],
+        Status::WT_NEW
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn toggle_stage_marked_stages_only_marked_paths() {
+    let (dir, repo) = scratch_repo("stage-marked");
+    fs::write(dir.join("keep.txt"), "k\n").unwrap();
+    commit_all(&repo, "init");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    fs::write(dir.join("b.txt"), "b\n").unwrap();
+    fs::write(dir.join("c.txt"), "c\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.tree.marked.insert(PathBuf::from("./a.txt"));
+    app.tree.marked.insert(PathBuf::from("./c.txt"));
+    app.toggle_stage_marked().unwrap();
+
+    assert_eq!(app.staged_count, 2);
+    assert_eq!(
+        app.file_statuses[&PathBuf::from("b.txt")],
+        Status::WT_NEW
+    );
+    assert!(app.tree.marked.is_empty());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn ignore_selected_appends_marked_paths_to_gitignore() {
+    let (dir, repo) = scratch_repo("ignore");
+    fs::write(dir.join(".gitignore"), "target").unwrap();
+    commit_all(&repo, "init");
+    fs::create_dir_all(dir.join("logs")).unwrap();
+    fs::write(dir.join("logs/out.log"), "x\n").unwrap();
+    fs::write(dir.join("notes.txt"), "n\n").unwrap();
+
+    let mut app = app_for(&dir, "notes.txt");
+    app.tree.marked.insert(PathBuf::from("./logs"));
+    app.ignore_selected().unwrap();
+
+    assert_eq!(
+        fs::read_to_string(dir.join(".gitignore")).unwrap(),
+        "target\n/logs/out.log\n"
+    );
+    assert!(!app.file_statuses.contains_key(&PathBuf::from("logs/out.log")));
+    assert!(app.file_statuses.contains_key(&PathBuf::from("notes.txt")));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn ignore_asks_before_writing_gitignore() {
+    let (dir, repo) = scratch_repo("ignore-confirm");
+    fs::write(dir.join(".gitignore"), "target\n").unwrap();
+    commit_all(&repo, "init");
+    fs::write(dir.join("notes.txt"), "n\n").unwrap();
+
+    let mut app = app_for(&dir, "notes.txt");
+    app.open_ignore_dialog();
+    assert_eq!(app.ignore_confirm, Some(vec!["/notes.txt".to_string()]));
+    app.cancel_ignore();
+    assert!(app.ignore_confirm.is_none());
+    assert_eq!(
+        fs::read_to_string(dir.join(".gitignore")).unwrap(),
+        "target\n"
+    );
+
+    app.open_ignore_dialog();
+    app.confirm_ignore().unwrap();
+    assert!(app.ignore_confirm.is_none());
+    assert_eq!(
+        fs::read_to_string(dir.join(".gitignore")).unwrap(),
+        "target\n/notes.txt\n"
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn discard_marked_paths_after_confirmation() {
+    let (dir, repo) = scratch_repo("discard-marked");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    fs::write(dir.join("b.txt"), "b\n").unwrap();
+    commit_all(&repo, "init");
+    fs::write(dir.join("a.txt"), "a2\n").unwrap();
+    fs::write(dir.join("b.txt"), "b2\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.tree.marked.insert(PathBuf::from("./a.txt"));
+    app.tree.marked.insert(PathBuf::from("./b.txt"));
+    app.open_discard_dialog();
+    assert_eq!(
+        app.discard_confirm,
+        Some(DiscardTarget::Marked(vec![
+            PathBuf::from("a.txt"),
+            PathBuf::from("b.txt")
+        ]))
+    );
+    app.confirm_discard();
+    assert!(!app.discard_delete_confirm);
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a\n");
+    assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "b\n");
+    assert!(app.tree.marked.is_empty());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn graph_tab_toggle_switches_between_commits_and_stash() {
+    let mut app = App::new();
+    assert_eq!(app.graph_tab, GraphTab::Commits);
+    app.graph_tab_toggle();
+    assert_eq!(app.graph_tab, GraphTab::Stash);
+    app.graph_tab_toggle();
+    assert_eq!(app.graph_tab, GraphTab::Commits);
+}
+
+#[test]
+fn stash_save_list_show_and_pop() {
+    let (dir, repo) = scratch_repo("stash");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "init");
+    fs::write(dir.join("a.txt"), "a2\n").unwrap();
+    fs::write(dir.join("new.txt"), "n\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.graph_tab = GraphTab::Stash;
+    app.open_stash_dialog();
+    app.stash_message = "wip thing".to_string();
+    app.stash_include_untracked = true;
+    app.confirm_stash_save();
+    assert!(app.stash_error.is_none());
+    assert!(!app.show_stash_dialog);
+    assert_eq!(app.stashes.len(), 1);
+    assert!(app.stashes[0].message.contains("wip thing"));
+    assert!(app.file_statuses.is_empty());
+    assert!(!dir.join("new.txt").exists());
+
+    app.load_stash_diff(0);
+    assert!(
+        app.commit_diff_label
+            .as_deref()
+            .unwrap()
+            .starts_with("stash@{0}")
+    );
+    assert!(app.diff_content.iter().any(|l| l.content == "a2"));
+    assert!(app.diff_content.iter().any(|l| l.content == "n"));
+
+    app.apply_selected_stash(true);
+    assert!(app.stash_error.is_none());
+    assert!(app.stashes.is_empty());
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a2\n");
+    assert!(dir.join("new.txt").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn stash_keep_index_apply_and_drop() {
+    let (dir, repo) = scratch_repo("stash-keep");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    fs::write(dir.join("b.txt"), "b\n").unwrap();
+    commit_all(&repo, "init");
+    fs::write(dir.join("a.txt"), "a2\n").unwrap();
+    fs::write(dir.join("b.txt"), "b2\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.toggle_stage(Path::new("a.txt")).unwrap();
+    app.open_stash_dialog();
+    app.stash_keep_index = true;
+    app.confirm_stash_save();
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a2\n");
+    assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "b\n");
+
+    commit_all(&repo, "keep a");
+    app.apply_selected_stash(false);
+    assert!(app.stash_error.is_none());
+    assert_eq!(app.stashes.len(), 1);
+    assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "b2\n");
+
+    app.stash_drop_confirm = true;
+    app.drop_selected_stash();
+    assert!(!app.stash_drop_confirm);
+    assert!(app.stashes.is_empty());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn stash_save_with_clean_tree_reports_error() {
+    let (dir, repo) = scratch_repo("stash-clean");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "init");
+
+    let mut app = app_for(&dir, "a.txt");
+    app.open_stash_dialog();
+    app.confirm_stash_save();
+    assert!(app.stash_error.is_some());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn amend_prefills_head_message_and_rewrites_head() {
+    let (dir, repo) = scratch_repo("amend");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "first");
+    fs::write(dir.join("a.txt"), "b\n").unwrap();
+    commit_all(&repo, "second summary\n\nsecond body\n");
+    let parent = repo
+        .head()
+        .unwrap()
+        .peel_to_commit()
+        .unwrap()
+        .parent_id(0)
+        .unwrap();
+
+    fs::write(dir.join("b.txt"), "new\n").unwrap();
+    let mut app = app_for(&dir, "b.txt");
+    app.toggle_stage(Path::new("b.txt")).unwrap();
+    app.open_amend_dialog();
+    assert!(app.commit_amend);
+    assert!(app.commit_amend_pushed.is_none());
+    assert_eq!(app.commit_summary, "second summary");
+    assert_eq!(app.commit_description, "second body");
+
+    app.commit_summary = "second, amended".to_string();
+    let oid = app.commit().unwrap();
+
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.id(), oid);
+    assert_eq!(head.parent_count(), 1);
+    assert_eq!(head.parent_id(0).unwrap(), parent);
+    assert_eq!(head.message().unwrap(), "second, amended\n\nsecond body");
+    assert!(head.tree().unwrap().get_name("b.txt").is_some());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn toggle_commit_amend_off_clears_message() {
+    let (dir, repo) = scratch_repo("amend-off");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "only");
+
+    let mut app = app_for(&dir, "a.txt");
+    app.open_commit_dialog();
+    app.toggle_commit_amend();
+    assert_eq!(app.commit_summary, "only");
+    app.toggle_commit_amend();
+    assert!(!app.commit_amend);
+    assert!(app.commit_summary.is_empty());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn toggle_commit_amend_needs_a_head_commit() {
+    let (dir, _repo) = scratch_repo("amend-unborn");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.open_amend_dialog();
+    assert!(!app.commit_amend);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn amend_warns_when_head_is_on_upstream() {
+    let (dir, repo) = scratch_repo("amend-pushed");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "pushed");
+    let head = repo.head().unwrap();
+    let branch = head.shorthand().unwrap().to_string();
+    let oid = head.target().unwrap();
+    repo.remote("origin", "https://example.com/repo.git")
+        .unwrap();
+    repo.reference(
+        &format!("refs/remotes/origin/{}", branch),
+        oid,
+        true,
+        "test",
+    )
+    .unwrap();
+    repo.find_branch(&branch, git2::BranchType::Local)
+        .unwrap()
+        .set_upstream(Some(&format!("origin/{}", branch)))
+        .unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.open_amend_dialog();
+    assert_eq!(
+        app.commit_amend_pushed.as_deref(),
+        Some(format!("origin/{}", branch).as_str())
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+fn fake_signer(dir: &Path, body: &str) -> PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+    let path = dir.join(".git").join("fake-gpg");
+    fs::write(&path, format!("#!/bin/sh\ncat > /dev/null\n{}\n", body)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[cfg(unix)]
+#[test]
+fn commit_is_signed_when_gpgsign_is_set() {
+    let (dir, repo) = scratch_repo("sign");
+    let signer = fake_signer(
+        &dir,
+        "printf -- '-----BEGIN PGP SIGNATURE-----\\nfake\\n-----END PGP SIGNATURE-----\\n'",
+    );
+    {
+        let mut cfg = repo.config().unwrap();
+        cfg.set_bool("commit.gpgsign", true).unwrap();
+        cfg.set_str("gpg.program", signer.to_str().unwrap())
+            .unwrap();
+    }
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.toggle_stage(Path::new("a.txt")).unwrap();
+    app.commit_summary = "signed".to_string();
+    let oid = app.commit().unwrap();
+
+    assert_eq!(repo.head().unwrap().target(), Some(oid));
+    let (signature, _) = repo.extract_signature(&oid, None).unwrap();
+    assert!(signature.as_str().unwrap().contains("fake"));
+    assert!(app.commit_sign_error.is_none());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn failed_signing_reports_error_and_keeps_head() {
+    let (dir, repo) = scratch_repo("sign-fail");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "init");
+    let before = repo.head().unwrap().target();
+    let signer = fake_signer(&dir, "echo 'no secret key' >&2\nexit 2");
+    {
+        let mut cfg = repo.config().unwrap();
+        cfg.set_bool("commit.gpgsign", true).unwrap();
+        cfg.set_str("gpg.program", signer.to_str().unwrap())
+            .unwrap();
+    }
+    fs::write(dir.join("a.txt"), "b\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.toggle_stage(Path::new("a.txt")).unwrap();
+    app.commit_summary = "unsigned".to_string();
+    assert!(app.commit().is_err());
+    assert!(
+        app.commit_sign_error
+            .as_deref()
+            .unwrap()
+            .contains("no secret key")
+    );
+    assert_eq!(repo.head().unwrap().target(), before);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn commit_failed_keeps_message_and_dismiss_reopens_dialog() {
+    let (dir, _repo) = scratch_repo("commit-error");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.toggle_stage(Path::new("a.txt")).unwrap();
+    app.open_commit_dialog();
+    app.commit_summary = "summary".to_string();
+    app.commit_description = "body".to_string();
+
+    let err = git2::Error::new(
+        git2::ErrorCode::NotFound,
+        git2::ErrorClass::Config,
+        "config value 'user.name' was not found",
+    );
+    app.commit_failed(&err);
+    assert!(!app.show_commit_dialog);
+    assert_eq!(
+        app.commit_error,
+        Some((
+            git2::ErrorClass::Config,
+            "config value 'user.name' was not found".to_string()
+        ))
+    );
+    assert_eq!(app.commit_summary, "summary");
+    assert_eq!(app.commit_description, "body");
+
+    app.dismiss_commit_error();
+    assert!(app.commit_error.is_none());
+    assert!(app.show_commit_dialog);
+    assert_eq!(app.commit_summary, "summary");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn commit_failed_does_not_repeat_signing_errors() {
+    let mut app = App::new();
+    app.show_commit_dialog = true;
+    app.commit_sign_error = Some("gpg failed".to_string());
+    app.commit_failed(&git2::Error::from_str("gpg failed"));
+    assert!(app.commit_error.is_none());
+    app.dismiss_commit_error();
+    assert!(app.commit_sign_error.is_none());
+    assert!(app.show_commit_dialog);
+}
+
+#[cfg(unix)]
+fn write_hook(repo: &Repository, name: &str, body: &str) {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = repo.path().join("hooks");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[cfg(unix)]
+fn wait_for_hooks(app: &mut App) {
+    for _ in 0..500 {
+        app.check_hook_result();
+        if app.hook_rx.is_none() {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    panic!("hooks did not finish");
+}
+
+#[cfg(unix)]
+#[test]
+fn submit_commit_runs_commit_msg_hook_before_committing() {
+    let (dir, repo) = scratch_repo("hook-commit");
+    write_hook(
+        &repo,
+        "commit-msg",
+        "echo checking\necho 'Refs: #1' >> \"$1\"",
+    );
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.toggle_stage(Path::new("a.txt")).unwrap();
+    app.open_commit_dialog();
+    app.commit_summary = "hooked".to_string();
+    app.submit_commit();
+    assert!(app.show_hook_dialog);
+    assert!(!app.show_commit_dialog);
+
+    wait_for_hooks(&mut app);
+    assert!(app.hook_error.is_none());
+    assert!(!app.show_hook_dialog);
+    assert!(app.commit_success_open);
+    assert!(app.hook_output.contains(&"checking".to_string()));
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.message().unwrap(), "hooked\n\nRefs: #1");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn failing_pre_commit_hook_aborts_and_keeps_message() {
+    let (dir, repo) = scratch_repo("hook-reject");
+    write_hook(&repo, "pre-commit", "echo 'lint: bad' >&2\nexit 1");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.toggle_stage(Path::new("a.txt")).unwrap();
+    app.open_commit_dialog();
+    app.commit_summary = "rejected".to_string();
+    app.submit_commit();
+    wait_for_hooks(&mut app);
+
+    assert!(app.hook_error.as_deref().unwrap().contains("pre-commit"));
+    assert!(app.hook_output.contains(&"lint: bad".to_string()));
+    assert!(repo.head().is_err());
+
+    app.dismiss_hook_dialog();
+    assert!(!app.show_hook_dialog);
+    assert!(app.show_commit_dialog);
+    assert_eq!(app.commit_summary, "rejected");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn skip_hooks_commits_without_running_them() {
+    let (dir, repo) = scratch_repo("hook-skip");
+    write_hook(&repo, "pre-commit", "exit 1");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.toggle_stage(Path::new("a.txt")).unwrap();
+    app.open_commit_dialog();
+    app.commit_summary = "skipped".to_string();
+    app.commit_skip_hooks = true;
+    app.submit_commit();
+
+    assert!(!app.show_hook_dialog);
+    assert!(app.commit_success_open);
+    assert!(!app.commit_skip_hooks);
+    assert_eq!(
+        repo.head().unwrap().peel_to_commit().unwrap().message(),
+        Some("skipped")
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn failing_pre_push_hook_stops_the_push() {
+    let (dir, repo) = scratch_repo("hook-push");
+    let remote_dir = dir.with_extension("remote.git");
+    let _ = fs::remove_dir_all(&remote_dir);
+    Repository::init_bare(&remote_dir).unwrap();
+    repo.remote("origin", remote_dir.to_str().unwrap()).unwrap();
+    write_hook(&repo, "pre-push", "echo \"pushing to $1\"\ncat\nexit 1");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "init");
+
+    let mut app = app_for(&dir, "a.txt");
+    app.start_push();
+    assert!(app.show_hook_dialog);
+    for _ in 0..500 {
+        app.check_push_result();
+        if !app.push_in_progress {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    assert!(!app.push_in_progress);
+    assert!(app.push_error.is_none());
+    assert!(app.hook_error.as_deref().unwrap().contains("pre-push"));
+    assert!(app.hook_output.contains(&"pushing to origin".to_string()));
+    assert!(app.hook_output.iter().any(|l| l.starts_with("refs/heads/")));
+    let remote = Repository::open_bare(&remote_dir).unwrap();
+    assert!(remote.references().unwrap().next().is_none());
+    let _ = fs::remove_dir_all(&dir);
+    let _ = fs::remove_dir_all(&remote_dir);
+}
+
+fn commit_with_parents(repo: &Repository, message: &str, parents: &[git2::Oid]) -> git2::Oid {
+    let sig = repo.signature().unwrap();
+    let tree = repo
+        .find_tree(repo.index().unwrap().write_tree().unwrap())
+        .unwrap();
+    let parents: Vec<git2::Commit> = parents
+        .iter()
+        .map(|p| repo.find_commit(*p).unwrap())
+        .collect();
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+    repo.commit(None, &sig, &sig, message, &tree, &parent_refs)
+        .unwrap()
+}
+
+#[test]
+fn refresh_commit_graph_lays_out_merge_lanes() {
+    let (dir, repo) = scratch_repo("graph-lanes");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let base = repo.head().unwrap().target().unwrap();
+    let side = commit_with_parents(&repo, "side", &[base]);
+    let main = commit_with_parents(&repo, "main", &[base]);
+    let merge = commit_with_parents(&repo, "merge", &[main, side]);
+    let head = repo.head().unwrap().name().unwrap().to_string();
+    repo.reference(&head, merge, true, "test").unwrap();
+
+    let app = app_for(&dir, "a.txt");
+    assert_eq!(app.commit_graph.len(), 4);
+    assert_eq!(app.commit_graph_rows.len(), 4);
+    assert!(app.commit_graph[0].ends_with(" merge"));
+    let text = |row: &Vec<fastgit::graph::GraphCell>| row.iter().map(|c| c.ch).collect::<String>();
+    assert_eq!(text(&app.commit_graph_rows[0]), "●─╮");
+    assert_eq!(text(&app.commit_graph_rows[3]), "●─╯");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn all_refs_graph_shows_other_branches_with_labels() {
+    let (dir, repo) = scratch_repo("graph-refs");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let base = repo.head().unwrap().target().unwrap();
+    let side = commit_with_parents(&repo, "side", &[base]);
+    repo.reference("refs/heads/feature", side, true, "test")
+        .unwrap();
+    repo.reference("refs/remotes/origin/main", base, true, "test")
+        .unwrap();
+    repo.tag_lightweight("v1", &repo.find_object(base, None).unwrap(), false)
+        .unwrap();
+    // Neither shows up as a row or a label.
+    let stash = commit_with_parents(&repo, "stash", &[base]);
+    repo.reference("refs/stash", stash, true, "test").unwrap();
+    let notes = commit_with_parents(&repo, "notes", &[]);
+    repo.reference("refs/notes/commits", notes, true, "test")
+        .unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    assert_eq!(app.commit_graph.len(), 1);
+
+    app.toggle_graph_all_refs();
+    assert!(app.graph_all_refs);
+    assert_eq!(app.commit_graph.len(), 2);
+    assert_eq!(
+        app.commit_graph_head.as_deref(),
+        Some(base.to_string().as_str())
+    );
+
+    let names = |oid: git2::Oid| -> Vec<(String, RefKind)> {
+        app.commit_graph_labels[&oid.to_string()]
+            .iter()
+            .map(|l| (l.name.clone(), l.kind.clone()))
+            .collect()
+    };
+    let head_branch = repo.head().unwrap().shorthand().unwrap().to_string();
+    assert_eq!(
+        names(base),
+        vec![
+            (format!("HEAD -> {}", head_branch), RefKind::Head),
+            ("origin/main".to_string(), RefKind::Remote),
+            ("tag: v1".to_string(), RefKind::Tag),
+        ]
+    );
+    assert_eq!(names(side), vec![("feature".to_string(), RefKind::Local)]);
+    assert!(!app.commit_graph_labels.contains_key(&stash.to_string()));
+    assert!(!app.commit_graph_labels.contains_key(&notes.to_string()));
+
+    app.toggle_graph_all_refs();
+    assert_eq!(app.commit_graph.len(), 1);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn detached_head_gets_a_bare_head_label() {
+    let (dir, repo) = scratch_repo("graph-detached");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let base = repo.head().unwrap().target().unwrap();
+    repo.set_head_detached(base).unwrap();
+
+    let app = app_for(&dir, "a.txt");
+    let labels = &app.commit_graph_labels[&base.to_string()];
+    assert_eq!(labels[0].name, "HEAD");
+    assert_eq!(labels[0].kind, RefKind::Head);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+fn linear_history(repo: &Repository, count: usize) -> Vec<git2::Oid> {
+    let mut oids = vec![repo.head().unwrap().target().unwrap()];
+    for i in 0..count {
+        let oid = commit_with_parents(repo, &format!("c{}", i), &[*oids.last().unwrap()]);
+        oids.push(oid);
+    }
+    let head = repo.head().unwrap().name().unwrap().to_string();
+    repo.reference(&head, *oids.last().unwrap(), true, "test")
+        .unwrap();
+    oids
+}
+
+#[test]
+fn commit_graph_loads_history_in_pages() {
+    let (dir, repo) = scratch_repo("graph-pages");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    linear_history(&repo, 249);
+
+    let mut app = app_for(&dir, "a.txt");
+    assert_eq!(app.commit_graph.len(), GRAPH_PAGE_SIZE);
+    assert!(app.graph_has_more());
+
+    app.commit_graph_state.select(Some(GRAPH_PAGE_SIZE - 5));
+    app.commit_graph_next();
+    assert_eq!(app.commit_graph.len(), 250);
+    assert!(!app.graph_has_more());
+    assert_eq!(app.commit_graph_state.selected(), Some(GRAPH_PAGE_SIZE - 4));
+    assert!(app.commit_graph[249].ends_with(" base"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn commit_graph_refresh_is_skipped_until_refs_move() {
+    let (dir, repo) = scratch_repo("graph-cache");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+
+    let mut app = app_for(&dir, "a.txt");
+    app.commit_graph[0] = "cached".to_string();
+    app.scan_git();
+    assert_eq!(app.commit_graph[0], "cached");
+
+    linear_history(&repo, 1);
+    app.scan_git();
+    assert_eq!(app.commit_graph.len(), 2);
+    assert!(app.commit_graph[0].ends_with(" c0"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn commit_graph_refresh_keeps_selection_deep_in_history() {
+    let (dir, repo) = scratch_repo("graph-keep");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let oids = linear_history(&repo, 229);
+
+    let mut app = app_for(&dir, "a.txt");
+    for _ in 0..215 {
+        app.commit_graph_next();
+    }
+    let selected = app.commit_graph_oids[215].clone();
+    assert_eq!(selected, oids[oids.len() - 216].to_string());
+
+    let tip = commit_with_parents(&repo, "new tip", &[*oids.last().unwrap()]);
+    let head = repo.head().unwrap().name().unwrap().to_string();
+    repo.reference(&head, tip, true, "test").unwrap();
+    app.scan_git();
+
+    assert_eq!(app.commit_graph_state.selected(), Some(216));
+    assert_eq!(app.commit_graph_oids[216], selected);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn load_commit_diff_fills_commit_detail() {
+    let (dir, repo) = scratch_repo("commit-detail");
+    fs::write(dir.join("a.txt"), "one\ntwo\n").unwrap();
+    commit_all(&repo, "first");
+    fs::write(dir.join("a.txt"), "one\nthree\n").unwrap();
+    fs::write(dir.join("b.txt"), "new\n").unwrap();
+    commit_all(
+        &repo,
+        "Second change\n\nLonger body.\n\nSigned-off-by: A U Thor <a@example.com>\nReviewed-by: R <r@example.com>\n",
+    );
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.refresh_commit_graph();
+    app.load_commit_diff(0);
+
+    let detail = app.commit_detail.clone().unwrap();
+    assert_eq!(detail.oid, head.id().to_string());
+    assert_eq!(detail.parents, vec![head.parent_id(0).unwrap().to_string()]);
+    assert_eq!(detail.author.name, "fastgit");
+    assert_eq!(detail.message, "Second change\n\nLonger body.");
+    assert_eq!(
+        detail.trailers,
+        vec![
+            (
+                "Signed-off-by".to_string(),
+                "A U Thor <a@example.com>".to_string()
+            ),
+            ("Reviewed-by".to_string(), "R <r@example.com>".to_string()),
+        ]
+    );
+    assert_eq!(
+        (detail.files_changed, detail.insertions, detail.deletions),
+        (2, 2, 1)
+    );
+    assert_eq!(detail.signature, SignatureStatus::Unsigned);
+
+    app.selected_file = Some(PathBuf::from("a.txt"));
+    app.load_diff();
+    assert!(app.commit_detail.is_none());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn commit_detail_reports_signature_kind() {
+    let (dir, repo) = scratch_repo("commit-detail-signed");
+    let signer = fake_signer(
+        &dir,
+        "printf -- '-----BEGIN PGP SIGNATURE-----\\nfake\\n-----END PGP SIGNATURE-----\\n'",
+    );
+    {
+        let mut cfg = repo.config().unwrap();
+        cfg.set_bool("commit.gpgsign", true).unwrap();
+        cfg.set_str("gpg.program", signer.to_str().unwrap())
+            .unwrap();
+    }
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.toggle_stage(Path::new("a.txt")).unwrap();
+    app.commit_summary = "signed".to_string();
+    app.commit().unwrap();
+    app.refresh_commit_graph();
+    app.load_commit_diff(0);
+
+    // Verification runs on the signature worker.
+    assert_eq!(
+        app.commit_detail.clone().unwrap().signature,
+        SignatureStatus::Checking {
+            kind: "GPG".to_string()
+        }
+    );
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while matches!(
+        app.commit_detail.as_ref().unwrap().signature,
+        SignatureStatus::Checking { .. }
+    ) {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "verify-commit never answered"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        app.check_signature_result();
+    }
+
+    // The fake signer prints no gpg status lines, so git cannot verify it.
+    let signature = app.commit_detail.clone().unwrap().signature;
+    match &signature {
+        SignatureStatus::Signed { kind, verified, .. } => {
+            assert_eq!(kind, "GPG");
+            assert!(!verified);
+        }
+        other => panic!("commit should be signed, got {:?}", other),
+    }
+
+    // Coming back to the commit reuses the cached result.
+    app.load_commit_diff(0);
+    assert_eq!(app.commit_detail.clone().unwrap().signature, signature);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn load_commit_diff_lists_files_and_steps_through_them() {
+    let (dir, repo) = scratch_repo("commit-files");
+    fs::write(dir.join("keep.txt"), "one\ntwo\n").unwrap();
+    fs::write(dir.join("gone.txt"), "bye\n").unwrap();
+    fs::write(dir.join("old.txt"), numbered_lines(20)).unwrap();
+    commit_all(&repo, "first");
+
+    fs::write(dir.join("keep.txt"), "one\nthree\nfour\n").unwrap();
+    fs::remove_file(dir.join("gone.txt")).unwrap();
+    fs::rename(dir.join("old.txt"), dir.join("new.txt")).unwrap();
+    fs::write(dir.join("added.txt"), "hi\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.remove_path(Path::new("gone.txt")).unwrap();
+    index.remove_path(Path::new("old.txt")).unwrap();
+    index.write().unwrap();
+    commit_all(&repo, "second");
+
+    let mut app = app_for(&dir, "keep.txt");
+    app.refresh_commit_graph();
+    app.load_commit_diff(0);
+
+    let files: Vec<(char, &str, Option<&str>, usize, usize)> = app
+        .commit_files
+        .iter()
+        .map(|f| {
+            (
+                f.status,
+                f.path.as_str(),
+                f.old_path.as_deref(),
+                f.additions,
+                f.deletions,
+            )
+        })
+        .collect();
+    assert_eq!(
+        files,
+        vec![
+            ('A', "added.txt", None, 1, 0),
+            ('D', "gone.txt", None, 0, 1),
+            ('M', "keep.txt", None, 2, 1),
+            ('R', "new.txt", Some("old.txt"), 0, 0),
+        ]
+    );
+
+    assert_eq!(app.commit_file_state.selected(), Some(0));
+    assert!(app.diff_content.iter().any(|l| l.content == "hi"));
+
+    app.commit_file_next();
+    assert_eq!(app.commit_file_state.selected(), Some(1));
+    assert!(app.diff_content.iter().any(|l| l.content == "bye"));
+
+    app.commit_file_previous();
+    app.commit_file_previous();
+    assert_eq!(app.commit_file_state.selected(), Some(3));
+
+    // Reloading the same commit stays on the file being read.
+    app.load_commit_diff(0);
+    assert_eq!(app.commit_file_state.selected(), Some(3));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+fn commit_as(repo: &Repository, dir: &Path, file: &str, who: &str, time: i64, message: &str) {
+    fs::write(dir.join(file), format!("{} {}\n", message, time)).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(file)).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = git2::Signature::new(
+        who,
+        &format!("{}@example.com", who.to_lowercase()),
+        &git2::Time::new(time, 0),
+    )
+    .unwrap();
+    let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+        .unwrap();
+}
+
+#[test]
+fn graph_filter_narrows_history() {
+    let (dir, repo) = scratch_repo("graph-filter");
+    let day = 86_400;
+    let start = 1_790_000_000;
+    commit_as(&repo, &dir, "a.txt", "Alice", start, "feat: start");
+    commit_as(&repo, &dir, "b.txt", "Bob", start + day, "fix: crash in b");
+    commit_as(&repo, &dir, "a.txt", "Bob", start + 2 * day, "feat: more a");
+    commit_as(
+        &repo,
+        &dir,
+        "b.txt",
+        "Alice",
+        start + 3 * day,
+        "fix: tidy b",
+    );
+
+    let mut app = app_for(&dir, "a.txt");
+    app.refresh_commit_graph();
+    assert_eq!(app.commit_graph.len(), 4);
+
+    let summaries = |app: &App| -> Vec<String> {
+        app.commit_graph
+            .iter()
+            .map(|l| l.split_once(' ').unwrap().1.to_string())
+            .collect()
+    };
+
+    app.open_graph_filter_dialog();
+    app.graph_filter_input = "author:alice".to_string();
+    app.apply_graph_filter();
+    assert!(!app.show_graph_filter_dialog);
+    assert_eq!(summaries(&app), vec!["fix: tidy b", "feat: start"]);
+    assert!(app.commit_graph_rows.iter().all(|row| row.len() == 1));
+
+    app.open_graph_filter_dialog();
+    assert_eq!(app.graph_filter_input, "author:alice");
+    app.graph_filter_input = "path:a.txt".to_string();
+    app.apply_graph_filter();
+    assert_eq!(summaries(&app), vec!["feat: more a", "feat: start"]);
+
+    app.graph_filter_input = r#"re:"^fix: CRASH""#.to_string();
+    app.apply_graph_filter();
+    assert!(app.commit_graph.is_empty());
+    app.graph_filter_input = "crash".to_string();
+    app.apply_graph_filter();
+    assert_eq!(summaries(&app), vec!["fix: crash in b"]);
+
+    app.graph_filter_input = "since:2026-09-22 until:2026-09-22".to_string();
+    app.apply_graph_filter();
+    assert_eq!(summaries(&app), vec!["fix: crash in b"]);
+
+    app.open_graph_filter_dialog();
+    app.graph_filter_input = "since:soon".to_string();
+    app.apply_graph_filter();
+    assert!(app.show_graph_filter_dialog);
+    assert!(app.graph_filter_error.is_some());
+
+    app.graph_filter_input.clear();
+    app.apply_graph_filter();
+    assert!(app.graph_filter.is_none());
+    assert_eq!(app.commit_graph.len(), 4);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn graph_filter_scans_a_bounded_number_of_commits_per_page() {
+    let (dir, repo) = scratch_repo("graph-filter-budget");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "needle");
+    let mut tip = repo.head().unwrap().target().unwrap();
+    for i in 0..GRAPH_SCAN_BUDGET + 5 {
+        tip = commit_with_parents(&repo, &format!("filler {}", i), &[tip]);
+    }
+    let head = repo.head().unwrap().name().unwrap().to_string();
+    repo.reference(&head, tip, true, "test").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.graph_filter_input = "needle".to_string();
+    app.apply_graph_filter();
+    assert!(app.commit_graph.is_empty());
+    assert!(app.graph_has_more());
+    assert_eq!(app.graph_scanned, GRAPH_SCAN_BUDGET);
+
+    // Scrolling searches on from where the last page stopped.
+    app.commit_graph_next();
+    assert_eq!(app.commit_graph.len(), 1);
+    assert!(app.commit_graph[0].ends_with("needle"));
+    assert!(!app.graph_has_more());
+    assert_eq!(app.graph_scanned, GRAPH_SCAN_BUDGET + 6);
+
+    // Pickaxe and follow diff every commit, so they stop sooner.
+    app.graph_filter_input = "S:a".to_string();
+    app.apply_graph_filter();
+    assert!(app.commit_graph.is_empty());
+    assert_eq!(app.graph_scanned, GRAPH_DIFF_SCAN_BUDGET);
+    app.graph_filter_input = "follow:a.txt".to_string();
+    app.apply_graph_filter();
+    assert_eq!(app.graph_scanned, GRAPH_DIFF_SCAN_BUDGET);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn pickaxe_finds_commits_that_add_or_remove_a_string() {
+    let (dir, repo) = scratch_repo("pickaxe");
+    fs::write(dir.join("other.txt"), "nothing here\n").unwrap();
+    fs::write(dir.join("a.txt"), "one\n").unwrap();
+    commit_all(&repo, "start");
+    fs::write(dir.join("a.txt"), "one\nneedle\n").unwrap();
+    commit_all(&repo, "add needle");
+    fs::write(dir.join("a.txt"), "one\nneedle!\n").unwrap();
+    commit_all(&repo, "edit needle");
+    fs::write(dir.join("other.txt"), "changed\n").unwrap();
+    fs::write(dir.join("a.txt"), "one\n").unwrap();
+    commit_all(&repo, "drop needle");
+
+    let mut app = app_for(&dir, "a.txt");
+    app.refresh_commit_graph();
+    let summaries = |app: &App| -> Vec<String> {
+        app.commit_graph
+            .iter()
+            .map(|l| l.split_once(' ').unwrap().1.to_string())
+            .collect()
+    };
+
+    // -S ignores the commit that keeps the number of needles.
+    app.graph_filter_input = "S:needle".to_string();
+    app.apply_graph_filter();
+    assert_eq!(summaries(&app), vec!["drop needle", "add needle"]);
+
+    // -G sees the edited line in the patch.
+    app.graph_filter_input = "G:^nee+dle".to_string();
+    app.apply_graph_filter();
+    assert_eq!(
+        summaries(&app),
+        vec!["drop needle", "edit needle", "add needle"]
+    );
+
+    // The matching file is opened, not the first one in the commit.
+    app.load_commit_diff(0);
+    let file = app.commit_file_state.selected().unwrap();
+    assert_eq!(app.commit_files[file].path, "a.txt");
+    let filter = app.graph_filter.clone().unwrap();
+    assert!(
+        app.diff_content
+            .iter()
+            .any(|l| { l.kind == DiffLineKind::Delete && filter.pickaxe_hit(&l.content) })
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn file_history_follows_renames() {
+    let (dir, repo) = scratch_repo("file-history");
+    fs::write(dir.join("old.txt"), numbered_lines(20)).unwrap();
+    fs::write(dir.join("other.txt"), "x\n").unwrap();
+    commit_all(&repo, "add old");
+    fs::write(dir.join("old.txt"), numbered_lines(21)).unwrap();
+    commit_all(&repo, "edit old");
+    fs::rename(dir.join("old.txt"), dir.join("new.txt")).unwrap();
+    let mut index = repo.index().unwrap();
+    index.remove_path(Path::new("old.txt")).unwrap();
+    index.write().unwrap();
+    commit_all(&repo, "rename");
+    fs::write(dir.join("other.txt"), "y\n").unwrap();
+    commit_all(&repo, "other");
+    fs::write(dir.join("new.txt"), numbered_lines(22)).unwrap();
+    commit_all(&repo, "edit new");
+    // The tree lists changed files, so leave one to pick.
+    fs::write(dir.join("new.txt"), numbered_lines(23)).unwrap();
+
+    let mut app = app_for(&dir, "new.txt");
+    let row = app
+        .tree
+        .items
+        .iter()
+        .position(|(p, _, _)| p.ends_with("new.txt"))
+        .unwrap();
+    app.tree.state.select(Some(row));
+    app.open_file_history();
+
+    assert_eq!(app.window_index, 1);
+    assert_eq!(app.graph_filter.as_ref().unwrap().query, "follow:new.txt");
+    let summaries: Vec<String> = app
+        .commit_graph
+        .iter()
+        .map(|l| l.split_once(' ').unwrap().1.to_string())
+        .collect();
+    assert_eq!(summaries, vec!["edit new", "rename", "edit old", "add old"]);
+
+    // Each entry shows only the file, under the name it had back then.
+    assert_eq!(app.commit_files.len(), 1);
+    assert_eq!(app.commit_files[0].path, "new.txt");
+    app.commit_graph_next();
+    assert_eq!(app.commit_files.len(), 1);
+    assert_eq!(app.commit_files[0].status, 'R');
+    assert_eq!(app.commit_files[0].old_path.as_deref(), Some("old.txt"));
+    app.commit_graph_next();
+    assert_eq!(app.commit_files.len(), 1);
+    assert_eq!(app.commit_files[0].path, "old.txt");
+    assert!(app.diff_content.iter().any(|l| l.content == "line 21"));
+
+    // A rebuilt graph traces the renames again from the start.
+    app.graph_refs_key.clear();
+    app.refresh_commit_graph();
+    assert_eq!(app.commit_graph.len(), 4);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn blame_attributes_lines_and_digs_into_parents() {
+    let (dir, repo) = scratch_repo("blame");
+    fs::write(dir.join("a.txt"), "one\ntwo\n").unwrap();
+    commit_all(&repo, "first");
+    let first = repo.head().unwrap().target().unwrap();
+    fs::write(dir.join("a.txt"), "one\nTWO\nthree\n").unwrap();
+    commit_all(&repo, "second");
+    let second = repo.head().unwrap().target().unwrap();
+    fs::write(dir.join("a.txt"), "one\nTWO\nthree\nfour\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.refresh_commit_graph();
+    app.open_blame();
+    assert_eq!(app.window_index, 3);
+    let blame = app.blame.clone().unwrap();
+    assert_eq!(blame.rev, None);
+    let owners: Vec<_> = blame.lines.iter().map(|l| l.oid).collect();
+    assert_eq!(owners, vec![first, second, second]);
+    assert_eq!(blame.lines[1].content, "TWO");
+    assert_eq!(blame.lines[0].author, "fastgit");
+
+    // Looking past "second" shows the line as it was before.
+    app.blame_next();
+    app.blame_parent();
+    let older = app.blame.clone().unwrap();
+    assert_eq!(older.rev, Some(first));
+    assert_eq!(older.lines[1].content, "two");
+    assert_eq!(app.blame_state.selected(), Some(1));
+
+    // The root commit has nothing older to blame.
+    app.blame_parent();
+    assert!(app.blame_error.is_some());
+    app.blame_error = None;
+
+    app.blame_back();
+    assert_eq!(app.blame.as_ref().unwrap().rev, None);
+
+    app.blame_show_commit();
+    assert!(app.blame.is_none());
+    assert_eq!(app.window_index, 1);
+    assert_eq!(app.commit_detail.as_ref().unwrap().oid, second.to_string());
+    let file = app.commit_file_state.selected().unwrap();
+    assert_eq!(app.commit_files[file].path, "a.txt");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn blame_show_commit_keeps_a_filter_that_hides_it() {
+    let (dir, repo) = scratch_repo("blame-filter");
+    fs::write(dir.join("a.txt"), "one\n").unwrap();
+    commit_all(&repo, "first");
+    fs::write(dir.join("b.txt"), "b\n").unwrap();
+    commit_all(&repo, "second");
+    fs::write(dir.join("a.txt"), "one\ntwo\n").unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.graph_filter_input = "second".to_string();
+    app.apply_graph_filter();
+    assert_eq!(app.commit_graph.len(), 1);
+
+    app.open_blame();
+    app.blame_show_commit();
+    assert_eq!(app.graph_filter.as_ref().unwrap().query, "second");
+    assert_eq!(app.commit_graph.len(), 1);
+    assert!(app.blame.is_some());
+    let err = app.blame_error.clone().unwrap();
+    assert!(
+        err.contains("hidden by the history filter \"second\""),
+        "{}",
+        err
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn blame_show_commit_stays_within_the_scan_budget() {
+    let (dir, repo) = scratch_repo("blame-filter-budget");
+    fs::write(dir.join("a.txt"), "one\n").unwrap();
+    commit_all(&repo, "needle");
+    let mut tip = repo.head().unwrap().target().unwrap();
+    for i in 0..GRAPH_DIFF_SCAN_BUDGET * 2 + 5 {
+        tip = commit_with_parents(&repo, &format!("filler {}", i), &[tip]);
+    }
+    let head = repo.head().unwrap().name().unwrap().to_string();
+    repo.reference(&head, tip, true, "test").unwrap();
+
+    // A filter that rejects the commit says so without walking on.
+    let mut app = app_for(&dir, "a.txt");
+    app.graph_filter_input = "path:b.txt".to_string();
+    app.apply_graph_filter();
+    assert_eq!(app.graph_scanned, GRAPH_DIFF_SCAN_BUDGET);
+    app.open_blame();
+    app.blame_show_commit();
+    assert!(
+        app.blame_error
+            .take()
+            .unwrap()
+            .contains("hidden by the history filter")
+    );
+    assert_eq!(app.graph_scanned, GRAPH_DIFF_SCAN_BUDGET);
+    app.close_blame();
+
+    // A followed file is only known to the walk, which stops after a budget.
+    app.graph_filter_input = "follow:a.txt".to_string();
+    app.apply_graph_filter();
+    app.open_blame();
+    app.blame_show_commit();
+    let err = app.blame_error.take().unwrap();
+    assert!(err.contains("searched so far"), "{}", err);
+    assert_eq!(app.graph_scanned, GRAPH_DIFF_SCAN_BUDGET * 2);
+    assert!(app.blame.is_some());
+    assert!(app.graph_filter.is_some());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+/// Commit `files` on top of `parent` as `who`, without touching HEAD, the
+/// index or the work tree.
+fn commit_files_on(
+    repo: &Repository,
+    parent: git2::Oid,
+    files: &[(&str, &str)],
+    who: &str,
+    message: &str,
+) -> git2::Oid {
+    let parent = repo.find_commit(parent).unwrap();
+    let mut builder = repo.treebuilder(Some(&parent.tree().unwrap())).unwrap();
+    for (name, content) in files {
+        let blob = repo.blob(content.as_bytes()).unwrap();
+        builder.insert(name, blob, 0o100644).unwrap();
+    }
+    let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+    let sig = git2::Signature::now(who, &format!("{}@example.com", who)).unwrap();
+    repo.commit(None, &sig, &sig, message, &tree, &[&parent])
+        .unwrap()
+}
+
+fn select_graph_oid(app: &mut App, oid: git2::Oid) {
+    let row = app
+        .commit_graph_oids
+        .iter()
+        .position(|o| *o == oid.to_string())
+        .unwrap();
+    app.commit_graph_state.select(Some(row));
+}
+
+#[test]
+fn cherry_pick_marked_commits_keeps_authors_and_order() {
+    let (dir, repo) = scratch_repo("cherry-pick");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let base = repo.head().unwrap().target().unwrap();
+    let one = commit_files_on(&repo, base, &[("b.txt", "b\n")], "carol", "add b");
+    let two = commit_files_on(&repo, one, &[("c.txt", "c\n")], "dave", "add c");
+    repo.branch("feature", &repo.find_commit(two).unwrap(), false)
+        .unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.toggle_graph_all_refs();
+    // Marked newest first; they still land oldest first.
+    select_graph_oid(&mut app, two);
+    app.toggle_graph_mark();
+    select_graph_oid(&mut app, one);
+    app.toggle_graph_mark();
+    app.cherry_pick_selected();
+
+    assert!(app.operation.is_none(), "{:?}", app.operation_error);
+    assert!(app.graph_marked.is_empty());
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.summary(), Some("add c"));
+    assert_eq!(head.author().name(), Some("dave"));
+    assert_eq!(head.committer().name(), Some("fastgit"));
+    let parent = head.parent(0).unwrap();
+    assert_eq!(parent.summary(), Some("add b"));
+    assert_eq!(parent.author().name(), Some("carol"));
+    assert_eq!(parent.parent_id(0).unwrap(), base);
+    assert!(dir.join("c.txt").exists());
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn cherry_pick_conflict_can_be_continued_or_aborted() {
+    let (dir, repo) = scratch_repo("cherry-pick-conflict");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let base = repo.head().unwrap().target().unwrap();
+    let theirs = commit_files_on(&repo, base, &[("a.txt", "theirs\n")], "carol", "theirs");
+    let after = commit_files_on(&repo, theirs, &[("d.txt", "d\n")], "carol", "after");
+    repo.branch("feature", &repo.find_commit(after).unwrap(), false)
+        .unwrap();
+    fs::write(dir.join("a.txt"), "ours\n").unwrap();
+    commit_all(&repo, "ours");
+    let ours = repo.head().unwrap().target().unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.toggle_graph_all_refs();
+    select_graph_oid(&mut app, after);
+    app.toggle_graph_mark();
+    select_graph_oid(&mut app, theirs);
+    app.toggle_graph_mark();
+    app.cherry_pick_selected();
+
+    let op = app.operation.clone().unwrap();
+    assert_eq!(op.current, Some(theirs));
+    assert_eq!(op.conflicts, vec!["a.txt".to_string()]);
+    assert_eq!(op.queue.len(), 1);
+    assert_eq!(repo.state(), git2::RepositoryState::CherryPick);
+
+    // Abort puts everything back.
+    app.abort_operation();
+    assert!(app.operation.is_none());
+    assert_eq!(repo.head().unwrap().target(), Some(ours));
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "ours\n");
+
+    // Try again, resolve the conflict and continue.
+    app.toggle_graph_mark();
+    select_graph_oid(&mut app, after);
+    app.toggle_graph_mark();
+    app.cherry_pick_selected();
+    assert!(app.operation.is_some());
+
+    app.continue_operation();
+    assert!(app.operation_error.take().is_some());
+    assert!(app.operation.is_some());
+
+    fs::write(dir.join("a.txt"), "resolved\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("a.txt")).unwrap();
+    index.write().unwrap();
+    app.continue_operation();
+
+    assert!(app.operation.is_none(), "{:?}", app.operation_error);
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.summary(), Some("after"));
+    let picked = head.parent(0).unwrap();
+    assert_eq!(picked.summary(), Some("theirs"));
+    assert_eq!(picked.author().name(), Some("carol"));
+    assert_eq!(picked.parent_id(0).unwrap(), ours);
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn cherry_pick_abort_keeps_unrelated_changes() {
+    let (dir, repo) = scratch_repo("cherry-pick-dirty");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    fs::write(dir.join("notes.txt"), "notes\n").unwrap();
+    commit_all(&repo, "base");
+    let base = repo.head().unwrap().target().unwrap();
+    let added = commit_files_on(&repo, base, &[("new.txt", "new\n")], "carol", "add new");
+    let theirs = commit_files_on(&repo, added, &[("a.txt", "theirs\n")], "carol", "theirs");
+    repo.branch("feature", &repo.find_commit(theirs).unwrap(), false)
+        .unwrap();
+    fs::write(dir.join("a.txt"), "ours\n").unwrap();
+    commit_all(&repo, "ours");
+    let ours = repo.head().unwrap().target().unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.toggle_graph_all_refs();
+
+    // A dirty tree is refused up front.
+    fs::write(dir.join("notes.txt"), "edited\n").unwrap();
+    select_graph_oid(&mut app, theirs);
+    app.cherry_pick_selected();
+    assert!(app.operation.is_none());
+    assert!(app.operation_error.take().is_some());
+    fs::write(dir.join("notes.txt"), "notes\n").unwrap();
+
+    select_graph_oid(&mut app, theirs);
+    app.toggle_graph_mark();
+    select_graph_oid(&mut app, added);
+    app.toggle_graph_mark();
+    app.cherry_pick_selected();
+    assert_eq!(app.operation.as_ref().unwrap().current, Some(theirs));
+    assert!(dir.join("new.txt").exists());
+
+    // Edited while the conflict is being looked at.
+    fs::write(dir.join("notes.txt"), "edited\n").unwrap();
+    app.abort_operation();
+
+    assert!(app.operation.is_none(), "{:?}", app.operation_error);
+    assert_eq!(repo.head().unwrap().target(), Some(ours));
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "ours\n");
+    assert!(!dir.join("new.txt").exists());
+    assert_eq!(
+        fs::read_to_string(dir.join("notes.txt")).unwrap(),
+        "edited\n"
+    );
+    let index = repo.index().unwrap();
+    assert!(!index.has_conflicts());
+    assert!(index.get_path(Path::new("new.txt"), 0).is_none());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn cherry_pick_skip_keeps_unrelated_changes() {
+    let (dir, repo) = scratch_repo("cherry-pick-skip");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    fs::write(dir.join("notes.txt"), "notes\n").unwrap();
+    commit_all(&repo, "base");
+    let base = repo.head().unwrap().target().unwrap();
+    let theirs = commit_files_on(&repo, base, &[("a.txt", "theirs\n")], "carol", "theirs");
+    let after = commit_files_on(&repo, theirs, &[("d.txt", "d\n")], "carol", "after");
+    repo.branch("feature", &repo.find_commit(after).unwrap(), false)
+        .unwrap();
+    fs::write(dir.join("a.txt"), "ours\n").unwrap();
+    commit_all(&repo, "ours");
+    let ours = repo.head().unwrap().target().unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.toggle_graph_all_refs();
+    select_graph_oid(&mut app, after);
+    app.toggle_graph_mark();
+    select_graph_oid(&mut app, theirs);
+    app.toggle_graph_mark();
+    app.cherry_pick_selected();
+    assert_eq!(app.operation.as_ref().unwrap().current, Some(theirs));
+
+    fs::write(dir.join("notes.txt"), "edited\n").unwrap();
+    app.skip_operation();
+
+    assert!(app.operation.is_none(), "{:?}", app.operation_error);
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.summary(), Some("after"));
+    assert_eq!(head.parent_id(0).unwrap(), ours);
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "ours\n");
+    assert_eq!(
+        fs::read_to_string(dir.join("notes.txt")).unwrap(),
+        "edited\n"
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn operation_started_elsewhere_aborts_without_losing_changes() {
+    let (dir, repo) = scratch_repo("cherry-pick-external-abort");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    fs::write(dir.join("notes.txt"), "notes\n").unwrap();
+    commit_all(&repo, "base");
+    let base = repo.head().unwrap().target().unwrap();
+    let theirs = commit_files_on(&repo, base, &[("a.txt", "theirs\n")], "carol", "theirs");
+    fs::write(dir.join("a.txt"), "ours\n").unwrap();
+    commit_all(&repo, "ours");
+    fs::write(dir.join("notes.txt"), "edited\n").unwrap();
+    repo.cherrypick(&repo.find_commit(theirs).unwrap(), None)
+        .unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    assert!(app.operation.is_some());
+    app.abort_operation();
+
+    assert!(app.operation.is_none(), "{:?}", app.operation_error);
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "ours\n");
+    assert_eq!(
+        fs::read_to_string(dir.join("notes.txt")).unwrap(),
+        "edited\n"
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn operation_started_elsewhere_is_picked_up() {
+    let (dir, repo) = scratch_repo("cherry-pick-external");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let base = repo.head().unwrap().target().unwrap();
+    let theirs = commit_files_on(&repo, base, &[("a.txt", "theirs\n")], "carol", "theirs");
+    fs::write(dir.join("a.txt"), "ours\n").unwrap();
+    commit_all(&repo, "ours");
+    repo.cherrypick(&repo.find_commit(theirs).unwrap(), None)
+        .unwrap();
+
+    let app = app_for(&dir, "a.txt");
+    let op = app.operation.clone().unwrap();
+    assert_eq!(op.current, Some(theirs));
+    assert_eq!(op.conflicts, vec!["a.txt".to_string()]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn rebase_started_by_git_is_shown_but_left_to_git() {
+    let (dir, repo) = scratch_repo("rebase-external");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let head = repo.head().unwrap().target().unwrap().to_string();
+    // What `git rebase` leaves behind: a todo list, no cmt.N files.
+    let state = repo.path().join("rebase-merge");
+    fs::create_dir_all(&state).unwrap();
+    fs::write(
+        state.join("git-rebase-todo"),
+        format!("pick {} base\n", head),
+    )
+    .unwrap();
+    fs::write(state.join("head-name"), "refs/heads/main\n").unwrap();
+    fs::write(state.join("onto"), format!("{}\n", head)).unwrap();
+    fs::write(state.join("orig-head"), format!("{}\n", head)).unwrap();
+    fs::write(state.join("msgnum"), "1\n").unwrap();
+    fs::write(state.join("end"), "1\n").unwrap();
+    assert_eq!(repo.state(), git2::RepositoryState::RebaseMerge);
+
+    let mut app = app_for(&dir, "a.txt");
+    let op = app.operation.clone().unwrap();
+    assert_eq!(op.kind, OperationKind::Rebase);
+    assert!(!op.resumable);
+    assert!(app.rebase_stop.is_none());
+
+    app.continue_operation();
+    assert!(app.operation_error.take().unwrap().contains("git rebase"));
+    app.skip_operation();
+    assert!(app.operation_error.take().is_some());
+    app.abort_operation();
+    assert!(app.operation_error.take().is_some());
+    assert!(app.operation.is_some());
+    assert!(state.join("git-rebase-todo").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn revert_prefills_the_commit_dialog_and_finishes_on_commit() {
+    let (dir, repo) = scratch_repo("revert");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    fs::write(dir.join("b.txt"), "b\n").unwrap();
+    commit_all(&repo, "add b");
+    let added = repo.head().unwrap().target().unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    select_graph_oid(&mut app, added);
+    app.revert_selected();
+
+    assert!(app.show_commit_dialog, "{:?}", app.operation_error);
+    assert_eq!(app.commit_summary, "Revert \"add b\"");
+    assert!(app.commit_description.contains(&added.to_string()));
+    assert_eq!(app.operation.as_ref().unwrap().current, Some(added));
+    assert_eq!(repo.state(), git2::RepositoryState::Revert);
+    assert!(!dir.join("b.txt").exists());
+
+    app.submit_commit();
+    assert!(app.operation.is_none());
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.summary(), Some("Revert \"add b\""));
+    assert_eq!(head.parent_id(0).unwrap(), added);
+    assert!(head.tree().unwrap().get_name("b.txt").is_none());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn revert_refuses_a_dirty_tree_and_aborts_keeping_other_changes() {
+    let (dir, repo) = scratch_repo("revert-dirty");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    fs::write(dir.join("notes.txt"), "notes\n").unwrap();
+    commit_all(&repo, "base");
+    fs::write(dir.join("a.txt"), "changed\n").unwrap();
+    commit_all(&repo, "change a");
+    let changed = repo.head().unwrap().target().unwrap();
+    fs::write(dir.join("a.txt"), "changed again\n").unwrap();
+    commit_all(&repo, "change a again");
+    let head = repo.head().unwrap().target().unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    fs::write(dir.join("notes.txt"), "edited\n").unwrap();
+    select_graph_oid(&mut app, changed);
+    app.revert_selected();
+    assert!(app.operation.is_none());
+    assert!(app.operation_error.take().is_some());
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    fs::write(dir.join("notes.txt"), "notes\n").unwrap();
+
+    app.revert_selected();
+    assert_eq!(app.operation.as_ref().unwrap().conflicts.len(), 1);
+    fs::write(dir.join("notes.txt"), "edited\n").unwrap();
+    app.abort_operation();
+
+    assert!(app.operation.is_none(), "{:?}", app.operation_error);
+    assert_eq!(repo.head().unwrap().target(), Some(head));
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    assert_eq!(
+        fs::read_to_string(dir.join("a.txt")).unwrap(),
+        "changed again\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.join("notes.txt")).unwrap(),
+        "edited\n"
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn revert_of_a_merge_asks_for_the_mainline() {
+    let (dir, repo) = scratch_repo("revert-merge");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let base = repo.head().unwrap().target().unwrap();
+    let side = commit_files_on(&repo, base, &[("side.txt", "side\n")], "carol", "side");
+    let main = commit_files_on(&repo, base, &[("main.txt", "main\n")], "dave", "main");
+    let sig = repo.signature().unwrap();
+    let mut merged = repo
+        .merge_commits(
+            &repo.find_commit(main).unwrap(),
+            &repo.find_commit(side).unwrap(),
+            None,
+        )
+        .unwrap();
+    let tree = repo
+        .find_tree(merged.write_tree_to(&repo).unwrap())
+        .unwrap();
+    let merge = repo
+        .commit(
+            None,
+            &sig,
+            &sig,
+            "merge side",
+            &tree,
+            &[
+                &repo.find_commit(main).unwrap(),
+                &repo.find_commit(side).unwrap(),
+            ],
+        )
+        .unwrap();
+    let branch = repo.head().unwrap().name().unwrap().to_string();
+    repo.reference(&branch, merge, true, "merge").unwrap();
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    select_graph_oid(&mut app, merge);
+    app.revert_selected();
+
+    let (oid, parents) = app.revert_mainline.clone().unwrap();
+    assert_eq!(oid, merge);
+    assert_eq!(parents.len(), 2);
+    assert!(parents[0].ends_with("main"));
+    assert!(!app.show_commit_dialog);
+
+    // Out-of-range choices keep the prompt open.
+    app.choose_revert_mainline(3);
+    assert!(app.revert_mainline.is_some());
+    app.choose_revert_mainline(1);
+
+    assert!(app.revert_mainline.is_none());
+    assert!(app.show_commit_dialog, "{:?}", app.operation_error);
+    assert_eq!(app.commit_summary, "Revert \"merge side\"");
+    assert!(!dir.join("side.txt").exists());
+    assert!(dir.join("main.txt").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn revert_conflict_stops_and_can_be_aborted() {
+    let (dir, repo) = scratch_repo("revert-conflict");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    fs::write(dir.join("a.txt"), "b\n").unwrap();
+    commit_all(&repo, "to b");
+    let target = repo.head().unwrap().target().unwrap();
+    fs::write(dir.join("a.txt"), "c\n").unwrap();
+    commit_all(&repo, "to c");
+    let head = repo.head().unwrap().target().unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    select_graph_oid(&mut app, target);
+    app.revert_selected();
+
+    assert!(!app.show_commit_dialog);
+    let op = app.operation.clone().unwrap();
+    assert_eq!(op.conflicts, vec!["a.txt".to_string()]);
+    assert_eq!(op.kind.name(), "revert");
+
+    // Another revert has to wait.
+    app.revert_selected();
+    assert!(app.operation_error.take().is_some());
+
+    app.continue_operation();
+    assert!(app.operation_error.take().is_some());
+
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("a.txt")).unwrap();
+    index.write().unwrap();
+    app.continue_operation();
+    assert!(app.show_commit_dialog);
+    assert_eq!(app.commit_summary, "Revert \"to b\"");
+    app.close_commit_dialog();
+
+    app.abort_operation();
+    assert!(app.operation.is_none());
+    assert_eq!(repo.head().unwrap().target(), Some(head));
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "c\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn soft_and_mixed_reset_keep_the_work_and_can_be_undone() {
+    let (dir, repo) = scratch_repo("reset-soft");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let base = repo.head().unwrap().target().unwrap();
+    fs::write(dir.join("a.txt"), "b\n").unwrap();
+    commit_all(&repo, "to b");
+    let tip = repo.head().unwrap().target().unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    select_graph_oid(&mut app, base);
+    app.open_reset_dialog();
+    assert_eq!(app.reset_target, Some(base));
+    app.choose_reset(git2::ResetType::Soft);
+
+    assert!(app.reset_target.is_none());
+    let done = app.reset_done.clone().unwrap();
+    assert_eq!(done.previous, tip);
+    assert_eq!(done.previous_summary, "to b");
+    assert_eq!(repo.head().unwrap().target(), Some(base));
+    assert!(
+        repo.status_file(Path::new("a.txt"))
+            .unwrap()
+            .contains(git2::Status::INDEX_MODIFIED)
+    );
+
+    app.undo_reset();
+    assert_eq!(repo.head().unwrap().target(), Some(tip));
+    assert_eq!(app.reset_done.as_ref().unwrap().previous, base);
+    app.reset_done = None;
+
+    select_graph_oid(&mut app, base);
+    app.open_reset_dialog();
+    app.choose_reset(git2::ResetType::Mixed);
+    assert_eq!(repo.head().unwrap().target(), Some(base));
+    assert_eq!(
+        repo.status_file(Path::new("a.txt")).unwrap(),
+        git2::Status::WT_MODIFIED
+    );
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "b\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn hard_reset_lists_what_will_be_lost_before_resetting() {
+    let (dir, repo) = scratch_repo("reset-hard");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let base = repo.head().unwrap().target().unwrap();
+    fs::write(dir.join("b.txt"), "b\n").unwrap();
+    commit_all(&repo, "add b");
+    fs::write(dir.join("a.txt"), "edited\n").unwrap();
+    fs::write(dir.join("new.txt"), "staged\n").unwrap();
+    fs::write(dir.join("untracked.txt"), "kept\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("new.txt")).unwrap();
+    index.write().unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    select_graph_oid(&mut app, base);
+    app.open_reset_dialog();
+    app.choose_reset(git2::ResetType::Hard);
+
+    let lost = app.reset_hard_confirm.clone().unwrap();
+    assert_eq!(lost, vec!["M a.txt".to_string(), "A new.txt".to_string()]);
+    assert!(app.reset_done.is_none());
+
+    // Cancelling leaves everything alone.
+    app.cancel_reset();
+    assert!(app.reset_target.is_none());
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "edited\n");
+
+    app.open_reset_dialog();
+    app.choose_reset(git2::ResetType::Hard);
+    app.confirm_hard_reset();
+
+    assert!(app.reset_error.is_none(), "{:?}", app.reset_error);
+    assert!(app.reset_hard_confirm.is_none());
+    assert_eq!(repo.head().unwrap().target(), Some(base));
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a\n");
+    assert!(!dir.join("b.txt").exists());
+    assert!(!dir.join("new.txt").exists());
+    assert!(dir.join("untracked.txt").exists());
+    assert_eq!(app.reset_done.as_ref().unwrap().previous_summary, "add b");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn reset_is_refused_during_a_stopped_cherry_pick() {
+    let (dir, repo) = scratch_repo("reset-cherry-pick");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let base = repo.head().unwrap().target().unwrap();
+    let theirs = commit_files_on(&repo, base, &[("a.txt", "theirs\n")], "carol", "theirs");
+    repo.branch("feature", &repo.find_commit(theirs).unwrap(), false)
+        .unwrap();
+    fs::write(dir.join("a.txt"), "ours\n").unwrap();
+    commit_all(&repo, "ours");
+    let ours = repo.head().unwrap().target().unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    app.toggle_graph_all_refs();
+    select_graph_oid(&mut app, theirs);
+    app.toggle_graph_mark();
+    app.cherry_pick_selected();
+    assert!(app.operation.is_some());
+
+    select_graph_oid(&mut app, base);
+    app.open_reset_dialog();
+    assert!(app.reset_target.is_none());
+    assert!(
+        app.reset_error
+            .take()
+            .unwrap()
+            .contains("continue or abort it first")
+    );
+
+    // A reset already being chosen is refused too.
+    app.reset_target = Some(base);
+    app.choose_reset(git2::ResetType::Mixed);
+    assert!(app.reset_error.is_some());
+    assert!(app.reset_done.is_none());
+    assert_eq!(repo.head().unwrap().target(), Some(ours));
+    assert_eq!(repo.state(), git2::RepositoryState::CherryPick);
+    assert!(app.operation.is_some());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+/// Four commits on top of "base", each adding its own file, by "carol".
+fn rebase_history(repo: &Repository) -> Vec<git2::Oid> {
+    let mut tip = repo.head().unwrap().target().unwrap();
+    let mut oids = Vec::new();
+    for name in ["one", "two", "three", "four"] {
+        tip = commit_files_on(
+            repo,
+            tip,
+            &[(&format!("{}.txt", name), &format!("{}\n", name))],
+            "carol",
+            name,
+        );
+        oids.push(tip);
+    }
+    let branch = repo.head().unwrap().name().unwrap().to_string();
+    repo.reference(&branch, tip, true, "test").unwrap();
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .unwrap();
+    oids
+}
+
+fn summaries_from_head(repo: &Repository) -> Vec<String> {
+    let mut walk = repo.revwalk().unwrap();
+    walk.push_head().unwrap();
+    walk.map(|oid| {
+        repo.find_commit(oid.unwrap())
+            .unwrap()
+            .summary()
+            .unwrap()
+            .to_string()
+    })
+    .collect()
+}
+
+#[test]
+fn rebase_plan_reorders_drops_and_fixes_up() {
+    let (dir, repo) = scratch_repo("rebase-plan");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let oids = rebase_history(&repo);
+
+    let mut app = app_for(&dir, "a.txt");
+    select_graph_oid(&mut app, oids[0]);
+    app.open_rebase_planner();
+    let plan = app.rebase_plan.clone().unwrap();
+    assert_eq!(plan.steps.len(), 4);
+    assert_eq!(plan.steps[0].summary, "one");
+
+    // one, three (fixed up into one), four, with two dropped: move four
+    // above three and fix three up into one.
+    app.rebase_plan_state.select(Some(1));
+    app.rebase_plan_set(RebaseAction::Drop);
+    app.rebase_plan_state.select(Some(3));
+    app.rebase_plan_move(true);
+    assert_eq!(app.rebase_plan_state.selected(), Some(2));
+    app.rebase_plan_state.select(Some(3));
+    app.rebase_plan_set(RebaseAction::Fixup);
+    app.rebase_plan_move(true);
+    app.rebase_plan_move(true);
+    app.start_rebase();
+
+    assert!(app.operation.is_none(), "{:?}", app.operation_error);
+    assert!(app.rebase_plan.is_none());
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    assert_eq!(summaries_from_head(&repo), vec!["four", "one", "base"]);
+    let head = repo.head().unwrap();
+    assert!(head.is_branch());
+    let one = head.peel_to_commit().unwrap().parent(0).unwrap();
+    assert_eq!(one.author().name(), Some("carol"));
+    assert!(one.tree().unwrap().get_name("three.txt").is_some());
+    assert!(!dir.join("two.txt").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn rebase_stops_for_reword_squash_and_edit() {
+    let (dir, repo) = scratch_repo("rebase-stops");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let oids = rebase_history(&repo);
+
+    let mut app = app_for(&dir, "a.txt");
+    select_graph_oid(&mut app, oids[0]);
+    app.open_rebase_planner();
+    app.rebase_plan_set(RebaseAction::Reword);
+    app.rebase_plan_state.select(Some(1));
+    app.rebase_plan_set(RebaseAction::Squash);
+    app.rebase_plan_state.select(Some(2));
+    app.rebase_plan_set(RebaseAction::Edit);
+    app.start_rebase();
+
+    // Reword: the dialog holds the old message; the author is kept.
+    assert_eq!(app.rebase_stop, Some(RebaseStop::Message));
+    assert!(app.show_commit_dialog, "{:?}", app.operation_error);
+    assert_eq!(app.commit_summary, "one");
+    assert_eq!(app.operation.as_ref().unwrap().queue.len(), 3);
+    app.commit_summary = "first".to_string();
+    app.submit_commit();
+
+    // Squash: the combined message, amending the reworded commit.
+    assert_eq!(app.rebase_stop, Some(RebaseStop::Message));
+    assert_eq!(app.commit_summary, "first");
+    assert_eq!(app.commit_description, "two");
+    assert!(app.commit_amend);
+    app.submit_commit();
+
+    // Edit: committed, then stopped.
+    assert_eq!(app.rebase_stop, Some(RebaseStop::Edit));
+    assert!(!app.show_commit_dialog);
+    assert_eq!(
+        repo.head().unwrap().peel_to_commit().unwrap().summary(),
+        Some("three")
+    );
+    app.continue_operation();
+
+    assert!(app.operation.is_none(), "{:?}", app.operation_error);
+    assert_eq!(
+        summaries_from_head(&repo),
+        vec!["four", "three", "first", "base"]
+    );
+    let first = repo
+        .revparse_single("HEAD~2")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    assert_eq!(first.message(), Some("first\n\ntwo"));
+    assert_eq!(first.author().name(), Some("carol"));
+    assert!(first.tree().unwrap().get_name("two.txt").is_some());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn amend_cannot_be_toggled_at_a_rebase_message_stop() {
+    let (dir, repo) = scratch_repo("rebase-stop-amend");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let oids = rebase_history(&repo);
+
+    let mut app = app_for(&dir, "a.txt");
+    select_graph_oid(&mut app, oids[0]);
+    app.open_rebase_planner();
+    app.rebase_plan_set(RebaseAction::Reword);
+    app.rebase_plan_state.select(Some(1));
+    app.rebase_plan_set(RebaseAction::Squash);
+    app.start_rebase();
+
+    // Reword: amending would fold the commit into the one before it.
+    assert_eq!(app.rebase_stop, Some(RebaseStop::Message));
+    assert!(!app.commit_amend_allowed());
+    app.toggle_commit_amend();
+    assert!(!app.commit_amend);
+    assert_eq!(app.commit_summary, "one");
+    app.submit_commit();
+
+    // Squash: turning amend off would keep the commit separate.
+    assert_eq!(app.rebase_stop, Some(RebaseStop::Message));
+    app.toggle_commit_amend();
+    assert!(app.commit_amend);
+    assert_eq!(app.commit_summary, "one");
+    assert_eq!(app.commit_description, "two");
+    app.submit_commit();
+
+    assert!(app.operation.is_none(), "{:?}", app.operation_error);
+    assert_eq!(
+        summaries_from_head(&repo),
+        vec!["four", "three", "one", "base"]
+    );
+    let one = repo
+        .revparse_single("HEAD~2")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    assert_eq!(one.message(), Some("one\n\ntwo"));
+    assert!(one.tree().unwrap().get_name("two.txt").is_some());
+    assert!(app.commit_amend_allowed());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn rebase_plan_survives_a_restart() {
+    let (dir, repo) = scratch_repo("rebase-restart");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let oids = rebase_history(&repo);
+
+    let mut app = app_for(&dir, "a.txt");
+    select_graph_oid(&mut app, oids[0]);
+    app.open_rebase_planner();
+    app.rebase_plan_set(RebaseAction::Reword);
+    app.rebase_plan_state.select(Some(1));
+    app.rebase_plan_set(RebaseAction::Squash);
+    app.rebase_plan_state.select(Some(2));
+    app.rebase_plan_set(RebaseAction::Edit);
+    app.start_rebase();
+    assert_eq!(app.rebase_stop, Some(RebaseStop::Message));
+    drop(app);
+
+    // The reword is asked for again.
+    let mut app = app_for(&dir, "a.txt");
+    assert_eq!(app.rebase_stop, Some(RebaseStop::Message));
+    assert_eq!(app.rebase_actions[&oids[1]], RebaseAction::Squash);
+    app.continue_operation();
+    assert!(app.show_commit_dialog, "{:?}", app.operation_error);
+    assert_eq!(app.commit_summary, "one");
+    app.commit_summary = "first".to_string();
+    app.submit_commit();
+    assert!(app.commit_amend);
+    app.submit_commit();
+    assert_eq!(app.rebase_stop, Some(RebaseStop::Edit));
+    drop(app);
+
+    // The edit is not committed a second time.
+    let mut app = app_for(&dir, "a.txt");
+    assert_eq!(app.rebase_stop, Some(RebaseStop::Edit));
+    app.continue_operation();
+    assert!(app.operation.is_none(), "{:?}", app.operation_error);
+    assert_eq!(
+        summaries_from_head(&repo),
+        vec!["four", "three", "first", "base"]
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn rebase_conflict_can_be_skipped_or_aborted() {
+    let (dir, repo) = scratch_repo("rebase-conflict");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    fs::write(dir.join("a.txt"), "b\n").unwrap();
+    commit_all(&repo, "to b");
+    let to_b = repo.head().unwrap().target().unwrap();
+    fs::write(dir.join("a.txt"), "c\n").unwrap();
+    commit_all(&repo, "to c");
+    fs::write(dir.join("d.txt"), "d\n").unwrap();
+    commit_all(&repo, "add d");
+    let tip = repo.head().unwrap().target().unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    select_graph_oid(&mut app, to_b);
+    app.open_rebase_planner();
+    // Dropping "to b" makes "to c" conflict.
+    app.rebase_plan_set(RebaseAction::Drop);
+    app.start_rebase();
+
+    let op = app.operation.clone().unwrap();
+    assert_eq!(op.kind.name(), "rebase");
+    assert_eq!(op.conflicts, vec!["a.txt".to_string()]);
+    assert_eq!(app.rebase_stop, Some(RebaseStop::Conflicts));
+
+    app.abort_operation();
+    assert!(app.operation.is_none());
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    assert!(repo.head().unwrap().is_branch());
+    assert_eq!(repo.head().unwrap().target(), Some(tip));
+
+    select_graph_oid(&mut app, to_b);
+    app.open_rebase_planner();
+    app.rebase_plan_set(RebaseAction::Drop);
+    app.start_rebase();
+    assert!(app.operation.is_some());
+    app.skip_operation();
+
+    assert!(app.operation.is_none(), "{:?}", app.operation_error);
+    assert_eq!(summaries_from_head(&repo), vec!["add d", "base"]);
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a\n");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn fixup_commits_from_the_dialog_are_autosquashed() {
+    let (dir, repo) = scratch_repo("autosquash");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    fs::write(dir.join("parser.txt"), "parser\n").unwrap();
+    commit_all(&repo, "add parser");
+    let parser = repo.head().unwrap().target().unwrap();
+    fs::write(dir.join("lexer.txt"), "lexer\n").unwrap();
+    commit_all(&repo, "add lexer");
+    let lexer = repo.head().unwrap().target().unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    fs::write(dir.join("parser.txt"), "parser, fixed\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("parser.txt")).unwrap();
+    index.write().unwrap();
+    select_graph_oid(&mut app, parser);
+    app.open_commit_dialog();
+    app.toggle_commit_fixup();
+    assert_eq!(app.commit_fixup, Some((RebaseAction::Fixup, parser)));
+    assert_eq!(app.commit_summary, "fixup! add parser");
+    app.submit_commit();
+
+    fs::write(dir.join("lexer.txt"), "lexer, fixed\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("lexer.txt")).unwrap();
+    index.write().unwrap();
+    select_graph_oid(&mut app, lexer);
+    app.open_commit_dialog();
+    app.commit_description = "handle tabs".to_string();
+    app.toggle_commit_fixup();
+    app.toggle_commit_fixup();
+    assert_eq!(app.commit_summary, "squash! add lexer");
+    assert_eq!(app.commit_description, "handle tabs");
+    app.submit_commit();
+    assert_eq!(
+        summaries_from_head(&repo),
+        vec![
+            "squash! add lexer",
+            "fixup! add parser",
+            "add lexer",
+            "add parser",
+            "base"
+        ]
+    );
+
+    app.autosquash();
+    // The squash stops for its combined message.
+    assert_eq!(app.rebase_stop, Some(RebaseStop::Message));
+    assert_eq!(app.commit_summary, "add lexer");
+    assert_eq!(app.commit_description, "handle tabs");
+    app.submit_commit();
+
+    assert!(app.operation.is_none(), "{:?}", app.operation_error);
+    assert_eq!(
+        summaries_from_head(&repo),
+        vec!["add lexer", "add parser", "base"]
+    );
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    let blob = |commit: &git2::Commit, name: &str| {
+        let entry = commit.tree().unwrap().get_name(name).unwrap().id();
+        String::from_utf8(repo.find_blob(entry).unwrap().content().to_vec()).unwrap()
+    };
+    assert_eq!(blob(&head, "lexer.txt"), "lexer, fixed\n");
+    assert_eq!(
+        blob(&head.parent(0).unwrap(), "parser.txt"),
+        "parser, fixed\n"
+    );
+
+    app.autosquash();
+    assert!(app.operation_error.take().is_some());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn autosquash_finds_a_target_by_the_start_of_its_summary() {
+    let (dir, repo) = scratch_repo("autosquash-prefix");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    fs::write(dir.join("parser.txt"), "parser\n").unwrap();
+    commit_all(&repo, "add parser for config files");
+    fs::write(dir.join("lexer.txt"), "lexer\n").unwrap();
+    commit_all(&repo, "add lexer");
+    fs::write(dir.join("parser.txt"), "parser, fixed\n").unwrap();
+    commit_all(&repo, "fixup! add parser");
+
+    let mut app = app_for(&dir, "a.txt");
+    app.autosquash();
+
+    assert!(app.operation.is_none(), "{:?}", app.operation_error);
+    assert_eq!(
+        summaries_from_head(&repo),
+        vec!["add lexer", "add parser for config files", "base"]
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn reword_rewrites_an_older_commit_and_replays_the_rest() {
+    let (dir, repo) = scratch_repo("reword");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let oids = rebase_history(&repo);
+
+    let mut app = app_for(&dir, "a.txt");
+    select_graph_oid(&mut app, oids[1]);
+    app.open_reword_dialog();
+    assert!(app.reword_confirm.is_none());
+    assert!(app.show_commit_dialog, "{:?}", app.operation_error);
+    assert_eq!(app.commit_reword, Some(oids[1]));
+    assert_eq!(app.commit_summary, "two");
+    // Amend and fixup do not apply to a reword.
+    app.toggle_commit_amend();
+    app.toggle_commit_fixup();
+    assert!(!app.commit_amend);
+    assert!(app.commit_fixup.is_none());
+
+    app.commit_summary = "second".to_string();
+    app.submit_commit();
+
+    assert!(app.operation.is_none(), "{:?}", app.operation_error);
+    assert!(!app.show_commit_dialog);
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    assert!(repo.head().unwrap().is_branch());
+    assert_eq!(
+        summaries_from_head(&repo),
+        vec!["four", "three", "second", "one", "base"]
+    );
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    let original = repo.find_commit(oids[3]).unwrap();
+    assert_eq!(head.tree_id(), original.tree_id());
+    assert_eq!(head.author().name(), Some("carol"));
+    let second = head.parent(0).unwrap().parent(0).unwrap();
+    assert_eq!(second.author().name(), Some("carol"));
+    assert_eq!(second.parent_id(0).unwrap(), oids[0]);
+
+    // HEAD itself is reworded in place.
+    let tip = repo.head().unwrap().target().unwrap();
+    select_graph_oid(&mut app, tip);
+    app.open_reword_dialog();
+    app.commit_summary = "fourth".to_string();
+    app.submit_commit();
+    assert_eq!(summaries_from_head(&repo)[..2], ["fourth", "three"]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn reword_failures_are_told_apart_from_signing_failures() {
+    let (dir, repo) = scratch_repo("reword-errors");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let oids = rebase_history(&repo);
+    let branch = repo.head().unwrap().name().unwrap().to_string();
+
+    // The branch moved off the commit while its message was being edited.
+    let mut app = app_for(&dir, "a.txt");
+    select_graph_oid(&mut app, oids[1]);
+    app.open_reword_dialog();
+    repo.reference(&branch, oids[0], true, "test").unwrap();
+    app.submit_commit();
+    assert!(app.commit_sign_error.is_none());
+    let (_, message) = app.commit_error.take().unwrap();
+    assert!(message.contains("not an ancestor of HEAD"), "{}", message);
+    app.dismiss_commit_error();
+    app.close_commit_dialog();
+    repo.reference(&branch, oids[3], true, "test").unwrap();
+
+    let signer = fake_signer(&dir, "echo 'no secret key' >&2\nexit 2");
+    {
+        let mut cfg = repo.config().unwrap();
+        cfg.set_bool("commit.gpgsign", true).unwrap();
+        cfg.set_str("gpg.program", signer.to_str().unwrap())
+            .unwrap();
+    }
+    select_graph_oid(&mut app, oids[1]);
+    app.open_reword_dialog();
+    app.submit_commit();
+    assert!(app.commit_error.is_none());
+    assert!(
+        app.commit_sign_error
+            .as_deref()
+            .unwrap()
+            .contains("no secret key")
+    );
+    assert_eq!(repo.head().unwrap().target(), Some(oids[3]));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn reword_warns_about_published_commits_and_dirty_trees() {
+    let (dir, repo) = scratch_repo("reword-published");
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    commit_all(&repo, "base");
+    let oids = rebase_history(&repo);
+    repo.reference("refs/remotes/origin/main", oids[1], true, "test")
+        .unwrap();
+
+    let mut app = app_for(&dir, "a.txt");
+    select_graph_oid(&mut app, oids[0]);
+    app.open_reword_dialog();
+    assert_eq!(
+        app.reword_confirm,
+        Some((oids[0], "origin/main".to_string()))
+    );
+    assert!(!app.show_commit_dialog);
+    app.confirm_reword();
+    assert!(app.show_commit_dialog);
+    assert_eq!(app.commit_amend_pushed.as_deref(), Some("origin/main"));
+    app.close_commit_dialog();
+
+    // Not yet pushed: no warning.
+    select_graph_oid(&mut app, oids[2]);
+    app.open_reword_dialog();
+    assert!(app.reword_confirm.is_none());
+    assert!(app.commit_amend_pushed.is_none());
+    app.close_commit_dialog();
+
+    fs::write(dir.join("one.txt"), "dirty\n").unwrap();
+    app.open_reword_dialog();
+    assert!(app.operation_error.take().is_some());
+    assert!(!app.show_commit_dialog);
+    let _ = fs::remove_dir_all(&dir);
+}
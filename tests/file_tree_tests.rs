@@ -131,6 +131,7 @@ fn file_tree_populate_empty_paths_clears_items() {
 }
 
 #[test]
+#[allow(clippy::len_zero)]
 fn file_tree_update_items_respects_collapsed() {
     let mut tree = FileTree::new(PathBuf::from("."));
     tree.populate_from_paths(vec![
@@ -139,3 +140,37 @@ fn file_tree_update_items_respects_collapsed() {
     ]);
     assert!(tree.items.len() >= 1);
 }
+
+#[test]
+fn file_tree_toggle_mark_marks_and_unmarks_selected_row() {
+    let mut tree = FileTree::new(PathBuf::from("."));
+    tree.populate_from_paths(vec![PathBuf::from("a.rs"), PathBuf::from("b.rs")]);
+    tree.state.select(Some(1));
+    tree.toggle_mark();
+    assert!(tree.is_marked(&PathBuf::from("./b.rs")));
+    assert!(!tree.is_marked(&PathBuf::from("./a.rs")));
+    tree.toggle_mark();
+    assert!(tree.marked.is_empty());
+}
+
+#[test]
+fn file_tree_populate_drops_marks_for_vanished_paths() {
+    let mut tree = FileTree::new(PathBuf::from("."));
+    tree.populate_from_paths(vec![PathBuf::from("a.rs"), PathBuf::from("b.rs")]);
+    tree.state.select(Some(0));
+    tree.toggle_mark();
+    tree.next();
+    tree.toggle_mark();
+    tree.populate_from_paths(vec![PathBuf::from("b.rs")]);
+    assert_eq!(tree.marked.len(), 1);
+    assert!(tree.is_marked(&PathBuf::from("./b.rs")));
+}
+
+#[test]
+fn file_tree_clear_marks() {
+    let mut tree = FileTree::new(PathBuf::from("."));
+    tree.populate_from_paths(vec![PathBuf::from("a.rs")]);
+    tree.toggle_mark();
+    tree.clear_marks();
+    assert!(tree.marked.is_empty());
+}
@@ -0,0 +1,148 @@
+use fastgit::graph::{GraphCell, GraphLayout, HistoryWalk};
+use git2::Oid;
+
+fn oid(n: u8) -> Oid {
+    Oid::from_bytes(&[n; 20]).unwrap()
+}
+
+fn text(row: &[GraphCell]) -> String {
+    row.iter().map(|c| c.ch).collect()
+}
+
+fn layout(commits: &[(u8, &[u8])]) -> Vec<String> {
+    let mut layout = GraphLayout::new();
+    commits
+        .iter()
+        .map(|(id, parents)| {
+            let parents: Vec<Oid> = parents.iter().map(|p| oid(*p)).collect();
+            text(&layout.push(oid(*id), &parents))
+        })
+        .collect()
+}
+
+#[test]
+fn linear_history_is_one_lane() {
+    assert_eq!(
+        layout(&[(3, &[2]), (2, &[1]), (1, &[])]),
+        vec!["●", "●", "●"]
+    );
+}
+
+#[test]
+fn merge_forks_a_lane_and_joins_it_back() {
+    // 4 merges 3 into 2; both branch off 1.
+    assert_eq!(
+        layout(&[(4, &[2, 3]), (3, &[1]), (2, &[1]), (1, &[])]),
+        vec!["●─╮", "│ ●", "● │", "●─╯"]
+    );
+}
+
+#[test]
+fn two_branch_tips_share_a_parent() {
+    assert_eq!(
+        layout(&[(3, &[1]), (2, &[1]), (1, &[])]),
+        vec!["●", "│ ●", "●─╯"]
+    );
+}
+
+#[test]
+fn join_crosses_a_passing_lane() {
+    // 5 merges 2 while a third branch (4 -> 1) keeps its own lane.
+    assert_eq!(
+        layout(&[
+            (6, &[5]),
+            (4, &[1]),
+            (5, &[3, 2]),
+            (2, &[1]),
+            (3, &[1]),
+            (1, &[]),
+        ]),
+        vec!["●", "│ ●", "●─┼─╮", "│ │ ●", "● │ │", "●─┴─╯"]
+    );
+}
+
+#[test]
+fn lanes_keep_their_color() {
+    let mut layout = GraphLayout::new();
+    let first = layout.push(oid(4), &[oid(2), oid(3)]);
+    let second = layout.push(oid(3), &[oid(1)]);
+    assert_ne!(first[0].color, first[2].color);
+    assert_eq!(first[2].color, second[2].color);
+    assert_eq!(first[1].color, first[2].color);
+}
+
+#[test]
+fn octopus_merge_opens_several_lanes() {
+    assert_eq!(
+        layout(&[(5, &[4, 3, 2]), (4, &[1]), (3, &[1]), (2, &[1]), (1, &[])]),
+        vec!["●─┬─╮", "● │ │", "│ ● │", "│ │ ●", "●─┴─╯"]
+    );
+}
+
+#[test]
+fn history_walk_keeps_children_first_on_equal_timestamps() {
+    let dir = std::env::temp_dir().join(format!("fastgit-graph-walk-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = git2::Repository::init(&dir).unwrap();
+    let sig = git2::Signature::new("t", "t@example.com", &git2::Time::new(1000, 0)).unwrap();
+    let tree = repo
+        .find_tree(repo.index().unwrap().write_tree().unwrap())
+        .unwrap();
+    let commit = |message: &str, parents: &[Oid]| {
+        let parents: Vec<git2::Commit> = parents
+            .iter()
+            .map(|p| repo.find_commit(*p).unwrap())
+            .collect();
+        let refs: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(None, &sig, &sig, message, &tree, &refs)
+            .unwrap()
+    };
+    let base = commit("base", &[]);
+    let main = commit("main", &[base]);
+    let side = commit("side", &[base]);
+    let merge = commit("merge", &[main, side]);
+
+    let mut walk = HistoryWalk::new(&repo, &[merge]);
+    let mut order = vec![];
+    while let Some(c) = walk.next_commit(&repo) {
+        order.push(c.id());
+    }
+    assert!(walk.is_done());
+    assert_eq!(order, vec![merge, main, side, base]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn history_walk_keeps_children_first_despite_clock_skew() {
+    let dir = std::env::temp_dir().join(format!("fastgit-graph-skew-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = git2::Repository::init(&dir).unwrap();
+    let tree = repo
+        .find_tree(repo.index().unwrap().write_tree().unwrap())
+        .unwrap();
+    let commit = |message: &str, time: i64, parents: &[Oid]| {
+        let sig = git2::Signature::new("t", "t@example.com", &git2::Time::new(time, 0)).unwrap();
+        let parents: Vec<git2::Commit> = parents
+            .iter()
+            .map(|p| repo.find_commit(*p).unwrap())
+            .collect();
+        let refs: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(None, &sig, &sig, message, &tree, &refs)
+            .unwrap()
+    };
+    let base = commit("base", 1000, &[]);
+    // Committed on a machine whose clock was ahead.
+    let ahead = commit("ahead", 5000, &[base]);
+    let behind = commit("behind", 2000, &[ahead]);
+    let side = commit("side", 3000, &[base]);
+    let merge = commit("merge", 6000, &[behind, ahead, side]);
+
+    let mut walk = HistoryWalk::new(&repo, &[merge]);
+    let mut order = vec![];
+    while let Some(c) = walk.next_commit(&repo) {
+        order.push(c.id());
+    }
+    assert!(walk.is_done());
+    assert_eq!(order, vec![merge, side, behind, ahead, base]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
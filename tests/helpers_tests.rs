@@ -119,3 +119,23 @@ fn dialog_type_partial_eq() {
     assert_ne!(DialogType::Warning, DialogType::Error);
     assert_ne!(DialogType::Info, DialogType::Success);
 }
+
+#[test]
+fn format_age_buckets() {
+    let h = Helper;
+    assert_eq!(h.format_age(5), "just now");
+    assert_eq!(h.format_age(-5), "just now");
+    assert_eq!(h.format_age(120), "2m ago");
+    assert_eq!(h.format_age(3 * 3600), "3h ago");
+    assert_eq!(h.format_age(2 * 86400), "2d ago");
+    assert_eq!(h.format_age(90 * 86400), "3mo ago");
+    assert_eq!(h.format_age(800 * 86400), "2y ago");
+}
+
+#[test]
+fn format_date_uses_the_commit_timezone() {
+    let h = Helper;
+    assert_eq!(h.format_date(0, 0), "1970-01-01 00:00 +0000");
+    assert_eq!(h.format_date(1_792_245_780, 120), "2026-10-17 16:03 +0200");
+    assert_eq!(h.format_date(951_782_400, -330), "2000-02-28 18:30 -0530");
+}
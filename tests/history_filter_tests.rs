@@ -0,0 +1,82 @@
+use fastgit::history_filter::{HistoryFilter, MessageMatch, Pickaxe};
+
+#[test]
+fn blank_query_is_no_filter() {
+    assert!(HistoryFilter::parse("").unwrap().is_none());
+    assert!(HistoryFilter::parse("   ").unwrap().is_none());
+}
+
+#[test]
+fn parses_keys_and_quoted_values() {
+    let filter = HistoryFilter::parse(r#"author:"Jane Doe" path:src/ fix crash"#)
+        .unwrap()
+        .unwrap();
+    assert_eq!(filter.author.as_deref(), Some("jane doe"));
+    assert_eq!(filter.path.as_deref(), Some("src"));
+    match filter.message {
+        Some(MessageMatch::Text(text)) => assert_eq!(text, "fix crash"),
+        other => panic!("unexpected message match {:?}", other),
+    }
+}
+
+#[test]
+fn dates_cover_whole_days() {
+    let filter = HistoryFilter::parse("since:2026-10-17 until:2026-10-17")
+        .unwrap()
+        .unwrap();
+    assert_eq!(filter.since, Some(1_792_195_200));
+    assert_eq!(filter.until, Some(1_792_195_200 + 86_399));
+    assert_eq!(
+        HistoryFilter::parse("since:2000-03-01")
+            .unwrap()
+            .unwrap()
+            .since,
+        Some(951_868_800)
+    );
+}
+
+#[test]
+fn regex_message_match() {
+    let filter = HistoryFilter::parse(r"re:^feat(\(ui\))?:")
+        .unwrap()
+        .unwrap();
+    match filter.message {
+        Some(MessageMatch::Regex(re)) => {
+            assert!(re.is_match("feat(ui): graph"));
+            assert!(!re.is_match("fix: feat"));
+        }
+        other => panic!("unexpected message match {:?}", other),
+    }
+}
+
+#[test]
+fn invalid_terms_are_errors() {
+    assert!(HistoryFilter::parse("since:yesterday").is_err());
+    assert!(HistoryFilter::parse("until:2026-13-01").is_err());
+    assert!(HistoryFilter::parse("re:(").is_err());
+    assert!(HistoryFilter::parse("re:x extra words").is_err());
+}
+
+#[test]
+fn pickaxe_terms() {
+    let filter = HistoryFilter::parse(r#"S:"fn main""#).unwrap().unwrap();
+    match &filter.pickaxe {
+        Some(Pickaxe::Count(needle)) => assert_eq!(needle, "fn main"),
+        other => panic!("unexpected pickaxe {:?}", other),
+    }
+    assert!(filter.pickaxe_hit("pub fn main() {"));
+    assert!(!filter.pickaxe_hit("fn_main()"));
+    assert!(filter.message.is_none());
+
+    let filter = HistoryFilter::parse(r"G:^\s*todo").unwrap().unwrap();
+    assert!(filter.pickaxe_hit("    todo: later"));
+    assert!(!filter.pickaxe_hit("// todo"));
+    assert!(HistoryFilter::parse("G:[").is_err());
+}
+
+#[test]
+fn follow_term_starts_at_the_given_path() {
+    let filter = HistoryFilter::parse("follow:src/app.rs/").unwrap().unwrap();
+    assert_eq!(filter.follow.as_deref(), Some("src/app.rs"));
+    assert!(filter.follow_paths.is_empty());
+}
@@ -0,0 +1,153 @@
+use fastgit::hooks::{HookEvent, Hooks, cleanup_message};
+use git2::Repository;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn scratch_repo(name: &str) -> (PathBuf, Repository) {
+    let dir = std::env::temp_dir().join(format!("fastgit-hooks-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let repo = Repository::init(&dir).unwrap();
+    (dir, repo)
+}
+
+#[cfg(unix)]
+fn write_hook(dir: &Path, name: &str, body: &str) {
+    use std::os::unix::fs::PermissionsExt;
+    fs::create_dir_all(dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn cleanup_message_strips_comments_and_blank_edges() {
+    let message = "\nsummary  \n\nbody\n# comment\n\n";
+    assert_eq!(cleanup_message(message), "summary\n\nbody");
+}
+
+#[test]
+fn hooks_default_to_git_dir() {
+    let (dir, repo) = scratch_repo("default");
+    let hooks = Hooks::for_repo(&repo);
+    assert_eq!(hooks.dir, repo.path().join("hooks"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn hooks_path_is_relative_to_workdir() {
+    let (dir, repo) = scratch_repo("hookspath");
+    repo.config()
+        .unwrap()
+        .set_str("core.hooksPath", "githooks")
+        .unwrap();
+    let hooks = Hooks::for_repo(&repo);
+    assert_eq!(
+        hooks.dir.canonicalize().unwrap_or(hooks.dir.clone()),
+        dir.canonicalize().unwrap().join("githooks")
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn missing_hook_is_not_run() {
+    let (dir, repo) = scratch_repo("missing");
+    let hooks = Hooks::for_repo(&repo);
+    assert!(hooks.find("pre-commit").is_none());
+    let ran = hooks.run("pre-commit", &[], None, &mut |_| {}).unwrap();
+    assert!(!ran);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn non_executable_hook_is_ignored() {
+    let (dir, repo) = scratch_repo("noexec");
+    let hooks_dir = repo.path().join("hooks");
+    fs::create_dir_all(&hooks_dir).unwrap();
+    fs::write(hooks_dir.join("pre-commit"), "#!/bin/sh\nexit 1\n").unwrap();
+    assert!(Hooks::for_repo(&repo).find("pre-commit").is_none());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn run_streams_stdout_and_stderr() {
+    let (dir, repo) = scratch_repo("stream");
+    write_hook(
+        &repo.path().join("hooks"),
+        "pre-push",
+        "echo out\necho err >&2\ncat",
+    );
+    let hooks = Hooks::for_repo(&repo);
+    let mut lines = vec![];
+    let ran = hooks
+        .run("pre-push", &[], Some("refs line\n"), &mut |l| lines.push(l))
+        .unwrap();
+    assert!(ran);
+    assert!(lines.contains(&"out".to_string()));
+    assert!(lines.contains(&"err".to_string()));
+    assert!(lines.contains(&"refs line".to_string()));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn failing_hook_is_an_error() {
+    let (dir, repo) = scratch_repo("fail");
+    write_hook(
+        &repo.path().join("hooks"),
+        "pre-commit",
+        "echo lint failed\nexit 3",
+    );
+    let hooks = Hooks::for_repo(&repo);
+    let err = hooks.run("pre-commit", &[], None, &mut |_| {}).unwrap_err();
+    assert!(err.message().contains("exited with 3"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn commit_hooks_can_rewrite_the_message() {
+    let (dir, repo) = scratch_repo("commit-msg");
+    let hooks_dir = repo.path().join("hooks");
+    write_hook(&hooks_dir, "prepare-commit-msg", "echo \"# $2\" >> \"$1\"");
+    write_hook(
+        &hooks_dir,
+        "commit-msg",
+        "echo 'Signed-off-by: dev' >> \"$1\"",
+    );
+    let hooks = Hooks::for_repo(&repo);
+
+    let mut events = vec![];
+    let message = hooks
+        .run_commit_hooks("summary", false, &mut |e| events.push(e))
+        .unwrap();
+    assert_eq!(message, "summary\nSigned-off-by: dev");
+    assert!(events.contains(&HookEvent::Started("prepare-commit-msg".to_string())));
+    assert!(events.contains(&HookEvent::Started("commit-msg".to_string())));
+    assert!(!events.contains(&HookEvent::Started("pre-commit".to_string())));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn prepare_commit_msg_sees_the_amend_source() {
+    let (dir, repo) = scratch_repo("amend-source");
+    write_hook(
+        &repo.path().join("hooks"),
+        "prepare-commit-msg",
+        "echo \"Source: $2 $3\" >> \"$1\"",
+    );
+    let hooks = Hooks::for_repo(&repo);
+
+    let message = hooks
+        .run_commit_hooks("summary", false, &mut |_| {})
+        .unwrap();
+    assert_eq!(message, "summary\nSource: message");
+    let message = hooks
+        .run_commit_hooks("summary", true, &mut |_| {})
+        .unwrap();
+    assert_eq!(message, "summary\nSource: commit HEAD");
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,94 @@
+use fastgit::patch::{HunkLines, partial_patch};
+use git2::FileMode;
+
+fn hunk(old_start: u32, new_start: u32, lines: &[(char, &str)], selected: &[bool]) -> HunkLines {
+    HunkLines {
+        old_start,
+        new_start,
+        lines: lines
+            .iter()
+            .map(|(o, c)| (*o, format!("{}\n", c)))
+            .collect(),
+        selected: selected.to_vec(),
+    }
+}
+
+#[test]
+fn partial_patch_nothing_selected_is_none() {
+    let h = hunk(1, 1, &[(' ', "a"), ('+', "b")], &[false, false]);
+    assert!(partial_patch("f.txt", None, &[h], false).is_none());
+}
+
+#[test]
+fn partial_patch_keeps_selected_add_only() {
+    let h = hunk(
+        1,
+        1,
+        &[(' ', "a"), ('+', "b"), ('+', "c"), (' ', "d")],
+        &[false, true, false, false],
+    );
+    let patch = partial_patch("f.txt", None, &[h], false).unwrap();
+    assert!(patch.starts_with("diff --git a/f.txt b/f.txt\n--- a/f.txt\n+++ b/f.txt\n"));
+    assert!(patch.contains("@@ -1,2 +1,3 @@\n a\n+b\n d\n"));
+    assert!(!patch.contains("+c"));
+}
+
+#[test]
+fn partial_patch_unselected_delete_becomes_context() {
+    let h = hunk(
+        3,
+        3,
+        &[('-', "x"), ('-', "y"), ('+', "z")],
+        &[true, false, true],
+    );
+    let patch = partial_patch("f.txt", None, &[h], false).unwrap();
+    assert!(patch.contains("@@ -3,2 +3,2 @@\n-x\n y\n+z\n"));
+}
+
+#[test]
+fn partial_patch_reverse_swaps_sides() {
+    // Staged hunk HEAD -> index: `+b` is in the index, `-a` only in HEAD.
+    let h = hunk(1, 1, &[('-', "a"), ('+', "b")], &[false, true]);
+    let patch = partial_patch("f.txt", None, &[h], true).unwrap();
+    assert!(patch.contains("@@ -1,1 +0,0 @@\n-b\n"));
+}
+
+#[test]
+fn partial_patch_new_file_header() {
+    let h = hunk(0, 1, &[('+', "a"), ('+', "b")], &[true, false]);
+    let patch = partial_patch("new.txt", Some(FileMode::Blob), &[h], false).unwrap();
+    assert!(patch.contains("new file mode 100644\n--- /dev/null\n+++ b/new.txt\n"));
+    assert!(patch.contains("@@ -0,0 +1,1 @@\n+a\n"));
+}
+
+#[test]
+fn partial_patch_new_executable_keeps_its_mode() {
+    let h = hunk(0, 1, &[('+', "#!/bin/sh")], &[true]);
+    let patch = partial_patch("run.sh", Some(FileMode::BlobExecutable), &[h], false).unwrap();
+    assert!(patch.contains("new file mode 100755\n--- /dev/null\n"));
+}
+
+#[test]
+fn partial_patch_offsets_later_hunks() {
+    let first = hunk(1, 1, &[(' ', "a"), ('+', "b")], &[false, true]);
+    let second = hunk(10, 11, &[(' ', "j"), ('+', "k")], &[false, true]);
+    let patch = partial_patch("f.txt", None, &[first, second], false).unwrap();
+    assert!(patch.contains("@@ -1,1 +1,2 @@"));
+    assert!(patch.contains("@@ -10,1 +11,2 @@"));
+}
+
+#[test]
+fn partial_patch_keeps_no_newline_marker() {
+    let h = HunkLines {
+        old_start: 1,
+        new_start: 1,
+        lines: vec![
+            ('-', "a".to_string()),
+            ('+', "b".to_string()),
+            ('>', "\n\\ No newline at end of file\n".to_string()),
+        ],
+        selected: vec![true, true, false],
+    };
+    let patch = partial_patch("f.txt", None, &[h], false).unwrap();
+    assert!(patch.ends_with("-a\n+b\n\\ No newline at end of file\n"));
+}
@@ -0,0 +1,146 @@
+use fastgit::rebase::{RebaseAction, RebasePlan, RebaseStep, fixup_target};
+use git2::Oid;
+
+fn plan(actions: &[RebaseAction]) -> RebasePlan {
+    RebasePlan {
+        upstream: Oid::from_str("1000000000000000000000000000000000000000").unwrap(),
+        onto: None,
+        steps: actions
+            .iter()
+            .enumerate()
+            .map(|(i, action)| RebaseStep {
+                action: *action,
+                oid: Oid::from_str(&format!("{:040x}", i + 2)).unwrap(),
+                summary: format!("commit {}", i),
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn actions_are_bound_to_their_initials() {
+    for action in [
+        RebaseAction::Pick,
+        RebaseAction::Reword,
+        RebaseAction::Edit,
+        RebaseAction::Squash,
+        RebaseAction::Fixup,
+        RebaseAction::Drop,
+    ] {
+        let key = action.name().chars().next().unwrap();
+        assert_eq!(RebaseAction::from_key(key), Some(action));
+    }
+    assert_eq!(RebaseAction::from_key('x'), None);
+}
+
+#[test]
+fn steps_move_within_bounds() {
+    let mut plan = plan(&[RebaseAction::Pick; 3]);
+    let first = plan.steps[0].oid;
+
+    assert_eq!(plan.move_step(0, true), 0);
+    assert_eq!(plan.move_step(0, false), 1);
+    assert_eq!(plan.steps[1].oid, first);
+    assert_eq!(plan.move_step(1, false), 2);
+    assert_eq!(plan.move_step(2, false), 2);
+    assert_eq!(plan.steps[2].oid, first);
+}
+
+#[test]
+fn squash_needs_an_earlier_commit() {
+    assert!(
+        plan(&[RebaseAction::Fixup, RebaseAction::Pick])
+            .validate()
+            .is_err()
+    );
+    assert!(
+        plan(&[RebaseAction::Drop, RebaseAction::Squash])
+            .validate()
+            .is_err()
+    );
+    assert!(
+        plan(&[RebaseAction::Reword, RebaseAction::Squash])
+            .validate()
+            .is_ok()
+    );
+    assert!(
+        plan(&[RebaseAction::Drop, RebaseAction::Drop])
+            .validate()
+            .is_ok()
+    );
+}
+
+#[test]
+fn dropped_commits_have_no_action() {
+    let plan = plan(&[RebaseAction::Pick, RebaseAction::Drop, RebaseAction::Fixup]);
+    let actions = plan.actions();
+    assert_eq!(actions.len(), 2);
+    assert_eq!(actions[&plan.steps[2].oid], RebaseAction::Fixup);
+    assert!(!actions.contains_key(&plan.steps[1].oid));
+}
+
+#[test]
+fn fixup_prefixes_name_the_target() {
+    assert_eq!(
+        fixup_target("fixup! add parser"),
+        Some((RebaseAction::Fixup, "add parser"))
+    );
+    assert_eq!(
+        fixup_target("squash! fixup! add parser"),
+        Some((RebaseAction::Squash, "add parser"))
+    );
+    assert_eq!(fixup_target("fixup add parser"), None);
+}
+
+#[test]
+fn autosquash_moves_fixups_behind_their_targets() {
+    let mut plan = plan(&[RebaseAction::Pick; 5]);
+    let names = [
+        "parser",
+        "lexer",
+        "fixup! parser",
+        "squash! lexer",
+        "fixup! parser",
+    ];
+    for (step, name) in plan.steps.iter_mut().zip(names) {
+        step.summary = name.to_string();
+    }
+    let ids: Vec<Oid> = plan.steps.iter().map(|s| s.oid).collect();
+
+    assert_eq!(plan.autosquash(), 3);
+    let order: Vec<Oid> = plan.steps.iter().map(|s| s.oid).collect();
+    assert_eq!(order, vec![ids[0], ids[2], ids[4], ids[1], ids[3]]);
+    let actions: Vec<RebaseAction> = plan.steps.iter().map(|s| s.action).collect();
+    assert_eq!(
+        actions,
+        vec![
+            RebaseAction::Pick,
+            RebaseAction::Fixup,
+            RebaseAction::Fixup,
+            RebaseAction::Pick,
+            RebaseAction::Squash,
+        ]
+    );
+}
+
+#[test]
+fn autosquash_falls_back_to_a_summary_the_subject_starts() {
+    let mut plan = plan(&[RebaseAction::Pick; 5]);
+    let names = [
+        "Add parser for config files",
+        "Add lexer tokens",
+        "Add lexer",
+        "fixup! Add parser",
+        "fixup! Add lexer",
+    ];
+    for (step, name) in plan.steps.iter_mut().zip(names) {
+        step.summary = name.to_string();
+    }
+    let ids: Vec<Oid> = plan.steps.iter().map(|s| s.oid).collect();
+
+    // The prefix finds the parser; the exact summary beats an earlier
+    // prefix match for the lexer.
+    assert_eq!(plan.autosquash(), 2);
+    let order: Vec<Oid> = plan.steps.iter().map(|s| s.oid).collect();
+    assert_eq!(order, vec![ids[0], ids[3], ids[1], ids[2], ids[4]]);
+}
@@ -0,0 +1,116 @@
+use fastgit::signing::{SignConfig, SignFormat};
+use git2::Config;
+use std::fs;
+
+fn scratch_config(name: &str, entries: &[(&str, &str)]) -> Config {
+    let path =
+        std::env::temp_dir().join(format!("fastgit-signcfg-{}-{}", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    fs::write(&path, "").unwrap();
+    let mut cfg = Config::open(&path).unwrap();
+    for (key, value) in entries {
+        cfg.set_str(key, value).unwrap();
+    }
+    cfg
+}
+
+#[test]
+fn sign_config_off_without_gpgsign() {
+    let cfg = scratch_config("off", &[("user.signingkey", "ABC")]);
+    assert_eq!(SignConfig::from_config(&cfg).unwrap(), None);
+}
+
+#[test]
+fn sign_config_defaults_to_openpgp_with_gpg() {
+    let cfg = scratch_config("pgp", &[("commit.gpgsign", "true")]);
+    let sign = SignConfig::from_config(&cfg).unwrap().unwrap();
+    assert_eq!(sign.format, SignFormat::OpenPgp);
+    assert_eq!(sign.program, "gpg");
+    assert_eq!(sign.key, None);
+}
+
+#[test]
+fn sign_config_reads_ssh_program_and_key() {
+    let cfg = scratch_config(
+        "ssh",
+        &[
+            ("commit.gpgsign", "true"),
+            ("gpg.format", "ssh"),
+            ("gpg.ssh.program", "/opt/ssh-keygen"),
+            ("user.signingkey", "~/.ssh/id_ed25519.pub"),
+        ],
+    );
+    let sign = SignConfig::from_config(&cfg).unwrap().unwrap();
+    assert_eq!(sign.format, SignFormat::Ssh);
+    assert_eq!(sign.program, "/opt/ssh-keygen");
+    assert_eq!(sign.key.as_deref(), Some("~/.ssh/id_ed25519.pub"));
+}
+
+#[test]
+fn sign_config_gpg_program_fallback() {
+    let cfg = scratch_config(
+        "program",
+        &[("commit.gpgsign", "true"), ("gpg.program", "gpg2")],
+    );
+    let sign = SignConfig::from_config(&cfg).unwrap().unwrap();
+    assert_eq!(sign.program, "gpg2");
+}
+
+#[test]
+fn sign_config_rejects_unknown_format() {
+    let cfg = scratch_config(
+        "unknown",
+        &[("commit.gpgsign", "true"), ("gpg.format", "pgp2")],
+    );
+    assert!(SignConfig::from_config(&cfg).is_err());
+}
+
+#[test]
+fn ssh_signing_requires_a_key() {
+    let sign = SignConfig {
+        format: SignFormat::Ssh,
+        program: "ssh-keygen".to_string(),
+        key: None,
+    };
+    assert_eq!(
+        sign.sign("tree 0\n", "a <a@b>").unwrap_err().to_string(),
+        "gpg.format is ssh but user.signingkey is not set"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn ssh_literal_key_file_is_unpredictable_and_removed() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = std::env::temp_dir().join(format!("fastgit-sshsign-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let program = dir.join("fake-ssh-keygen");
+    let log = dir.join("args");
+    fs::write(
+        &program,
+        format!(
+            "#!/bin/sh\ncat > /dev/null\necho \"$@\" > {}\nexit 1\n",
+            log.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let sign = SignConfig {
+        format: SignFormat::Ssh,
+        program: program.display().to_string(),
+        key: Some("ssh-ed25519 AAAAtest".to_string()),
+    };
+    assert!(sign.sign("tree 0\n", "a <a@b>").is_err());
+
+    let args = fs::read_to_string(&log).unwrap();
+    let key_file = args.split_whitespace().last().unwrap();
+    assert!(
+        !key_file.ends_with(&format!("-{}.pub", std::process::id())),
+        "{}",
+        key_file
+    );
+    assert!(!std::path::Path::new(key_file).exists());
+    let _ = fs::remove_dir_all(&dir);
+}